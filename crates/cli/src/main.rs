@@ -1,54 +1,1308 @@
+mod migrations;
+mod project;
+mod sources;
+mod status;
+mod watch;
+
 use anyhow::{Context, Result};
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{ColorChoice, Parser, Subcommand, ValueEnum};
+use cores::ast::Schema;
+use cores::ddl::Dialect;
+use cores::diagnostic::{Diagnostic, LocatedDiagnostic, SYNTAX_ERROR};
+use cores::merge::Merged;
+use cores::sqlite;
+use project::{PROJECT_FILE, Project};
+use rusqlite::{Connection, OpenFlags};
+use status::{Status, fail};
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::{env, fs};
 
 #[derive(Parser, Debug)]
+#[command(
+    name = "mecha",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_help = "Every command exits with 0 when it succeeds, 2 when it fails, 3 when a source \
+doesn't parse, 4 when it isn't a valid schema and 5 when a file can't be read or written"
+)]
 struct Args {
-    #[arg(short, long)]
-    source: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    #[arg(short, long)]
+    /// Same as `mecha build`, which is the way to go
+    #[command(flatten)]
+    build: BuildArgs,
+
+    /// Only print errors, warnings and status lines are left out
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// When to color diagnostics
+    #[arg(long, value_enum, global = true, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+}
+
+#[derive(clap::Args, Debug)]
+struct BuildArgs {
+    /// The schemas to compile, as files, directories of .mecha files or patterns such as
+    /// `schemas/**/*.mecha`, `-` reads the standard input. Every target of the mecha.toml
+    /// project is built without any
+    #[arg(short, long, num_args = 1..)]
+    source: Vec<PathBuf>,
+
+    /// The name the standard input goes by in diagnostics and the output's name
+    #[arg(long, value_name = "NAME", requires = "source")]
+    stdin_filename: Option<String>,
+
+    /// Compile the sources into one schema named `schema`, rather than each on its own, so
+    /// that they may reference each other's tables
+    #[arg(short, long, requires = "source")]
+    merge: bool,
+
+    /// The directory the output is written to, the working directory by default, `-` writes
+    /// it to the standard output
+    #[arg(short, long, requires = "source")]
     out: Option<PathBuf>,
 
+    /// The dialect to generate DDL for, the schema is compiled to JSON without one
+    #[arg(short, long, value_enum, requires = "source")]
+    target: Option<Dialect>,
+
+    /// Build again whenever a source changes, until interrupted
     #[arg(short, long)]
-    target: Option<String>,
+    watch: bool,
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+#[derive(clap::Args, Debug)]
+struct CheckArgs {
+    /// The schemas to check, as files, directories of .mecha files or patterns such as
+    /// `schemas/**/*.mecha`, `-` reads the standard input. Every entry of the mecha.toml
+    /// project is checked without any
+    sources: Vec<PathBuf>,
+
+    /// The name the standard input goes by in diagnostics
+    #[arg(long, value_name = "NAME", requires = "sources")]
+    stdin_filename: Option<String>,
+
+    /// Check the sources as one schema, rather than each on its own, so that they may
+    /// reference each other's tables
+    #[arg(short, long, requires = "sources")]
+    merge: bool,
+
+    /// Override the severity of a lint rule, e.g. `--rule L001=error` or
+    /// `--rule naming-convention=off`
+    #[arg(long = "rule", value_name = "RULE=SEVERITY")]
+    rules: Vec<String>,
+
+    /// How diagnostics are printed, json and sarif go to the standard output
+    #[arg(short, long, value_enum, default_value_t = DiagnosticFormat::Human)]
+    format: DiagnosticFormat,
+
+    /// Check again whenever a schema changes, until interrupted
+    #[arg(short, long)]
+    watch: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Parse, check and lint a schema without generating anything, exits with 1 when a
+    /// lint rule set to error is broken
+    Check(CheckArgs),
+
+    /// Compile a schema into the DDL of a dialect, or into JSON without one. Without a
+    /// source, every target of the mecha.toml project is built
+    Build(BuildArgs),
+
+    /// Compare two schemas and report their semantic differences
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+
+        #[arg(short, long, value_enum, default_value_t = Format::Human)]
+        format: Format,
+    },
+
+    /// Report the changes from an older schema that may break running services, exits
+    /// with 1 when some of them are errors
+    CheckCompat {
+        schema: PathBuf,
+
+        /// The previous schema, as a .mecha source or its compiled .json
+        #[arg(long)]
+        against: PathBuf,
+
+        /// Override the severity of a rule, e.g. `--rule B008=error` or `--rule column-removed=off`
+        #[arg(long = "rule", value_name = "RULE=SEVERITY")]
+        rules: Vec<String>,
+    },
+
+    /// Rewrite schema files in the canonical layout, comments are kept
+    Fmt {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Only report the files that aren't formatted, exits with 1 when there are some
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Compare a live SQLite database with the schema it was deployed from. Changes read
+    /// from the schema to the database, exits with 1 when they drifted apart
+    Drift {
+        /// The SQLite database file
+        #[arg(long)]
+        sqlite: PathBuf,
+
+        schema: PathBuf,
 
-    let source_path = args.source;
+        #[arg(short, long, value_enum, default_value_t = Format::Human)]
+        format: Format,
+    },
 
-    if !source_path.exists() {
-        anyhow::bail!("source file doesn't exist: {}", source_path.display());
+    /// Describe a diagnostic code with an example and its fix, or list every code
+    Explain {
+        /// Such as M0003
+        code: Option<String>,
+    },
+
+    /// Translate an existing database schema into .mecha source
+    Import {
+        input: PathBuf,
+
+        #[arg(long, value_enum)]
+        from: ImportSource,
+
+        /// The dialect the SQL is written in, a SQLite database is always read as sqlite
+        #[arg(short, long, value_enum, default_value = "postgres")]
+        dialect: Dialect,
+
+        /// Where the .mecha source is written, standard output by default
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Manage SQL migrations between schema versions
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum MigrateCommand {
+    /// Write the next migration of the directory, going from its snapshot to the schema
+    Generate {
+        schema: PathBuf,
+
+        /// Required for the first migration, later ones follow the snapshot
+        #[arg(short, long, value_enum)]
+        target: Option<Dialect>,
+
+        #[arg(short, long, default_value = "migrations")]
+        dir: PathBuf,
+
+        #[arg(short, long, default_value = "migration")]
+        name: String,
+    },
+
+    /// Replay the migrations in SQLite and compare the result with the schema, exits with 1
+    /// when they don't reproduce it. Only sqlite migrations can be replayed, for other
+    /// dialects it stops once the snapshot is found up to date with the schema
+    Verify {
+        schema: PathBuf,
+
+        #[arg(short, long, default_value = "migrations")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ImportSource {
+    /// SQL DDL, such as the output of `pg_dump --schema-only`
+    Sql,
+    /// A SQLite database file
+    Sqlite,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Human,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum DiagnosticFormat {
+    Human,
+    Json,
+    /// SARIF 2.1.0, for code scanning services
+    Sarif,
+}
+
+/// Where diagnostics and status lines go, the standard output is left to what a command
+/// produces so that it can be piped
+struct Output {
+    quiet: bool,
+    color: bool,
+}
+
+impl Output {
+    fn new(quiet: bool, color: ColorChoice) -> Self {
+        let color = match color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none()
+            }
+        };
+        Output { quiet, color }
     }
 
+    /// Prints diagnostics against their source, quiet keeps the errors only
+    fn diagnostics(&self, src: &str, filename: &str, diagnostics: &[Diagnostic]) {
+        let shown: Vec<Diagnostic> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.is_error() || !self.quiet)
+            .cloned()
+            .collect();
+        eprint!(
+            "{}",
+            cores::codegen::render(src, filename, &shown, self.color)
+        );
+    }
+
+    fn warning(&self, message: impl Display) {
+        if !self.quiet {
+            eprintln!("warning: {message}");
+        }
+    }
+
+    /// Tells what a command did, when it has nothing else to show for it
+    fn status(&self, message: impl Display) {
+        if !self.quiet {
+            eprintln!("{message}");
+        }
+    }
+}
+
+/// A checked schema along with the source it comes from, to report diagnostics against
+struct SchemaFile {
+    schema: Schema,
+    src: String,
+    filename: String,
+}
+
+/// Parses and checks a source, the schema comes with its warnings. The errors come with
+/// the status of the step that found them.
+fn checked(
+    src: &str,
+    filename: &str,
+) -> Result<(Schema, Vec<Diagnostic>), (Status, Vec<Diagnostic>)> {
+    let mut schema = cores::parser::parse(filename, src).map_err(|errs| (Status::Parse, errs))?;
+    schema.check().map_err(|errs| (Status::Semantic, errs))?;
+    let warnings = schema.warnings();
+    Ok((schema, warnings))
+}
+
+/// The error a source fails with, after its diagnostics are printed
+fn failed(status: Status, filename: &str) -> anyhow::Error {
+    match status {
+        Status::Parse => fail(status, format!("{filename} doesn't parse")),
+        _ => fail(status, format!("{filename} has errors")),
+    }
+}
+
+/// Parses and checks a source, failing with the status of the step that has errors
+fn check_source(src: &str, filename: &str, out: &Output) -> Result<Schema> {
+    match checked(src, filename) {
+        Ok((schema, warnings)) => {
+            out.diagnostics(src, filename, &warnings);
+            Ok(schema)
+        }
+        Err((status, errs)) => {
+            out.diagnostics(src, filename, &errs);
+            Err(failed(status, filename))
+        }
+    }
+}
+
+/// Reads, parses and checks a schema file, diagnostics are printed along the way
+fn load_schema(path: &Path, out: &Output) -> Result<SchemaFile> {
+    let filename = path
+        .file_name()
+        .context("invalid source filename")?
+        .to_string_lossy()
+        .to_string();
+    load_source(path, filename, out)
+}
+
+/// Reads, parses and checks a schema, which is reported under the given name
+fn load_source(path: &Path, filename: String, out: &Output) -> Result<SchemaFile> {
+    let src = read_source(path)?;
+    let schema = check_source(&src, &filename, out)?;
+
+    Ok(SchemaFile {
+        schema,
+        src,
+        filename,
+    })
+}
+
+/// Stands for the standard input as a source, and for the standard output as an output
+const STDIO: &str = "-";
+
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new(STDIO)
+}
+
+/// Reads a source file, or the standard input for `-`
+fn read_source(path: &Path) -> Result<String> {
+    if is_stdio(path) {
+        return std::io::read_to_string(std::io::stdin())
+            .context("unable to read the standard input");
+    }
+    fs::read_to_string(path)
+        .with_context(|| format!("unable to read source file at {}", path.display()))
+}
+
+/// Generates the DDL of a checked schema, some of it may not fit the dialect
+fn generate_ddl(schema_file: &SchemaFile, dialect: Dialect, out: &Output) -> Result<String> {
+    cores::ddl::generate(&schema_file.schema, dialect).map_err(|errs| {
+        out.diagnostics(&schema_file.src, &schema_file.filename, &errs);
+        fail(
+            Status::Semantic,
+            format!(
+                "unable to generate the {} ddl of {}",
+                dialect.name(),
+                schema_file.filename
+            ),
+        )
+    })
+}
+
+/// Points at the `@renamed_from` markers of the new schema that the old one doesn't need
+fn warn_applied_renames(old: &Schema, new: &SchemaFile, out: &Output) {
+    let warnings = cores::diff::applied_renames(old, &new.schema);
+    if !warnings.is_empty() {
+        out.diagnostics(&new.src, &new.filename, &warnings);
+    }
+}
+
+/// The error of a command that went through several files, once each of them was tried
+fn failures(mut errs: Vec<anyhow::Error>, total: usize, action: &str) -> Result<()> {
+    if errs.len() <= 1 {
+        return errs.pop().map_or(Ok(()), Err);
+    }
+    let reasons: Vec<String> = errs.iter().map(|err| err.to_string()).collect();
+    Err(fail(
+        Status::of(&errs[0]),
+        format!(
+            "{} of {total} files failed to {action}: {}",
+            errs.len(),
+            reasons.join(", ")
+        ),
+    ))
+}
+
+/// The lint levels of the project, if any, with the `--rule` ones on top
+fn lint_config(project: Option<&Project>, rules: &[String]) -> Result<cores::rules::Config> {
+    let mut config = match project {
+        Some(project) => project.lint_config()?,
+        None => cores::rules::Config::new(cores::lint::RULES),
+    };
+    for rule in rules {
+        config.set(rule).map_err(anyhow::Error::msg)?;
+    }
+    Ok(config)
+}
+
+fn check(args: &CheckArgs, out: &Output) -> Result<()> {
+    let files = |project: Option<&Project>| match (args.sources.is_empty(), project) {
+        (false, _) => sources::expand(&args.sources),
+        (true, Some(project)) => Ok(project.entries().collect()),
+        (true, None) => Err(anyhow::anyhow!(
+            "no schema to check and no {PROJECT_FILE} found"
+        )),
+    };
+
+    if !args.watch {
+        let project = Project::discover()?;
+        let config = lint_config(project.as_ref(), &args.rules)?;
+        return check_files(&files(project.as_ref())?, args, &config, out);
+    }
+    if args.sources.iter().any(|source| is_stdio(source)) {
+        anyhow::bail!("the standard input can't be watched");
+    }
+
+    let watched = || {
+        let project = Project::discover()?;
+        let mut watched = files(project.as_ref())?;
+        watched.extend(project.map(|project| project.file()));
+        Ok(watched)
+    };
+    watch::watch(out, watched, |changed| {
+        let project = Project::discover()?;
+        let config = lint_config(project.as_ref(), &args.rules)?;
+        let files = match &project {
+            Some(project) if args.sources.is_empty() => project.affected(changed),
+            _ => files(project.as_ref())?,
+        };
+        check_files(&files, args, &config, out)
+    })
+}
+
+/// Checks and lints schema files, the diagnostics of all of them are printed before failing
+fn check_files(
+    files: &[PathBuf],
+    args: &CheckArgs,
+    config: &cores::rules::Config,
+    out: &Output,
+) -> Result<()> {
+    let sources = files
+        .iter()
+        .map(|path| {
+            // tools resolve the file the way it was given, rather than by its name alone
+            let file = if is_stdio(path) {
+                stdin_filename(args.stdin_filename.as_deref())
+            } else {
+                path.display().to_string()
+            };
+            Ok((file, read_source(path)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let checked = if args.merge {
+        lint_merged(sources, config)
+    } else {
+        sources
+            .into_iter()
+            .map(|(file, src)| {
+                let (diagnostics, failure) = lint_source(&src, &file, config);
+                (file, src, diagnostics, failure)
+            })
+            .collect()
+    };
+
+    let mut located: Vec<LocatedDiagnostic> = Vec::new();
+    let mut errs = Vec::new();
+    for (file, src, diagnostics, failure) in checked {
+        if let DiagnosticFormat::Human = args.format {
+            out.diagnostics(&src, &file, &diagnostics);
+        } else {
+            located.extend(
+                diagnostics
+                    .iter()
+                    .filter(|diagnostic| diagnostic.is_error() || !out.quiet)
+                    .map(|diagnostic| LocatedDiagnostic::new(&file, &src, diagnostic)),
+            );
+        }
+
+        match failure {
+            Some(Status::Findings) => {
+                errs.push(fail(Status::Findings, format!("{file} breaks lint rules")))
+            }
+            Some(status) => errs.push(failed(status, &file)),
+            None if matches!(args.format, DiagnosticFormat::Human) => {
+                out.status(format!("{file} is valid"))
+            }
+            None => {}
+        }
+    }
+
+    if !matches!(args.format, DiagnosticFormat::Human) {
+        let json = match args.format {
+            DiagnosticFormat::Sarif => serde_json::to_string_pretty(&cores::sarif::log(&located)),
+            _ => serde_json::to_string_pretty(&located),
+        };
+        println!("{}", json.context("failed to serialize the diagnostics")?);
+    }
+
+    failures(errs, files.len(), "check")
+}
+
+/// The diagnostics of a source, lints included, and the status it fails with if any
+fn lint_source(
+    src: &str,
+    file: &str,
+    config: &cores::rules::Config,
+) -> (Vec<Diagnostic>, Option<Status>) {
+    match checked(src, file) {
+        Ok((schema, mut warnings)) => {
+            warnings.extend(cores::lint::lint(&schema, config));
+            let broken = warnings.iter().any(|diagnostic| diagnostic.is_error());
+            (warnings, broken.then_some(Status::Findings))
+        }
+        Err((status, errs)) => (errs, Some(status)),
+    }
+}
+
+/// Checks and lints sources as one schema, each comes back with the diagnostics in it and
+/// the status it fails with if any
+fn lint_merged(
+    sources: Vec<(String, String)>,
+    config: &cores::rules::Config,
+) -> Vec<(String, String, Vec<Diagnostic>, Option<Status>)> {
+    let merged = Merged::new(sources);
+    let (diagnostics, status) = match merged.check("schema") {
+        Ok((schema, mut warnings)) => {
+            warnings.extend(cores::lint::lint(&schema, config));
+            (warnings, Status::Findings)
+        }
+        Err(errs) if errs.iter().any(|err| err.code == Some(SYNTAX_ERROR)) => (errs, Status::Parse),
+        Err(errs) => (errs, Status::Semantic),
+    };
+
+    merged
+        .split(&diagnostics)
+        .into_iter()
+        .map(|(file, diagnostics)| {
+            let broken = diagnostics.iter().any(|diagnostic| diagnostic.is_error());
+            let failure = broken.then_some(status);
+            (
+                file.filename.clone(),
+                file.src.clone(),
+                diagnostics,
+                failure,
+            )
+        })
+        .collect()
+}
+
+fn build(args: &BuildArgs, out: &Output) -> Result<()> {
+    let project = || {
+        Project::discover()?.with_context(|| {
+            format!("no source to build and no {PROJECT_FILE} found, pass one with -s")
+        })
+    };
+
+    if args.watch && args.source.iter().any(|source| is_stdio(source)) {
+        anyhow::bail!("the standard input can't be watched");
+    }
+
+    match (args.source.is_empty(), args.watch) {
+        (false, false) => build_sources(&sources::expand(&args.source)?, args, out),
+        // a merged schema is built from every source, whichever changed
+        (false, true) => watch::watch(
+            out,
+            || sources::expand(&args.source),
+            |changed| match args.merge {
+                true => build_sources(&sources::expand(&args.source)?, args, out),
+                false => build_sources(changed, args, out),
+            },
+        ),
+        (true, false) => {
+            let project = project()?;
+            build_project(&project, &project.entries().collect::<Vec<_>>(), out)
+        }
+        (true, true) => watch::watch(
+            out,
+            || {
+                let project = project()?;
+                Ok(project.entries().chain([project.file()]).collect())
+            },
+            |changed| {
+                let project = project()?;
+                build_project(&project, &project.affected(changed), out)
+            },
+        ),
+    }
+}
+
+/// Compiles each source into the output directory, or all of them into one schema
+fn build_sources(files: &[PathBuf], args: &BuildArgs, out: &Output) -> Result<()> {
     let current_dir = env::current_dir().context("failed to get current dir")?;
-    let output_dir = args.out.unwrap_or(current_dir);
+    let output_dir = args.out.clone().unwrap_or(current_dir);
+    let extension = if args.target.is_some() { "sql" } else { "json" };
+
+    if args.merge {
+        return build_merged(files, args, &output_dir, extension, out);
+    }
+    if is_stdio(&output_dir) && files.len() > 1 {
+        anyhow::bail!(
+            "only one output can go to the standard output, pass --merge or a single source"
+        );
+    }
+
+    // files of the same name in different directories would overwrite each other
+    let mut outputs: Vec<(String, &Path)> = Vec::new();
+    for file in files {
+        let named = if is_stdio(file) {
+            Path::new(args.stdin_filename.as_deref().unwrap_or("stdin"))
+        } else {
+            file
+        };
+        let output_filename = format!(
+            "{}.{extension}",
+            named
+                .file_stem()
+                .context("invalid source filename")?
+                .to_string_lossy()
+        );
+        if let Some((_, other)) = outputs.iter().find(|(name, _)| *name == output_filename) {
+            anyhow::bail!(
+                "{} and {} would both be compiled to {output_filename}, pass --merge or build them apart",
+                other.display(),
+                file.display()
+            );
+        }
+        outputs.push((output_filename, file));
+    }
+
+    let errs = outputs
+        .iter()
+        .filter_map(|(output_filename, file)| {
+            build_file(file, args, &output_dir, output_filename, out).err()
+        })
+        .collect();
+    failures(errs, files.len(), "build")
+}
+
+fn build_file(
+    source_path: &Path,
+    args: &BuildArgs,
+    output_dir: &Path,
+    output_filename: &str,
+    out: &Output,
+) -> Result<()> {
+    let schema_file = if is_stdio(source_path) {
+        load_source(
+            source_path,
+            stdin_filename(args.stdin_filename.as_deref()),
+            out,
+        )?
+    } else {
+        load_schema(source_path, out)?
+    };
+    let output = match args.target {
+        Some(dialect) => generate_ddl(&schema_file, dialect, out)?,
+        None => serde_json::to_string(&schema_file.schema).context("failed to serialize")?,
+    };
+
+    write_output(output_dir, output_filename, &output, out)
+}
+
+/// Compiles several sources as one schema, their diagnostics are printed against each file
+fn build_merged(
+    files: &[PathBuf],
+    args: &BuildArgs,
+    output_dir: &Path,
+    extension: &str,
+    out: &Output,
+) -> Result<()> {
+    const NAME: &str = "schema";
+
+    let sources = files
+        .iter()
+        .map(|path| {
+            let filename = if is_stdio(path) {
+                stdin_filename(args.stdin_filename.as_deref())
+            } else {
+                path.display().to_string()
+            };
+            Ok((filename, read_source(path)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let merged = Merged::new(sources);
+    let report = |diagnostics: &[Diagnostic]| {
+        for (file, diagnostics) in merged.split(diagnostics) {
+            out.diagnostics(&file.src, &file.filename, &diagnostics);
+        }
+    };
+
+    let schema = match merged.check(NAME) {
+        Ok((schema, warnings)) => {
+            report(&warnings);
+            schema
+        }
+        Err(errs) => {
+            report(&errs);
+            let status = if errs.iter().any(|err| err.code == Some(SYNTAX_ERROR)) {
+                Status::Parse
+            } else {
+                Status::Semantic
+            };
+            let failing = merged
+                .split(&errs)
+                .iter()
+                .filter(|(_, errs)| !errs.is_empty())
+                .count();
+            return Err(fail(
+                status,
+                format!("{failing} of {} merged files have errors", files.len()),
+            ));
+        }
+    };
 
-    let file_stem = source_path
+    let output = match args.target {
+        Some(dialect) => cores::ddl::generate(&schema, dialect).map_err(|errs| {
+            report(&errs);
+            fail(
+                Status::Semantic,
+                format!(
+                    "unable to generate the {} ddl of the merged schema",
+                    dialect.name()
+                ),
+            )
+        })?,
+        None => serde_json::to_string(&schema).context("failed to serialize")?,
+    };
+
+    write_output(output_dir, &format!("{NAME}.{extension}"), &output, out)
+}
+
+/// Generates every target of a project from some of its entries
+fn build_project(project: &Project, entries: &[PathBuf], out: &Output) -> Result<()> {
+    if project.targets.is_empty() {
+        anyhow::bail!("{PROJECT_FILE} has no target to build");
+    }
+
+    let errs = entries
+        .iter()
+        .filter_map(|entry| build_entry(project, entry, out).err())
+        .collect();
+    failures(errs, entries.len(), "build")
+}
+
+/// Generates every target of a project from one of its entries
+fn build_entry(project: &Project, entry: &Path, out: &Output) -> Result<()> {
+    let schema_file = load_schema(entry, out)?;
+    let file_stem = entry
         .file_stem()
         .context("invalid source filename")?
-        .to_string_lossy();
+        .to_string_lossy()
+        .to_string();
+
+    for target in &project.targets {
+        let mut schema = schema_file.schema.clone();
+        cores::naming::apply(&mut schema, target.naming).map_err(|errs| {
+            out.diagnostics(&schema_file.src, &schema_file.filename, &errs);
+            fail(
+                Status::Semantic,
+                format!(
+                    "the names of {} aren't distinct in {}",
+                    schema_file.filename,
+                    target.naming.name()
+                ),
+            )
+        })?;
+
+        let (output, extension) = match target.dialect {
+            Some(dialect) => {
+                if let Some(types) = project.types.get(&dialect) {
+                    cores::ddl::map_types(&mut schema, dialect, types);
+                }
+                let target_file = SchemaFile {
+                    schema,
+                    src: schema_file.src.clone(),
+                    filename: schema_file.filename.clone(),
+                };
+                (generate_ddl(&target_file, dialect, out)?, "sql")
+            }
+            None => (
+                serde_json::to_string(&schema).context("failed to serialize")?,
+                "json",
+            ),
+        };
+
+        let output_dir = project.root.join(&target.out);
+        fs::create_dir_all(&output_dir)
+            .with_context(|| format!("unable to create {}", output_dir.display()))?;
+        write_output(
+            &output_dir,
+            &format!("{file_stem}.{extension}"),
+            &output,
+            out,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// What the standard input is reported as
+fn stdin_filename(name: Option<&str>) -> String {
+    name.unwrap_or("<stdin>").to_string()
+}
+
+/// Writes an output unless it's the same already, so that whatever watches it isn't set off.
+/// The standard output gets the output alone, without a status line.
+fn write_output(
+    output_dir: &Path,
+    output_filename: &str,
+    output: &str,
+    out: &Output,
+) -> Result<()> {
+    if is_stdio(output_dir) {
+        print!("{output}");
+        if !output.ends_with('\n') {
+            println!();
+        }
+        return Ok(());
+    }
+
+    let full_output_path = output_dir.join(output_filename);
+    if fs::read_to_string(&full_output_path).is_ok_and(|current| current == output) {
+        out.status(format!(
+            "{output_filename} is up to date in {}",
+            output_dir.display()
+        ));
+        return Ok(());
+    }
+
+    fs::write(&full_output_path, output)
+        .with_context(|| format!("error writing to {}", full_output_path.display()))?;
+    out.status(format!(
+        "{output_filename} is compiled in {}",
+        output_dir.display()
+    ));
+    Ok(())
+}
+
+fn diff(old: &Path, new: &Path, format: Format, out: &Output) -> Result<()> {
+    let old_schema = load_schema(old, out)?;
+    let new_schema = load_schema(new, out)?;
+    warn_applied_renames(&old_schema.schema, &new_schema, out);
+
+    let schema_diff = cores::diff::diff(&old_schema.schema, &new_schema.schema);
+
+    match format {
+        Format::Human if schema_diff.is_empty() => out.status("no changes"),
+        Format::Human => print!("{schema_diff}"),
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&schema_diff).context("failed to serialize the diff")?
+        ),
+    }
+
+    Ok(())
+}
+
+fn check_compat(schema: &Path, against: &Path, rules: &[String], out: &Output) -> Result<()> {
+    let mut config = cores::rules::Config::new(cores::compat::RULES);
+    for rule in rules {
+        config.set(rule).map_err(anyhow::Error::msg)?;
+    }
+
+    let new_schema = load_schema(schema, out)?;
+    let old_filename = against.display().to_string();
+    let (old_schema, old_src) = if against.extension().is_some_and(|ext| ext == "json") {
+        let json = fs::read_to_string(against)
+            .with_context(|| format!("unable to read {}", against.display()))?;
+        let old_schema: Schema = serde_json::from_str(&json)
+            .with_context(|| format!("{} is not a compiled mecha schema", against.display()))?;
+        (old_schema, None)
+    } else {
+        let old_schema = load_schema(against, out)?;
+        (old_schema.schema, Some(old_schema.src))
+    };
+
+    let mut violations = cores::compat::check(&old_schema, &new_schema.schema, &config);
+    if out.quiet {
+        violations.retain(|v| v.severity == cores::rules::Severity::Error);
+    }
+    cores::codegen::report_violations(
+        &violations,
+        (&old_filename, old_src.as_deref()),
+        (&new_schema.filename, &new_schema.src),
+        out.color,
+    );
+
+    let errors = violations
+        .iter()
+        .filter(|v| v.severity == cores::rules::Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(fail(
+            Status::Findings,
+            format!("{errors} breaking change(s) against {}", against.display()),
+        ));
+    }
+
+    Ok(())
+}
+
+fn migrate_generate(
+    schema: &Path,
+    target: Option<Dialect>,
+    dir: &Path,
+    name: &str,
+    out: &Output,
+) -> Result<()> {
+    let new_schema = load_schema(schema, out)?;
+
+    let snapshot = migrations::read_snapshot(dir)?;
+    let dialect = match (&snapshot, target) {
+        (Some(snapshot), Some(target)) if snapshot.dialect != target => anyhow::bail!(
+            "{} holds {} migrations, not {}",
+            dir.display(),
+            snapshot.dialect.name(),
+            target.name()
+        ),
+        (Some(snapshot), _) => snapshot.dialect,
+        (None, Some(target)) => target,
+        (None, None) => anyhow::bail!("the first migration needs a --target dialect"),
+    };
+    let old_schema = match snapshot {
+        Some(snapshot) => snapshot.schema,
+        None => Schema {
+            name: String::new(),
+            tables: Vec::new(),
+            span: Default::default(),
+        },
+    };
+    warn_applied_renames(&old_schema, &new_schema, out);
+
+    let up = match cores::migrate::generate(&old_schema, &new_schema.schema, dialect) {
+        Ok(up) => up,
+        Err(errs) => {
+            out.diagnostics(&new_schema.src, &new_schema.filename, &errs);
+            return Err(fail(
+                Status::Semantic,
+                format!("unable to migrate to {}", schema.display()),
+            ));
+        }
+    };
 
-    let output_filename = format!("{}.json", file_stem);
-    let full_output_path = output_dir.join(&output_filename);
+    if up.is_empty() {
+        out.status("no changes");
+        return Ok(());
+    }
+
+    // the snapshot went through this dialect already, it has no source to point errors into
+    let down =
+        cores::migrate::generate(&new_schema.schema, &old_schema, dialect).map_err(|errs| {
+            let reasons: Vec<String> = errs.iter().map(|err| err.to_string()).collect();
+            anyhow::anyhow!(
+                "unable to migrate back to the snapshot: {}",
+                reasons.join(", ")
+            )
+        })?;
+
+    let migration = migrations::write(dir, name, &up, &down)?;
+    migrations::write_snapshot(
+        dir,
+        &migrations::Snapshot {
+            dialect,
+            schema: new_schema.schema,
+        },
+    )?;
+
+    println!("{}", migration.up.display());
+    println!("{}", migration.down.display());
+
+    Ok(())
+}
+
+fn migrate_verify(schema: &Path, dir: &Path, out: &Output) -> Result<()> {
+    let schema_file = load_schema(schema, out)?;
+
+    let Some(snapshot) = migrations::read_snapshot(dir)? else {
+        anyhow::bail!("{} has no {}", dir.display(), migrations::SNAPSHOT_FILE);
+    };
+
+    let pending = cores::diff::diff(&snapshot.schema, &schema_file.schema);
+    if !pending.is_empty() {
+        eprint!("{pending}");
+        return Err(fail(
+            Status::Findings,
+            format!(
+                "{} has changes without a migration, run `mecha migrate generate`",
+                schema.display()
+            ),
+        ));
+    }
+
+    if snapshot.dialect != Dialect::Sqlite {
+        anyhow::bail!(
+            "{} holds {} migrations, which can't be verified: migrations are replayed with \
+SQLite, so only sqlite ones are. The snapshot is up to date with {}",
+            dir.display(),
+            snapshot.dialect.name(),
+            schema.display()
+        );
+    }
+
+    let migrations = migrations::list(dir)?;
+    let replayed = Connection::open_in_memory().context("unable to open a SQLite database")?;
+    for migration in &migrations {
+        let sql = fs::read_to_string(&migration.up)
+            .with_context(|| format!("unable to read {}", migration.up.display()))?;
+        replayed
+            .execute_batch(&sql)
+            .with_context(|| format!("{} failed", migration.up.display()))?;
+    }
+
+    let ddl = generate_ddl(&schema_file, Dialect::Sqlite, out)?;
+    let expected = Connection::open_in_memory().context("unable to open a SQLite database")?;
+    expected
+        .execute_batch(&ddl)
+        .context("the generated ddl failed")?;
+
+    let differences = sqlite::differences(&sqlite::tables(&expected)?, &sqlite::tables(&replayed)?);
+    if !differences.is_empty() {
+        for difference in &differences {
+            eprintln!("{difference}");
+        }
+        return Err(fail(
+            Status::Findings,
+            format!(
+                "replaying {} doesn't reproduce {}",
+                dir.display(),
+                schema.display()
+            ),
+        ));
+    }
+
+    // going all the way down should leave nothing behind
+    for migration in migrations.iter().rev() {
+        let sql = fs::read_to_string(&migration.down)
+            .with_context(|| format!("unable to read {}", migration.down.display()))?;
+        replayed
+            .execute_batch(&sql)
+            .with_context(|| format!("{} failed", migration.down.display()))?;
+    }
+    let leftovers: Vec<String> = sqlite::tables(&replayed)?
+        .into_iter()
+        .map(|table| table.name)
+        .collect();
+    if !leftovers.is_empty() {
+        return Err(fail(
+            Status::Findings,
+            format!("the down migrations leave {} behind", leftovers.join(", ")),
+        ));
+    }
+
+    out.status(format!(
+        "{} migration(s) reproduce {}",
+        migrations.len(),
+        schema.display()
+    ));
+    Ok(())
+}
+
+/// Whether every file was formatted already, the others are rewritten unless checking
+fn fmt(files: &[PathBuf], check: bool, out: &Output) -> Result<bool> {
+    let mut formatted = true;
+    let mut failed = 0;
+
+    for path in files {
+        let src = fs::read_to_string(path)
+            .with_context(|| format!("unable to read source file at {}", path.display()))?;
+        let filename = path.display().to_string();
+        let output = match cores::format::format_source(&filename, &src) {
+            Ok(output) => output,
+            Err(errs) => {
+                out.diagnostics(&src, &filename, &errs);
+                failed += 1;
+                continue;
+            }
+        };
+        if output == src {
+            continue;
+        }
+
+        formatted = false;
+        if check {
+            eprintln!("{} isn't formatted", path.display());
+        } else {
+            fs::write(path, output)
+                .with_context(|| format!("unable to write {}", path.display()))?;
+        }
+    }
+
+    if failed > 0 {
+        return Err(fail(
+            Status::Parse,
+            format!("{failed} file(s) couldn't be formatted"),
+        ));
+    }
+    Ok(formatted)
+}
+
+/// Opens a database read-only, a missing file fails rather than being created empty
+fn open_database(path: &Path) -> Result<Connection> {
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("unable to open {}", path.display()))
+}
 
-    println!("source = {}", source_path.display());
-    println!("full_output_path = {}", full_output_path.display());
+/// Whether the database drifted apart from the schema, the differences are printed
+fn drift(database: &Path, schema: &Path, format: Format, out: &Output) -> Result<bool> {
+    let schema_file = load_schema(schema, out)?;
 
-    let src = fs::read_to_string(&source_path)
-        .with_context(|| format!("unable to read source file at {}", source_path.display()))?;
+    let conn = open_database(database)?;
+    let (actual, _) = cores::import::sqlite(&database.display().to_string(), &conn)
+        .with_context(|| format!("unable to read {}", database.display()))?;
 
-    let source_filename = source_path
+    // the schema goes through SQLite as well, so that types and inheritance come out the
+    // way they do in the database
+    let ddl = generate_ddl(&schema_file, Dialect::Sqlite, out)?;
+    let deployed = Connection::open_in_memory().context("unable to open a SQLite database")?;
+    deployed
+        .execute_batch(&ddl)
+        .context("the generated ddl failed")?;
+    let (expected, _) = cores::import::sqlite(&schema_file.filename, &deployed)?;
+
+    let drift = cores::diff::diff(&expected, &actual);
+    match format {
+        Format::Human if drift.is_empty() => out.status("no drift"),
+        Format::Human => print!("{drift}"),
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&drift).context("failed to serialize the drift")?
+        ),
+    }
+
+    Ok(!drift.is_empty())
+}
+
+fn explain(code: Option<&str>) -> Result<()> {
+    let Some(code) = code else {
+        for (code, summary) in cores::explain::summaries() {
+            println!("{code}  {summary}");
+        }
+        for rule in cores::lint::RULES.iter().chain(cores::compat::RULES) {
+            println!("{}  {} ({})", rule.code, rule.summary, rule.name);
+        }
+        return Ok(());
+    };
+
+    if let Some(explanation) = cores::explain::explain(code) {
+        print!("{explanation}");
+    } else if let Some(rule) = cores::lint::rule(code) {
+        println!(
+            "{} ({}), a lint rule: {}. It's a {} unless configured otherwise, and allowed inline with `@allow(\"{}\")`.",
+            rule.code, rule.name, rule.summary, rule.severity, rule.name
+        );
+    } else if let Some(rule) = cores::compat::rule(code) {
+        println!(
+            "{} ({}), a compatibility rule of `mecha check-compat`: {}. It's reported as {} unless configured otherwise with `--rule {}=<severity>`.",
+            rule.code, rule.name, rule.summary, rule.severity, rule.code
+        );
+    } else {
+        anyhow::bail!("{code} isn't a mecha code, `mecha explain` lists them");
+    }
+    Ok(())
+}
+
+fn import(
+    input: &Path,
+    from: ImportSource,
+    dialect: Dialect,
+    out_path: Option<&Path>,
+    out: &Output,
+) -> Result<()> {
+    let filename = input
         .file_name()
-        .context("invalid source filename")?
-        .to_string_lossy();
+        .context("invalid input filename")?
+        .to_string_lossy()
+        .to_string();
 
-    let output_dir_str = output_dir.to_string_lossy();
+    let schema = match from {
+        ImportSource::Sql => {
+            let src = fs::read_to_string(input)
+                .with_context(|| format!("unable to read {}", input.display()))?;
+            let (schema, warnings) = cores::import::sql(&filename, &src, dialect);
+            out.diagnostics(&src, &filename, &warnings);
+            schema
+        }
+        ImportSource::Sqlite => {
+            let conn = open_database(input)?;
+            let (schema, warnings) = cores::import::sqlite(&filename, &conn)
+                .with_context(|| format!("unable to read {}", input.display()))?;
+            for warning in warnings {
+                out.warning(warning);
+            }
+            schema
+        }
+    };
 
-    cores::codegen::compile(&src, &source_filename, &output_dir_str, &output_filename);
+    if schema.tables.is_empty() {
+        anyhow::bail!("no tables to import from {}", input.display());
+    }
+    let output = cores::format::format(&schema);
+
+    // what mecha itself rejects is never written, e.g. indexes the SQL declares twice
+    let output_name = out_path.map_or("<imported>".to_string(), |path| path.display().to_string());
+    if let Err((status, errs)) = checked(&output, &output_name) {
+        out.diagnostics(&output, &output_name, &errs);
+        return Err(fail(
+            status,
+            format!(
+                "the schema imported from {} has errors, nothing is written",
+                input.display()
+            ),
+        ));
+    }
+
+    match out_path {
+        Some(path) => fs::write(path, &output)
+            .with_context(|| format!("unable to write {}", path.display()))?,
+        None => print!("{output}"),
+    }
 
     Ok(())
 }
+
+fn run(command: Option<Command>, legacy: BuildArgs, out: &Output) -> Result<Status> {
+    let Some(command) = command else {
+        return build(&legacy, out).map(|()| Status::Success);
+    };
+
+    let findings = |found: bool| {
+        if found {
+            Status::Findings
+        } else {
+            Status::Success
+        }
+    };
+    match command {
+        Command::Check(args) => check(&args, out).map(|()| Status::Success),
+        Command::Build(args) => build(&args, out).map(|()| Status::Success),
+        Command::Diff { old, new, format } => {
+            diff(&old, &new, format, out).map(|()| Status::Success)
+        }
+        Command::CheckCompat {
+            schema,
+            against,
+            rules,
+        } => check_compat(&schema, &against, &rules, out).map(|()| Status::Success),
+        Command::Fmt { files, check } => {
+            fmt(&files, check, out).map(|formatted| findings(check && !formatted))
+        }
+        Command::Drift {
+            sqlite,
+            schema,
+            format,
+        } => drift(&sqlite, &schema, format, out).map(findings),
+        Command::Explain { code } => explain(code.as_deref()).map(|()| Status::Success),
+        Command::Import {
+            input,
+            from,
+            dialect,
+            out: out_path,
+        } => import(&input, from, dialect, out_path.as_deref(), out).map(|()| Status::Success),
+        Command::Migrate { command } => match command {
+            MigrateCommand::Generate {
+                schema,
+                target,
+                dir,
+                name,
+            } => migrate_generate(&schema, target, &dir, &name, out),
+            MigrateCommand::Verify { schema, dir } => migrate_verify(&schema, &dir, out),
+        }
+        .map(|()| Status::Success),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let out = Output::new(args.quiet, args.color);
+
+    match run(args.command, args.build, &out) {
+        Ok(status) => status.into(),
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            Status::of(&err).into()
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use cores::ast::Schema;
+use cores::ddl::Dialect;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The resolved schema the last migration of a directory leads to
+pub const SNAPSHOT_FILE: &str = "schema.lock.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub dialect: Dialect,
+    pub schema: Schema,
+}
+
+/// A numbered pair of files, e.g. `0003_add_posts.up.sql` and `0003_add_posts.down.sql`
+pub struct Migration {
+    pub number: u32,
+    pub up: PathBuf,
+    pub down: PathBuf,
+}
+
+pub fn read_snapshot(dir: &Path) -> Result<Option<Snapshot>> {
+    let path = dir.join(SNAPSHOT_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let json =
+        fs::read_to_string(&path).with_context(|| format!("unable to read {}", path.display()))?;
+    let snapshot = serde_json::from_str(&json)
+        .with_context(|| format!("{} is not a valid snapshot", path.display()))?;
+    Ok(Some(snapshot))
+}
+
+pub fn write_snapshot(dir: &Path, snapshot: &Snapshot) -> Result<()> {
+    let path = dir.join(SNAPSHOT_FILE);
+    let json =
+        serde_json::to_string_pretty(snapshot).context("failed to serialize the snapshot")?;
+    fs::write(&path, json + "\n").with_context(|| format!("unable to write {}", path.display()))
+}
+
+/// The migrations of a directory, ordered by number
+pub fn list(dir: &Path) -> Result<Vec<Migration>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut migrations = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("unable to read {}", dir.display()))? {
+        let up = entry?.path();
+        let Some(filename) = up.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let Some(stem) = filename.strip_suffix(".up.sql") else {
+            continue;
+        };
+        let Some(number) = stem.split('_').next().and_then(|n| n.parse().ok()) else {
+            anyhow::bail!("{} doesn't start with a migration number", up.display());
+        };
+
+        let down = dir.join(format!("{stem}.down.sql"));
+        if !down.exists() {
+            anyhow::bail!("{} has no down migration", up.display());
+        }
+        migrations.push(Migration { number, up, down });
+    }
+
+    migrations.sort_by_key(|migration| migration.number);
+    if let Some(pair) = migrations
+        .windows(2)
+        .find(|pair| pair[0].number == pair[1].number)
+    {
+        anyhow::bail!(
+            "{} and {} share the same number",
+            pair[0].up.display(),
+            pair[1].up.display()
+        );
+    }
+
+    Ok(migrations)
+}
+
+/// Writes the next migration of a directory, numbered after the existing ones
+pub fn write(dir: &Path, name: &str, up: &str, down: &str) -> Result<Migration> {
+    let number = list(dir)?.last().map_or(1, |last| last.number + 1);
+    let stem = format!("{number:04}_{name}");
+
+    fs::create_dir_all(dir).with_context(|| format!("unable to create {}", dir.display()))?;
+
+    let migration = Migration {
+        number,
+        up: dir.join(format!("{stem}.up.sql")),
+        down: dir.join(format!("{stem}.down.sql")),
+    };
+    for (path, statements) in [(&migration.up, up), (&migration.down, down)] {
+        fs::write(path, statements)
+            .with_context(|| format!("unable to write {}", path.display()))?;
+    }
+
+    Ok(migration)
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use cores::ddl::Dialect;
+use cores::lint;
+use cores::naming::Convention;
+use cores::rules::Config;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The file a project is configured in, found from the working directory upward
+pub const PROJECT_FILE: &str = "mecha.toml";
+
+/// What `mecha build` and `mecha check` work on when they aren't given a schema, e.g.
+///
+/// ```toml
+/// entries = ["schema/store.mecha"]
+///
+/// [[target]]
+/// dialect = "postgres"
+/// naming = "snake_case"
+/// out = "build/postgres"
+///
+/// [lint]
+/// missing-primary-key = "error"
+///
+/// [types.postgres]
+/// string = "text"
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Project {
+    /// The schema files, relative to the project's directory
+    pub entries: Vec<PathBuf>,
+
+    #[serde(default, rename = "target")]
+    pub targets: Vec<Target>,
+
+    /// Severities of lint rules by code or name, `--rule` goes on top of them
+    #[serde(default)]
+    pub lint: BTreeMap<String, String>,
+
+    /// SQL types that mecha types are generated as, by dialect. A `@db.<dialect>`
+    /// annotation on a column still wins.
+    #[serde(default)]
+    pub types: HashMap<Dialect, HashMap<String, String>>,
+
+    /// The directory holding the project file
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+/// An artifact generated from every entry
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    /// The schema is compiled to JSON without one
+    pub dialect: Option<Dialect>,
+
+    #[serde(default)]
+    pub naming: Convention,
+
+    /// The directory the output is written to, relative to the project's directory
+    pub out: PathBuf,
+}
+
+impl Project {
+    /// The project the working directory is in, if any
+    pub fn discover() -> Result<Option<Project>> {
+        let current_dir = std::env::current_dir().context("failed to get current dir")?;
+        let Some(found) = current_dir
+            .ancestors()
+            .find(|dir| dir.join(PROJECT_FILE).is_file())
+        else {
+            return Ok(None);
+        };
+
+        // the root is kept relative so that paths read the way they would be typed
+        let depth = current_dir.strip_prefix(found)?.components().count();
+        let root: PathBuf = (0..depth).map(|_| "..").collect();
+        Project::read(&root).map(Some)
+    }
+
+    fn read(root: &Path) -> Result<Project> {
+        let path = root.join(PROJECT_FILE);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("unable to read {}", path.display()))?;
+        let mut project: Project = toml::from_str(&content)
+            .with_context(|| format!("{} is not a valid project file", path.display()))?;
+        project.root = root.to_path_buf();
+
+        if project.entries.is_empty() {
+            anyhow::bail!("{} has no entries", path.display());
+        }
+        project
+            .lint_config()
+            .with_context(|| format!("{} has invalid lint levels", path.display()))?;
+        Ok(project)
+    }
+
+    pub fn file(&self) -> PathBuf {
+        self.root.join(PROJECT_FILE)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.entries.iter().map(|entry| self.root.join(entry))
+    }
+
+    /// The entries to redo once some files changed, all of them when the project did
+    pub fn affected(&self, changed: &[PathBuf]) -> Vec<PathBuf> {
+        let everything = changed.contains(&self.file());
+        self.entries()
+            .filter(|entry| everything || changed.contains(entry))
+            .collect()
+    }
+
+    /// The lint configuration of the project, without any `--rule`
+    pub fn lint_config(&self) -> Result<Config> {
+        let mut config = Config::new(lint::RULES);
+        for (rule, severity) in &self.lint {
+            let severity = severity.parse().map_err(anyhow::Error::msg)?;
+            config
+                .set_rule(rule, severity)
+                .map_err(anyhow::Error::msg)?;
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,253 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// The extension of schema files, the only ones picked from directories
+const EXTENSION: &str = "mecha";
+
+/// Expands the sources given on the command line into schema files. A file is taken as it
+/// is, a directory stands for the .mecha files in it and below, and a path with `*`, `?` or
+/// `**` for the .mecha files it matches. Each file comes once, in the order given.
+pub fn expand(sources: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for source in sources {
+        let found = if is_pattern(source) {
+            let mut found = Vec::new();
+            glob(Path::new(""), &components(source), &mut found)?;
+            found.retain(|path| is_schema(path));
+            found
+        } else if source.is_dir() {
+            let mut found = Vec::new();
+            walk(source, &mut found)?;
+            found
+        } else {
+            vec![source.clone()]
+        };
+
+        if found.is_empty() && is_pattern(source) {
+            anyhow::bail!("no .{EXTENSION} file matches {}", source.display());
+        } else if found.is_empty() {
+            anyhow::bail!("no .{EXTENSION} file in {}", source.display());
+        }
+        for file in found {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn is_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?'])
+}
+
+fn is_schema(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|ext| ext == EXTENSION)
+}
+
+fn components(path: &Path) -> Vec<String> {
+    path.components()
+        .map(|component| match component {
+            Component::Normal(name) => name.to_string_lossy().to_string(),
+            other => other.as_os_str().to_string_lossy().to_string(),
+        })
+        .collect()
+}
+
+/// The entries of a directory, sorted so that the order doesn't depend on the file system
+fn entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    let read = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let mut entries = fs::read_dir(read)
+        .with_context(|| format!("unable to read {}", read.display()))?
+        .map(|entry| entry.map(|entry| dir.join(entry.file_name())))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("unable to read {}", read.display()))?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// Every schema file in a directory and below
+fn walk(dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    for path in entries(dir)? {
+        if path.is_dir() {
+            walk(&path, found)?;
+        } else if is_schema(&path) {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The paths under `base` that match the remaining components of a pattern
+fn glob(base: &Path, pattern: &[String], found: &mut Vec<PathBuf>) -> Result<()> {
+    let Some((component, rest)) = pattern.split_first() else {
+        found.push(base.to_path_buf());
+        return Ok(());
+    };
+
+    if component == "**" {
+        // any number of directories, none included
+        glob(base, rest, found)?;
+        for path in entries(base)? {
+            if path.is_dir() {
+                glob(&path, pattern, found)?;
+            }
+        }
+    } else if !component.contains(['*', '?']) {
+        let path = base.join(component);
+        if path.exists() {
+            glob(&path, rest, found)?;
+        }
+    } else if base.as_os_str().is_empty() || base.is_dir() {
+        for path in entries(base)? {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if matches(component, &name) {
+                glob(&path, rest, found)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether a name matches a pattern, where `*` is any run of characters and `?` any one
+fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // the last `*` met and where in the name it started matching, to go back to
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some('?') => (p, n) = (p + 1, n + 1),
+            Some(c) if *c == name[n] => (p, n) = (p + 1, n + 1),
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    (p, n) = (star_p + 1, star_n + 1);
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, matches};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// A directory of empty files under the system's temporary one, made anew for a test
+    fn tree(name: &str, files: &[&str]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("mecha-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for file in files {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        root
+    }
+
+    fn relative(root: &Path, files: Vec<PathBuf>) -> Vec<String> {
+        files
+            .iter()
+            .map(|file| file.strip_prefix(root).unwrap().display().to_string())
+            .collect()
+    }
+
+    const FILES: &[&str] = &[
+        "a.mecha",
+        "ab.mecha",
+        "notes.txt",
+        "sub/c.mecha",
+        "sub/deep/d.mecha",
+        "sub/deep/e.sql",
+    ];
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("*.mecha", "users.mecha"));
+        assert!(matches("*.mecha", ".mecha"));
+        assert!(!matches("*.mecha", "users.mecha.bak"));
+        assert!(matches("?.mecha", "a.mecha"));
+        assert!(!matches("?.mecha", "ab.mecha"));
+        assert!(matches("a*b*c", "axxbyyc"));
+        assert!(matches("a*b*c", "abcbc"));
+        assert!(!matches("a*b*c", "abcb"));
+        assert!(matches("*", ""));
+        assert!(!matches("?", ""));
+    }
+
+    #[test]
+    fn test_expand_patterns() {
+        let root = tree("patterns", FILES);
+        let expanded = |pattern: &str| relative(&root, expand(&[root.join(pattern)]).unwrap());
+
+        assert_eq!(expanded("*.mecha"), ["a.mecha", "ab.mecha"]);
+        assert_eq!(expanded("?.mecha"), ["a.mecha"]);
+        assert_eq!(expanded("*"), ["a.mecha", "ab.mecha"]);
+        assert_eq!(
+            expanded("**/*.mecha"),
+            ["a.mecha", "ab.mecha", "sub/c.mecha", "sub/deep/d.mecha"]
+        );
+        assert_eq!(
+            expanded("s*/**/?.mecha"),
+            ["sub/c.mecha", "sub/deep/d.mecha"]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_expand_directories() {
+        let root = tree("directories", FILES);
+        assert_eq!(
+            relative(&root, expand(std::slice::from_ref(&root)).unwrap()),
+            ["a.mecha", "ab.mecha", "sub/c.mecha", "sub/deep/d.mecha"]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_expand_without_matches() {
+        let root = tree("without-matches", FILES);
+        let err = expand(&[root.join("*.json")]).unwrap_err();
+        assert!(err.to_string().starts_with("no .mecha file matches"));
+
+        fs::create_dir_all(root.join("empty")).unwrap();
+        let err = expand(&[root.join("empty")]).unwrap_err();
+        assert!(err.to_string().starts_with("no .mecha file in"));
+
+        // a file is taken as it is, reading it tells whether it exists
+        let missing = root.join("missing.mecha");
+        assert_eq!(expand(std::slice::from_ref(&missing)).unwrap(), [missing]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_expand_duplicates() {
+        let root = tree("duplicates", FILES);
+        let sources = [
+            root.join("sub/c.mecha"),
+            root.join("sub"),
+            root.join("**/c.mecha"),
+            root.join("a.mecha"),
+        ];
+        assert_eq!(
+            relative(&root, expand(&sources).unwrap()),
+            ["sub/c.mecha", "sub/deep/d.mecha", "a.mecha"]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+}
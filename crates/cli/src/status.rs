@@ -0,0 +1,63 @@
+use std::fmt::{Display, Formatter};
+use std::process::ExitCode;
+
+/// How a command ended, each one exits with its own code so that scripts can tell them apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success = 0,
+    /// The command ran and found what it looks for, such as drift, unformatted files or
+    /// breaking changes
+    Findings = 1,
+    /// Anything else, wrong arguments included, which clap exits with as well
+    Failure = 2,
+    /// A source doesn't parse
+    Parse = 3,
+    /// A source parses, but it isn't a valid schema or can't be generated
+    Semantic = 4,
+    /// A file or a database can't be read or written
+    Io = 5,
+}
+
+impl Status {
+    /// The status an error ends a command with, one from [`fail`] knows its own, otherwise an
+    /// IO error anywhere in the chain makes it an IO failure
+    pub fn of(err: &anyhow::Error) -> Status {
+        if let Some(failed) = err.downcast_ref::<Failed>() {
+            return failed.status;
+        }
+        let io = err
+            .chain()
+            .any(|cause| cause.is::<std::io::Error>() || cause.is::<rusqlite::Error>());
+        if io { Status::Io } else { Status::Failure }
+    }
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        ExitCode::from(status as u8)
+    }
+}
+
+/// An error that ends a command with a given status
+#[derive(Debug)]
+pub struct Failed {
+    pub status: Status,
+    pub message: String,
+}
+
+/// An error that ends a command with a given status, for `return Err(fail(...))`
+pub fn fail(status: Status, message: impl Into<String>) -> anyhow::Error {
+    Failed {
+        status,
+        message: message.into(),
+    }
+    .into()
+}
+
+impl Display for Failed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Failed {}
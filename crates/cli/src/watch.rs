@@ -0,0 +1,84 @@
+use crate::Output;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+/// How often the files are looked at
+const POLL: Duration = Duration::from_millis(100);
+
+/// How long the files have to stay the same before a change is acted on, editors tend to
+/// write a file in several steps
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Runs a command over the files it works on, then again over the ones that changed each
+/// time some do, until interrupted. `files` lists what to watch, it's asked again on every
+/// poll since a project file may change it and a glob may match new files. A failing run
+/// is reported and waited out.
+pub fn watch(
+    out: &Output,
+    files: impl Fn() -> Result<Vec<PathBuf>>,
+    mut run: impl FnMut(&[PathBuf]) -> Result<()>,
+) -> Result<()> {
+    let mut watched = files()?;
+    let mut changed = watched.clone();
+    // taken before the run, so a file saved while it goes is seen changed after it
+    let mut before = stamps(&watched);
+
+    loop {
+        if let Err(err) = run(&changed) {
+            eprintln!("Error: {err:?}");
+        }
+
+        match files() {
+            Ok(files) => watched = files,
+            Err(err) => eprintln!("Error: {err:?}"),
+        }
+        out.status(format!("watching {} file(s) for changes", watched.len()));
+
+        let mut after = poll(&files, &mut watched);
+        while after == before {
+            sleep(POLL);
+            after = poll(&files, &mut watched);
+        }
+        loop {
+            sleep(DEBOUNCE);
+            let settled = poll(&files, &mut watched);
+            if settled == after {
+                break;
+            }
+            after = settled;
+        }
+
+        changed = after
+            .iter()
+            .filter(|(path, stamp)| before.get(*path) != Some(*stamp))
+            .map(|(path, _)| path.clone())
+            .collect();
+        before = after;
+    }
+}
+
+/// The stamps of the files watched now, the last list is kept while `files` fails, the
+/// failure is reported after the next run
+fn poll(files: &impl Fn() -> Result<Vec<PathBuf>>, watched: &mut Vec<PathBuf>) -> Stamps {
+    if let Ok(files) = files() {
+        *watched = files;
+    }
+    stamps(watched)
+}
+
+type Stamps = BTreeMap<PathBuf, Option<SystemTime>>;
+
+/// When each file was last modified, a missing one has no time
+fn stamps(files: &[PathBuf]) -> Stamps {
+    files
+        .iter()
+        .map(|path| {
+            let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok();
+            (path.clone(), modified)
+        })
+        .collect()
+}
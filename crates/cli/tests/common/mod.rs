@@ -0,0 +1,110 @@
+// each test file uses some of the helpers only
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+/// A directory under the system's temporary one that mecha runs in, removed once dropped
+pub struct Dir {
+    root: PathBuf,
+}
+
+/// How a run of mecha ended
+pub struct Run {
+    pub code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Dir {
+    pub fn new(name: &str) -> Dir {
+        let root = std::env::temp_dir().join(format!("mecha-cli-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        Dir { root }
+    }
+
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Writes a file, along with the directories it's in
+    pub fn write(&self, path: &str, content: &str) -> &Dir {
+        let path = self.path(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+        self
+    }
+
+    pub fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.path(path)).unwrap()
+    }
+
+    pub fn mecha(&self, args: &[&str]) -> Run {
+        self.mecha_in(".", args, None)
+    }
+
+    pub fn mecha_with_stdin(&self, args: &[&str], stdin: &str) -> Run {
+        self.mecha_in(".", args, Some(stdin))
+    }
+
+    /// Runs mecha from a directory below the root
+    pub fn mecha_in(&self, dir: &str, args: &[&str], stdin: Option<&str>) -> Run {
+        let mut child = self
+            .command(dir, args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut input = child.stdin.take().unwrap();
+        input.write_all(stdin.unwrap_or("").as_bytes()).unwrap();
+        drop(input);
+
+        let output = child.wait_with_output().unwrap();
+        Run {
+            code: output.status.code().unwrap(),
+            stdout: String::from_utf8(output.stdout).unwrap(),
+            stderr: String::from_utf8(output.stderr).unwrap(),
+        }
+    }
+
+    /// Starts mecha without waiting for it, it's killed once dropped
+    pub fn spawn(&self, args: &[&str]) -> Running {
+        let child = self
+            .command(".", args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        Running(child)
+    }
+
+    fn command(&self, dir: &str, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_mecha"));
+        command
+            .args(args)
+            .current_dir(self.root.join(dir))
+            .env_remove("RUST_BACKTRACE")
+            .env("NO_COLOR", "1");
+        command
+    }
+}
+
+impl Drop for Dir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+pub struct Running(Child);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::Dir;
+
+const OLD: &str = "table users {\n    id: uuid primary\n}\n\ntable posts {\n    user_id: uuid unique (ref => users.id)\n}\n";
+
+#[test]
+fn test_referenced_key_removed() {
+    let dir = Dir::new("referenced-key-removed");
+    dir.write("old.mecha", OLD)
+        .write("new.mecha", &OLD.replace("uuid unique", "uuid"));
+
+    let run = dir.mecha(&["check-compat", "new.mecha", "--against", "old.mecha"]);
+    assert_eq!(run.code, 1, "{}", run.stderr);
+    assert!(run.stderr.contains("[B006]"), "{}", run.stderr);
+
+    let run = dir.mecha(&[
+        "check-compat",
+        "new.mecha",
+        "--against",
+        "old.mecha",
+        "--rule",
+        "B006=off",
+    ]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+}
+
+#[test]
+fn test_explain_compat_rules() {
+    let dir = Dir::new("explain-compat-rules");
+    let run = dir.mecha(&["explain", "B006"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(
+        run.stdout
+            .contains("referenced-key-removed), a compatibility rule of `mecha check-compat`")
+    );
+
+    let run = dir.mecha(&["explain"]);
+    assert!(run.stdout.contains("B009  a foreign key is added"));
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::Dir;
+use rusqlite::Connection;
+
+const SCHEMA: &str = "table users {\n    id: int primary,\n    name: text\n}\n";
+
+/// A database deployed from the DDL mecha generates for the schema
+fn deploy(dir: &Dir) -> Connection {
+    dir.write("users.mecha", SCHEMA);
+    let run = dir.mecha(&["build", "-s", "users.mecha", "-t", "sqlite", "-o", "-"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+
+    let conn = Connection::open(dir.path("app.db")).unwrap();
+    conn.execute_batch(&run.stdout).unwrap();
+    conn
+}
+
+#[test]
+fn test_no_drift() {
+    let dir = Dir::new("no-drift");
+    deploy(&dir);
+
+    let run = dir.mecha(&["drift", "--sqlite", "app.db", "users.mecha"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(run.stdout.is_empty());
+    assert!(run.stderr.contains("no drift"));
+}
+
+#[test]
+fn test_drift() {
+    let dir = Dir::new("drift");
+    let conn = deploy(&dir);
+    conn.execute_batch("ALTER TABLE users ADD COLUMN age integer")
+        .unwrap();
+
+    let run = dir.mecha(&["drift", "--sqlite", "app.db", "users.mecha"]);
+    assert_eq!(run.code, 1, "{}", run.stderr);
+    assert!(run.stdout.contains("+ column users.age"), "{}", run.stdout);
+
+    let run = dir.mecha(&["drift", "--sqlite", "app.db", "users.mecha", "-f", "json"]);
+    assert_eq!(run.code, 1);
+    let drift: serde_json::Value = serde_json::from_str(&run.stdout).unwrap();
+    assert_eq!(drift["changes"][0]["kind"], "column_added");
+    assert_eq!(drift["changes"][0]["column"]["id"]["name"], "age");
+}
+
+#[test]
+fn test_missing_database() {
+    let dir = Dir::new("missing-database");
+    dir.write("users.mecha", SCHEMA);
+
+    let run = dir.mecha(&["drift", "--sqlite", "missing.db", "users.mecha"]);
+    assert_eq!(run.code, 5);
+    assert!(run.stderr.contains("unable to open missing.db"));
+}
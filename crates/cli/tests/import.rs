@@ -0,0 +1,53 @@
+mod common;
+
+use common::Dir;
+
+#[test]
+fn test_invalid_identifiers_renamed() {
+    let dir = Dir::new("import-invalid-identifiers");
+    dir.write(
+        "schema.sql",
+        "CREATE TABLE \"User Accounts\" (id bigserial PRIMARY KEY, \"Display Name\" text);\n",
+    );
+
+    let run = dir.mecha(&[
+        "import",
+        "schema.sql",
+        "--from",
+        "sql",
+        "-o",
+        "schema.mecha",
+    ]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(
+        run.stderr.contains("it's imported as 'display_name'"),
+        "{}",
+        run.stderr
+    );
+
+    let run = dir.mecha(&["check", "schema.mecha"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+}
+
+#[test]
+fn test_rejected_schema_not_written() {
+    let dir = Dir::new("import-rejected-schema");
+    dir.write(
+        "schema.sql",
+        "CREATE TABLE users (id int, email text);\n\
+         CREATE INDEX by_email ON users (email);\n\
+         CREATE INDEX by_email ON users (id);\n",
+    );
+
+    let run = dir.mecha(&[
+        "import",
+        "schema.sql",
+        "--from",
+        "sql",
+        "-o",
+        "schema.mecha",
+    ]);
+    assert_ne!(run.code, 0, "{}", run.stderr);
+    assert!(run.stderr.contains("nothing is written"), "{}", run.stderr);
+    assert!(!dir.path("schema.mecha").exists());
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::Dir;
+
+const SCHEMA: &str = "table users {\n    id: uuid primary\n}\n";
+
+#[test]
+fn test_verify_sqlite() {
+    let dir = Dir::new("verify-sqlite");
+    dir.write("users.mecha", SCHEMA);
+
+    let run = dir.mecha(&["migrate", "generate", "users.mecha", "-t", "sqlite"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    let run = dir.mecha(&["migrate", "verify", "users.mecha"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+
+    dir.write(
+        "users.mecha",
+        "table users {\n    id: uuid primary,\n    name: text\n}\n",
+    );
+    let run = dir.mecha(&["migrate", "verify", "users.mecha"]);
+    assert_eq!(run.code, 1);
+    assert!(run.stderr.contains("has changes without a migration"));
+}
+
+#[test]
+fn test_verify_other_dialects() {
+    let dir = Dir::new("verify-other-dialects");
+    dir.write("users.mecha", SCHEMA);
+
+    let run = dir.mecha(&["migrate", "generate", "users.mecha", "-t", "postgres"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    let run = dir.mecha(&["migrate", "verify", "users.mecha"]);
+    assert_eq!(run.code, 2);
+    assert!(
+        run.stderr
+            .contains("holds postgres migrations, which can't be verified")
+    );
+
+    let help = dir.mecha(&["migrate", "verify", "--help"]);
+    assert!(
+        help.stdout
+            .contains("Only sqlite migrations can be replayed")
+    );
+}
+
+#[test]
+fn test_first_migration_keeps_renames_quiet() {
+    let dir = Dir::new("first-migration-renames");
+    dir.write(
+        "users.mecha",
+        "@renamed_from(\"people\")\ntable users {\n    id: uuid primary\n}\n",
+    );
+
+    let run = dir.mecha(&["migrate", "generate", "users.mecha", "-t", "sqlite"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(!run.stderr.contains("can be removed"), "{}", run.stderr);
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::Dir;
+
+const PROJECT: &str = r#"entries = ["schema/store.mecha"]
+
+[[target]]
+dialect = "postgres"
+naming = "snake_case"
+out = "build/postgres"
+
+[[target]]
+out = "build/json"
+
+[lint]
+missing-primary-key = "error"
+
+[types.postgres]
+string = "varchar(255)"
+"#;
+
+const STORE: &str = "table users {\n    userId: uuid primary,\n    name: string\n}\n";
+
+fn project(name: &str) -> Dir {
+    let dir = Dir::new(name);
+    dir.write("mecha.toml", PROJECT)
+        .write("schema/store.mecha", STORE);
+    dir
+}
+
+#[test]
+fn test_build_every_target() {
+    let dir = project("build-every-target");
+    let run = dir.mecha(&["build"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+
+    let ddl = dir.read("build/postgres/store.sql");
+    assert!(ddl.contains(r#""user_id" uuid PRIMARY KEY"#), "{ddl}");
+    assert!(ddl.contains(r#""name" varchar(255)"#), "{ddl}");
+
+    let json: serde_json::Value = serde_json::from_str(&dir.read("build/json/store.json")).unwrap();
+    assert_eq!(json["tables"][0]["columns"][0]["id"]["name"], "userId");
+}
+
+#[test]
+fn test_names_colliding_in_a_convention() {
+    let dir = project("names-colliding");
+    dir.write(
+        "schema/store.mecha",
+        "table users {\n    userId: uuid primary,\n    user_id: uuid\n}\n",
+    );
+
+    let run = dir.mecha(&["build"]);
+    assert_eq!(run.code, 4);
+    assert!(
+        run.stderr
+            .contains("column user_id is named user_id in snake_case, like column userId"),
+        "{}",
+        run.stderr
+    );
+    assert!(!dir.path("build/postgres/store.sql").exists());
+}
+
+#[test]
+fn test_found_from_a_subdirectory() {
+    let dir = project("subdirectory");
+    let run = dir.mecha_in("schema", &["build"], None);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(dir.path("build/postgres/store.sql").exists());
+    assert!(!dir.path("schema/build").exists());
+}
+
+#[test]
+fn test_lint_levels() {
+    let dir = project("lint-levels");
+    dir.write("schema/store.mecha", "table users {\n    name: text\n}\n");
+
+    let run = dir.mecha(&["check"]);
+    assert_eq!(run.code, 1, "{}", run.stderr);
+    assert!(run.stderr.contains("L001"));
+
+    // `--rule` goes on top of the project's levels
+    let run = dir.mecha(&["check", "--rule", "L001=off"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+}
+
+#[test]
+fn test_invalid_project() {
+    let dir = project("invalid-project");
+    dir.write("mecha.toml", "entries = []\n");
+    let run = dir.mecha(&["build"]);
+    assert_eq!(run.code, 2);
+    assert!(run.stderr.contains("mecha.toml has no entries"));
+
+    dir.write(
+        "mecha.toml",
+        "entries = [\"schema/store.mecha\"]\nentry = 1\n",
+    );
+    let run = dir.mecha(&["check"]);
+    assert_eq!(run.code, 2);
+    assert!(
+        run.stderr
+            .contains("mecha.toml is not a valid project file")
+    );
+
+    dir.write(
+        "mecha.toml",
+        "entries = [\"schema/store.mecha\"]\n\n[lint]\nL001 = \"fatal\"\n",
+    );
+    let run = dir.mecha(&["check"]);
+    assert_eq!(run.code, 2);
+    assert!(run.stderr.contains("mecha.toml has invalid lint levels"));
+}
+
+#[test]
+fn test_sources_over_project() {
+    let dir = project("sources-over-project");
+    dir.write("other.mecha", "table others {\n    id: uuid primary\n}\n");
+
+    let run = dir.mecha(&["build", "-s", "other.mecha", "-t", "sqlite"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(dir.path("other.sql").exists());
+    assert!(!dir.path("build").exists());
+}
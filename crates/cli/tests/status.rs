@@ -0,0 +1,99 @@
+mod common;
+
+use common::Dir;
+
+const VALID: &str = "table users {\n    id: uuid primary,\n    name: text\n}\n";
+
+#[test]
+fn test_success() {
+    let dir = Dir::new("success");
+    dir.write("users.mecha", VALID);
+
+    let run = dir.mecha(&["check", "users.mecha"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(run.stderr.contains("users.mecha is valid"));
+
+    let run = dir.mecha(&["build", "-s", "users.mecha", "-t", "postgres"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(dir.read("users.sql").contains(r#"CREATE TABLE "users""#));
+}
+
+#[test]
+fn test_findings() {
+    let dir = Dir::new("findings");
+    dir.write("users.mecha", "table users {\n    name: text\n}\n");
+
+    // a warning alone doesn't fail
+    assert_eq!(dir.mecha(&["check", "users.mecha"]).code, 0);
+
+    let run = dir.mecha(&["check", "users.mecha", "--rule", "L001=error"]);
+    assert_eq!(run.code, 1, "{}", run.stderr);
+    assert!(run.stderr.contains("users.mecha breaks lint rules"));
+}
+
+#[test]
+fn test_failure() {
+    let dir = Dir::new("failure");
+    let run = dir.mecha(&["check"]);
+    assert_eq!(run.code, 2);
+    assert!(
+        run.stderr
+            .contains("no schema to check and no mecha.toml found")
+    );
+
+    // clap exits with 2 on wrong arguments as well
+    assert_eq!(dir.mecha(&["check", "--format", "xml"]).code, 2);
+    assert_eq!(dir.mecha(&["no-such-command"]).code, 2);
+}
+
+#[test]
+fn test_parse_error() {
+    let dir = Dir::new("parse-error");
+    dir.write("broken.mecha", "table users {\n    id uuid\n}\n");
+
+    let run = dir.mecha(&["check", "broken.mecha"]);
+    assert_eq!(run.code, 3);
+    assert!(run.stderr.contains("M0001"));
+    assert!(run.stderr.contains("broken.mecha doesn't parse"));
+
+    assert_eq!(dir.mecha(&["build", "-s", "broken.mecha"]).code, 3);
+}
+
+#[test]
+fn test_semantic_error() {
+    let dir = Dir::new("semantic-error");
+    dir.write(
+        "twice.mecha",
+        "table users {\n    id: uuid\n}\n\ntable users {\n    id: uuid\n}\n",
+    );
+
+    let run = dir.mecha(&["check", "twice.mecha"]);
+    assert_eq!(run.code, 4);
+    assert!(run.stderr.contains("M0002"));
+    assert!(run.stderr.contains("twice.mecha has errors"));
+
+    assert_eq!(dir.mecha(&["build", "-s", "twice.mecha"]).code, 4);
+}
+
+#[test]
+fn test_io_error() {
+    let dir = Dir::new("io-error");
+    let run = dir.mecha(&["check", "missing.mecha"]);
+    assert_eq!(run.code, 5);
+    assert!(run.stderr.contains("unable to read"));
+
+    dir.write("users.mecha", VALID);
+    let run = dir.mecha(&["build", "-s", "users.mecha", "-o", "no/such/dir"]);
+    assert_eq!(run.code, 5, "{}", run.stderr);
+}
+
+#[test]
+fn test_worst_status_of_several_files() {
+    let dir = Dir::new("several");
+    dir.write("users.mecha", VALID)
+        .write("broken.mecha", "table {\n");
+
+    let run = dir.mecha(&["check", "users.mecha", "broken.mecha"]);
+    assert_eq!(run.code, 3);
+    assert!(run.stderr.contains("users.mecha is valid"));
+}
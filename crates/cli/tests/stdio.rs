@@ -0,0 +1,96 @@
+mod common;
+
+use common::Dir;
+
+const SCHEMA: &str = "table users {\n    id: uuid primary,\n    name: text\n}\n";
+
+#[test]
+fn test_stdin_to_stdout() {
+    let dir = Dir::new("stdin-to-stdout");
+    let run = dir.mecha_with_stdin(&["build", "-s", "-", "-t", "postgres", "-o", "-"], SCHEMA);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(run.stdout.starts_with(r#"CREATE TABLE "users""#));
+    // status lines stay on the standard error, out of what is piped
+    assert!(!run.stdout.contains("compiled"));
+
+    let run = dir.mecha_with_stdin(&["build", "-s", "-", "-o", "-"], SCHEMA);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    let schema: serde_json::Value = serde_json::from_str(&run.stdout).unwrap();
+    assert_eq!(schema["tables"][0]["id"]["name"], "users");
+}
+
+#[test]
+fn test_file_to_stdout() {
+    let dir = Dir::new("file-to-stdout");
+    dir.write("users.mecha", &format!("@audited\n{SCHEMA}"));
+
+    let run = dir.mecha(&["build", "-s", "users.mecha", "-t", "sqlite", "-o", "-"]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(run.stdout.starts_with(r#"CREATE TABLE "users""#));
+    // the unknown annotation is a warning, printed on the standard error only
+    assert!(run.stderr.contains("M0015"));
+    assert!(!run.stdout.contains("M0015"));
+}
+
+#[test]
+fn test_stdin_to_file() {
+    let dir = Dir::new("stdin-to-file");
+    let run = dir.mecha_with_stdin(
+        &[
+            "build",
+            "-s",
+            "-",
+            "--stdin-filename",
+            "accounts.mecha",
+            "-t",
+            "sqlite",
+        ],
+        SCHEMA,
+    );
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(run.stdout.is_empty());
+    assert!(run.stderr.contains("accounts.sql is compiled"));
+    assert!(dir.read("accounts.sql").contains(r#"CREATE TABLE "users""#));
+}
+
+#[test]
+fn test_stdin_errors() {
+    let dir = Dir::new("stdin-errors");
+    let run = dir.mecha_with_stdin(
+        &[
+            "build",
+            "-s",
+            "-",
+            "--stdin-filename",
+            "users.mecha",
+            "-o",
+            "-",
+        ],
+        "table users {\n",
+    );
+    assert_eq!(run.code, 3);
+    assert!(run.stdout.is_empty());
+    assert!(run.stderr.contains("users.mecha"));
+
+    let run = dir.mecha_with_stdin(&["check", "-"], "table users {\n    id: uuid\n}\n");
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(run.stderr.contains("<stdin> is valid"));
+}
+
+#[test]
+fn test_one_output_on_stdout() {
+    let dir = Dir::new("one-output");
+    dir.write("a.mecha", "table a {\n    id: uuid primary\n}\n")
+        .write("b.mecha", "table b {\n    id: uuid primary\n}\n");
+
+    let run = dir.mecha(&["build", "-s", "a.mecha", "b.mecha", "-o", "-"]);
+    assert_eq!(run.code, 2);
+    assert!(run.stdout.is_empty());
+
+    let run = dir.mecha(&[
+        "build", "-s", "a.mecha", "b.mecha", "--merge", "-t", "sqlite", "-o", "-",
+    ]);
+    assert_eq!(run.code, 0, "{}", run.stderr);
+    assert!(run.stdout.contains(r#"CREATE TABLE "a""#));
+    assert!(run.stdout.contains(r#"CREATE TABLE "b""#));
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::Dir;
+use std::fs;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Waits for a file to hold some text, the watcher polls and debounces changes
+fn wait_for(dir: &Dir, path: &str, text: &str) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if fs::read_to_string(dir.path(path)).is_ok_and(|content| content.contains(text)) {
+            return;
+        }
+        assert!(Instant::now() < deadline, "{path} never held {text}");
+        sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn test_rebuild_on_change() {
+    let dir = Dir::new("rebuild-on-change");
+    dir.write("users.mecha", "table users {\n    id: uuid primary\n}\n");
+
+    let _watching = dir.spawn(&["build", "-s", "users.mecha", "-t", "sqlite", "-w"]);
+    wait_for(&dir, "users.sql", r#""id""#);
+
+    // a source that stops parsing is reported, and the watch goes on
+    dir.write("users.mecha", "table users {\n");
+    sleep(Duration::from_millis(500));
+    dir.write(
+        "users.mecha",
+        "table users {\n    id: uuid primary,\n    email: text\n}\n",
+    );
+    wait_for(&dir, "users.sql", r#""email""#);
+}
+
+#[test]
+fn test_new_source_matching_glob() {
+    let dir = Dir::new("new-source-matching-glob");
+    dir.write("users.mecha", "table users {\n    id: uuid primary\n}\n");
+
+    let _watching = dir.spawn(&["build", "-s", "*.mecha", "-t", "sqlite", "-w"]);
+    wait_for(&dir, "users.sql", r#""id""#);
+
+    // a file the glob didn't match when the watch started is built once it's there
+    dir.write("posts.mecha", "table posts {\n    id: uuid primary\n}\n");
+    wait_for(&dir, "posts.sql", r#""posts""#);
+}
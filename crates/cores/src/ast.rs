@@ -1,26 +1,275 @@
 use chumsky::span::SimpleSpan;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RefOperator {
     OneToMany,
     OneToOne,
     ManyToMany,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub enum Index {
-    Single(Ident, #[serde(skip)] SimpleSpan),
-    Composite(Vec<Ident>, #[serde(skip)] SimpleSpan),
+impl Display for RefOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefOperator::OneToMany => write!(f, "=>"),
+            RefOperator::OneToOne => write!(f, "=="),
+            RefOperator::ManyToMany => write!(f, "<>"),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IndexMethod {
+    Btree,
+    Hash,
+    Gin,
+    Gist,
+    Brin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexUsing {
+    pub method: IndexMethod,
+
+    #[serde(skip)]
+    pub span: SimpleSpan,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Index {
+    pub annotations: Vec<Annotation>,
+    pub name: Option<Ident>,
+    pub is_unique: bool,
+    pub columns: Vec<IndexColumn>,
+    pub using: Option<IndexUsing>,
+    pub include: Option<Vec<Ident>>,
+    pub predicate: Option<Expr>,
+
+    #[serde(skip)]
+    pub span: SimpleSpan,
+}
+
+impl Display for Index {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_unique {
+            write!(f, "unique ")?;
+        }
+        if let Some(name) = &self.name {
+            write!(f, "{} ", name.name)?;
+        }
+        let columns: Vec<String> = self.columns.iter().map(|c| c.to_string()).collect();
+        write!(f, "({})", columns.join(", "))?;
+        if let Some(using) = &self.using {
+            write!(f, " using {}", using.method)?;
+        }
+        if let Some(include) = &self.include {
+            let include: Vec<&str> = include.iter().map(|id| id.name.as_str()).collect();
+            write!(f, " include ({})", include.join(", "))?;
+        }
+        if let Some(predicate) = &self.predicate {
+            write!(f, " where {predicate}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexColumn {
+    pub id: Ident,
+    pub order: Option<SortOrder>,
+
+    #[serde(skip)]
+    pub span: SimpleSpan,
+}
+
+impl Display for IndexColumn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.order {
+            Some(SortOrder::Asc) => write!(f, "{} asc", self.id.name),
+            Some(SortOrder::Desc) => write!(f, "{} desc", self.id.name),
+            None => write!(f, "{}", self.id.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Literal {
+    Number(String),
+    String(String),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UnaryOp {
+    Not,
+    Neg,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BinaryOp {
+    And,
+    Or,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Concat,
+}
+
+impl Display for IndexMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndexMethod::Btree => write!(f, "btree"),
+            IndexMethod::Hash => write!(f, "hash"),
+            IndexMethod::Gin => write!(f, "gin"),
+            IndexMethod::Gist => write!(f, "gist"),
+            IndexMethod::Brin => write!(f, "brin"),
+        }
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::Number(value) => write!(f, "{value}"),
+            Literal::String(value) => {
+                let escaped = value.replace('\\', "\\\\").replace('\'', "\\'");
+                write!(f, "'{escaped}'")
+            }
+            Literal::Bool(value) => write!(f, "{value}"),
+            Literal::Null => write!(f, "null"),
+        }
+    }
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnaryOp::Not => write!(f, "not "),
+            UnaryOp::Neg => write!(f, "-"),
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryOp::And => write!(f, "and"),
+            BinaryOp::Or => write!(f, "or"),
+            BinaryOp::Eq => write!(f, "="),
+            BinaryOp::NotEq => write!(f, "!="),
+            BinaryOp::Lt => write!(f, "<"),
+            BinaryOp::LtEq => write!(f, "<="),
+            BinaryOp::Gt => write!(f, ">"),
+            BinaryOp::GtEq => write!(f, ">="),
+            BinaryOp::Add => write!(f, "+"),
+            BinaryOp::Sub => write!(f, "-"),
+            BinaryOp::Mul => write!(f, "*"),
+            BinaryOp::Div => write!(f, "/"),
+            BinaryOp::Concat => write!(f, "||"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Expr {
+    Column(Ident),
+    Literal(Literal, #[serde(skip)] SimpleSpan),
+    Unary {
+        op: UnaryOp,
+        expr: Box<Expr>,
+
+        #[serde(skip)]
+        span: SimpleSpan,
+    },
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+
+        #[serde(skip)]
+        span: SimpleSpan,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+
+        #[serde(skip)]
+        span: SimpleSpan,
+    },
+}
+
+impl Expr {
+    pub fn span(&self) -> SimpleSpan {
+        match self {
+            Expr::Column(id) => id.span,
+            Expr::Literal(_, span) => *span,
+            Expr::Unary { span, .. } | Expr::IsNull { span, .. } | Expr::Binary { span, .. } => {
+                *span
+            }
+        }
+    }
+
+    /// Collects every column referenced by the expression, in source order
+    pub fn columns(&self) -> Vec<&Ident> {
+        match self {
+            Expr::Column(id) => vec![id],
+            Expr::Literal(..) => vec![],
+            Expr::Unary { expr, .. } | Expr::IsNull { expr, .. } => expr.columns(),
+            Expr::Binary { lhs, rhs, .. } => {
+                let mut columns = lhs.columns();
+                columns.extend(rhs.columns());
+                columns
+            }
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Column(id) => write!(f, "{}", id.name),
+            Expr::Literal(literal, _) => write!(f, "{literal}"),
+            Expr::Unary { op, expr, .. } => write!(f, "{op}{expr}"),
+            Expr::IsNull { expr, negated, .. } => {
+                let not = if *negated { " not" } else { "" };
+                write!(f, "{expr} is{not} null")
+            }
+            Expr::Binary { op, lhs, rhs, .. } => write!(f, "({lhs} {op} {rhs})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ColumnAttribute {
     Primary,
     Unique,
 }
 
-#[derive(Debug, Serialize)]
+impl Display for ColumnAttribute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColumnAttribute::Primary => write!(f, "primary"),
+            ColumnAttribute::Unique => write!(f, "unique"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Schema {
     pub name: String,
     pub tables: Vec<TableDef>,
@@ -29,30 +278,78 @@ pub struct Schema {
     pub span: SimpleSpan,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableDef {
     pub id: Ident,
+    pub doc: Option<String>,
+    pub annotations: Vec<Annotation>,
     pub is_abstract: bool,
     pub extended_by: Option<Ident>,
     pub columns: Vec<ColumnDef>,
+    pub checks: Vec<CheckDef>,
     pub indexes: Option<Vec<Index>>,
 
     #[serde(skip)]
     pub span: SimpleSpan,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColumnDef {
     pub id: Ident,
+    pub doc: Option<String>,
+    pub annotations: Vec<Annotation>,
     pub typ: Ident,
     pub attribute: Option<ColumnAttribute>,
+    pub generated: Option<GeneratedDef>,
+    pub check: Option<CheckDef>,
     pub reference: Option<ReferenceDef>,
 
     #[serde(skip)]
     pub span: SimpleSpan,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GeneratedStorage {
+    Stored,
+    Virtual,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedDef {
+    pub expr: Expr,
+    /// `None` leaves the choice to the dialect's default
+    pub storage: Option<GeneratedStorage>,
+
+    #[serde(skip)]
+    pub span: SimpleSpan,
+}
+
+impl Display for GeneratedDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "generated ({})", self.expr)?;
+        match self.storage {
+            Some(GeneratedStorage::Stored) => write!(f, " stored"),
+            Some(GeneratedStorage::Virtual) => write!(f, " virtual"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckDef {
+    pub expr: Expr,
+
+    #[serde(skip)]
+    pub span: SimpleSpan,
+}
+
+impl Display for CheckDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "check ({})", self.expr)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReferenceDef {
     pub operator: RefOperator,
     pub table: Ident,
@@ -62,7 +359,66 @@ pub struct ReferenceDef {
     pub span: SimpleSpan,
 }
 
-#[derive(Debug, Clone, Serialize)]
+impl Display for ReferenceDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "(ref {} {}.{})",
+            self.operator, self.table.name, self.column.name
+        )
+    }
+}
+
+/// `@name`, `@name(value, ...)` or `@name(key = value, ...)`, where dotted names such as
+/// `db.postgres` namespace annotations meant for a single tool or backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub name: Ident,
+    pub args: Vec<AnnotationArg>,
+
+    #[serde(skip)]
+    pub span: SimpleSpan,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationArg {
+    pub key: Option<Ident>,
+    pub value: Literal,
+
+    #[serde(skip)]
+    pub span: SimpleSpan,
+}
+
+/// The previous name given with `@renamed_from("...")`, along with the annotation itself
+fn renamed_from(annotations: &[Annotation]) -> Option<(&str, &Annotation)> {
+    annotations
+        .iter()
+        .filter(|annotation| annotation.name.name == "renamed_from")
+        .find_map(|annotation| match annotation.args.as_slice() {
+            [
+                AnnotationArg {
+                    key: None,
+                    value: Literal::String(name),
+                    ..
+                },
+            ] => Some((name.as_str(), annotation)),
+            _ => None,
+        })
+}
+
+impl TableDef {
+    pub fn renamed_from(&self) -> Option<(&str, &Annotation)> {
+        renamed_from(&self.annotations)
+    }
+}
+
+impl ColumnDef {
+    pub fn renamed_from(&self) -> Option<(&str, &Annotation)> {
+        renamed_from(&self.annotations)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ident {
     pub name: String,
 
@@ -70,10 +426,44 @@ pub struct Ident {
     pub span: SimpleSpan,
 }
 
+/// What [`Schema::check`] and [`Schema::warnings`] report, each one has a stable code that
+/// `mecha explain` describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SemanticErr {
     TableRedeclaration,
     NonExistentParent,
     NonAbstractParent,
     CyclicRef,
     ColumnRedeclaration,
+    IndexRedeclaration,
+    RedundantIndex,
+    InvalidCheckColumn,
+    InvalidGeneratedColumn,
+    InvalidAnnotation,
+    InvalidRename,
+    NonExistentTable,
+    NonExistentColumn,
+    UnknownAnnotation,
+}
+
+impl SemanticErr {
+    /// Codes are never reused, a new kind of error takes the next free one
+    pub fn code(self) -> &'static str {
+        match self {
+            SemanticErr::TableRedeclaration => "M0002",
+            SemanticErr::NonExistentParent => "M0003",
+            SemanticErr::NonAbstractParent => "M0004",
+            SemanticErr::CyclicRef => "M0005",
+            SemanticErr::ColumnRedeclaration => "M0006",
+            SemanticErr::IndexRedeclaration => "M0007",
+            SemanticErr::RedundantIndex => "M0008",
+            SemanticErr::InvalidCheckColumn => "M0009",
+            SemanticErr::InvalidGeneratedColumn => "M0010",
+            SemanticErr::InvalidAnnotation => "M0011",
+            SemanticErr::InvalidRename => "M0012",
+            SemanticErr::NonExistentTable => "M0013",
+            SemanticErr::NonExistentColumn => "M0014",
+            SemanticErr::UnknownAnnotation => "M0015",
+        }
+    }
 }
@@ -1,55 +1,120 @@
-use crate::lexer::Token;
+use crate::ast::Schema;
+use crate::compat::{Location, Violation};
+use crate::ddl;
+use crate::ddl::Dialect;
+use crate::diagnostic::{self, Diagnostic};
 use crate::parser::parse;
+use crate::rules::Severity;
 use ariadne::{Color, Label, Report, ReportKind, Source};
-use chumsky::error::Rich;
-use chumsky::prelude::SimpleSpan;
-use std::fs;
-use std::path::Path;
-
-pub fn diagnose(src: &str, filename: &str, errs: Vec<Rich<Token, SimpleSpan>>) {
-    for err in errs {
-        Report::build(ReportKind::Error, (filename, err.span().into_range()))
-            .with_config(ariadne::Config::new().with_index_type(ariadne::IndexType::Byte))
-            .with_message(err.to_string())
-            .with_label(
-                Label::new((filename, err.span().into_range()))
-                    .with_message(err.reason().to_string())
-                    .with_color(Color::Red),
+use std::io::IsTerminal;
+
+/// Renders diagnostics against their source, the way a terminal shows them
+pub fn render(src: &str, filename: &str, diagnostics: &[Diagnostic], color: bool) -> String {
+    let mut out = Vec::new();
+    for diagnostic in diagnostics {
+        let (kind, label_color) = match diagnostic.severity {
+            diagnostic::Severity::Error => (ReportKind::Error, Color::Red),
+            diagnostic::Severity::Warning => (ReportKind::Warning, Color::Yellow),
+        };
+        let span = diagnostic.primary.span.into_range();
+
+        let mut report = Report::build(kind, (filename, span.clone()))
+            .with_config(
+                ariadne::Config::new()
+                    .with_index_type(ariadne::IndexType::Byte)
+                    .with_color(color),
             )
+            .with_message(&diagnostic.message)
+            .with_label(
+                Label::new((filename, span))
+                    .with_message(&diagnostic.primary.message)
+                    .with_color(label_color),
+            );
+        if let Some(code) = diagnostic.code {
+            report = report.with_code(code);
+        }
+        for label in &diagnostic.secondary {
+            report = report.with_label(
+                Label::new((filename, label.span.into_range()))
+                    .with_message(&label.message)
+                    .with_color(Color::Blue),
+            );
+        }
+        for note in &diagnostic.notes {
+            report = report.with_note(note);
+        }
+        if let Some(help) = &diagnostic.help {
+            report = report.with_help(help);
+        }
+
+        report
             .finish()
-            .print((filename, Source::from(src)))
-            .unwrap();
+            .write((filename, Source::from(src)), &mut out)
+            .expect("writing to a buffer doesn't fail");
     }
+    String::from_utf8_lossy(&out).into_owned()
 }
 
-pub fn compile(src: &str, input_filename: &str, output_dir: &str, output_name: &str) {
-    let mut ast = match parse(input_filename, src) {
-        Ok(ast) => ast,
-        Err(errs) => {
-            diagnose(src, input_filename, errs);
-            return;
-        }
-    };
+/// Prints diagnostics to the standard error, in color when it's a terminal
+pub fn report(src: &str, filename: &str, diagnostics: &[Diagnostic]) {
+    let color = std::io::stderr().is_terminal();
+    eprint!("{}", render(src, filename, diagnostics, color));
+}
+
+/// Prints compatibility violations against the source of the schema each one points into,
+/// a schema without source, such as one loaded from JSON, gets a plain message instead
+pub fn report_violations(
+    violations: &[Violation],
+    old: (&str, Option<&str>),
+    new: (&str, &str),
+    color: bool,
+) {
+    for violation in violations {
+        let (filename, src, span) = match violation.location {
+            Location::Old(span) => (old.0, old.1, span),
+            Location::New(span) => (new.0, Some(new.1), span),
+        };
 
-    if let Err(errs) = ast.check() {
-        diagnose(src, input_filename, errs);
-        return;
+        let Some(src) = src else {
+            eprintln!(
+                "{}[{}]: {} ({filename})",
+                violation.severity, violation.rule.code, violation.message
+            );
+            continue;
+        };
+
+        let diagnostic = match violation.severity {
+            Severity::Error => Diagnostic::error(span, &violation.message),
+            _ => Diagnostic::warning(span, &violation.message),
+        };
+        let diagnostic = diagnostic
+            .with_code(violation.rule.code)
+            .with_primary_message(violation.rule.summary);
+        eprint!("{}", render(src, filename, &[diagnostic], color));
     }
+}
 
-    let json_output = match serde_json::to_string(&ast) {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("failed to serialized the produced ast err={}", e);
-            return;
-        }
-    };
+/// Parses and checks a source, the schema comes with its warnings
+pub fn check(src: &str, filename: &str) -> Result<(Schema, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let mut ast = parse(filename, src)?;
+    ast.check()?;
+    let warnings = ast.warnings();
+    Ok((ast, warnings))
+}
 
-    let output_path = Path::new(output_dir).join(output_name);
+/// Checks a source and generates the DDL of a dialect, or the JSON of the schema without
+/// one. The output comes with the warnings of the source.
+pub fn compile(
+    src: &str,
+    filename: &str,
+    target: Option<Dialect>,
+) -> Result<(String, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let (ast, warnings) = check(src, filename)?;
 
-    match fs::write(&output_path, json_output) {
-        Ok(_) => println!("{output_name} is compiled in {output_dir}"),
-        Err(err) => {
-            eprintln!("error writing to {} err={}", output_path.display(), err)
-        }
+    let output = match target {
+        Some(dialect) => ddl::generate(&ast, dialect)
+            .map_err(|errs| warnings.iter().cloned().chain(errs).collect::<Vec<_>>())?,
+        None => serde_json::to_string(&ast).expect("a schema serializes to JSON"),
     };
+    Ok((output, warnings))
 }
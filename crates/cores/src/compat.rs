@@ -0,0 +1,347 @@
+use crate::ast::{ColumnAttribute, ColumnDef, RefOperator, Schema, TableDef};
+use crate::diff::{Change, diff};
+use crate::rules::{self, Config, Rule, Severity};
+use chumsky::span::SimpleSpan;
+use std::collections::HashMap;
+
+/// The kinds of schema changes that may break services running against the previous schema
+pub const RULES: &[Rule] = &[
+    Rule {
+        code: "B001",
+        name: "table-removed",
+        severity: Severity::Error,
+        summary: "a table is removed while services may still read or write it",
+    },
+    Rule {
+        code: "B002",
+        name: "table-renamed",
+        severity: Severity::Error,
+        summary: "a table is renamed while services may still use its previous name",
+    },
+    Rule {
+        code: "B003",
+        name: "column-removed",
+        severity: Severity::Error,
+        summary: "a column is removed while services may still read or write it",
+    },
+    Rule {
+        code: "B004",
+        name: "column-renamed",
+        severity: Severity::Error,
+        summary: "a column is renamed while services may still use its previous name",
+    },
+    Rule {
+        code: "B005",
+        name: "type-narrowed",
+        severity: Severity::Error,
+        summary: "a column type changes to one that can't hold every previous value",
+    },
+    Rule {
+        code: "B006",
+        name: "referenced-key-removed",
+        severity: Severity::Error,
+        summary: "a referenced column stops being primary or unique",
+    },
+    Rule {
+        code: "B007",
+        name: "column-generated",
+        severity: Severity::Error,
+        summary: "a column becomes generated, writing to it fails",
+    },
+    Rule {
+        code: "B008",
+        name: "constraint-added",
+        severity: Severity::Warning,
+        summary: "a check or unique constraint is added, existing rows or writes may violate it",
+    },
+    Rule {
+        code: "B009",
+        name: "reference-added",
+        severity: Severity::Warning,
+        summary: "a foreign key is added, existing rows or writes may violate it",
+    },
+];
+
+/// Looks a rule up by its code or its name, e.g. `B003` or `column-removed`
+pub fn rule(code_or_name: &str) -> Option<&'static Rule> {
+    rules::find(RULES, code_or_name)
+}
+
+/// Which of the two schemas a violation points into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Location {
+    Old(SimpleSpan),
+    New(SimpleSpan),
+}
+
+#[derive(Debug)]
+pub struct Violation {
+    pub rule: &'static Rule,
+    pub severity: Severity,
+    pub message: String,
+    pub location: Location,
+}
+
+/// Reports the changes from `old` to `new` that may break running services, rules turned
+/// off are left out. Both schemas went through [`Schema::check`].
+pub fn check(old: &Schema, new: &Schema, config: &Config) -> Vec<Violation> {
+    let new_tables: HashMap<&str, &TableDef> = new
+        .tables
+        .iter()
+        .filter(|t| !t.is_abstract)
+        .map(|t| (t.id.name.as_str(), t))
+        .collect();
+    let new_column = |table: &str, column: &str| -> &ColumnDef {
+        new_tables[table]
+            .columns
+            .iter()
+            .find(|c| c.id.name == column)
+            .expect("changed columns belong to the new schema")
+    };
+
+    let mut violations = Vec::new();
+    let mut violate = |code: &str, message: String, location: Location| {
+        let rule = rule(code).expect("compat rules are declared in RULES");
+        let severity = config.severity(rule);
+        if severity != Severity::Off {
+            violations.push(Violation {
+                rule,
+                severity,
+                message,
+                location,
+            });
+        }
+    };
+
+    for change in diff(old, new).changes {
+        match change {
+            Change::TableRemoved { table } => violate(
+                "B001",
+                format!("table '{}' is removed", table.id.name),
+                Location::Old(table.id.span),
+            ),
+            Change::TableRenamed { from, to } => violate(
+                "B002",
+                format!("table '{from}' is renamed to '{to}'"),
+                Location::New(new_tables[to.as_str()].id.span),
+            ),
+            Change::ColumnRemoved { table, column } => violate(
+                "B003",
+                format!("column '{table}.{}' is removed", column.id.name),
+                Location::Old(column.id.span),
+            ),
+            Change::ColumnRenamed { table, from, to } => violate(
+                "B004",
+                format!("column '{table}.{from}' is renamed to '{to}'"),
+                Location::New(new_column(&table, &to).id.span),
+            ),
+            Change::ColumnTypeChanged {
+                table,
+                column,
+                from,
+                to,
+            } if !widens(&from.name, &to.name) => violate(
+                "B005",
+                format!(
+                    "column '{table}.{column}' changes from '{}' to '{}', which may not hold every value",
+                    from.name, to.name
+                ),
+                Location::New(to.span),
+            ),
+            Change::ColumnAttributeChanged {
+                table,
+                column,
+                from,
+                to,
+            } => {
+                let column = new_column(&table, &column);
+                let is_referenced = column
+                    .reference
+                    .as_ref()
+                    .is_some_and(|r| !matches!(r.operator, RefOperator::ManyToMany));
+
+                if from.is_some() && to.is_none() && is_referenced {
+                    violate(
+                        "B006",
+                        format!(
+                            "column '{table}.{}' is referenced but is no longer {}",
+                            column.id.name,
+                            from.map(|a| a.to_string()).unwrap_or_default()
+                        ),
+                        Location::New(column.id.span),
+                    );
+                }
+                // a primary key is unique and not null, so it constrains more than unique
+                if matches!(
+                    (from, to),
+                    (None, Some(_))
+                        | (
+                            Some(ColumnAttribute::Unique),
+                            Some(ColumnAttribute::Primary)
+                        )
+                ) {
+                    violate(
+                        "B008",
+                        format!(
+                            "column '{table}.{}' becomes {}",
+                            column.id.name,
+                            to.map(|a| a.to_string()).unwrap_or_default()
+                        ),
+                        Location::New(column.id.span),
+                    );
+                }
+            }
+            Change::ColumnGeneratedChanged {
+                table,
+                column,
+                from: None,
+                to: Some(generated),
+            } => violate(
+                "B007",
+                format!("column '{table}.{}' becomes generated", column.id.name),
+                Location::New(generated.span),
+            ),
+            Change::ColumnCheckChanged {
+                table,
+                column,
+                to: Some(check),
+                ..
+            } => violate(
+                "B008",
+                format!("column '{table}.{column}' gets a new {check}"),
+                Location::New(check.span),
+            ),
+            Change::CheckAdded { table, check } => violate(
+                "B008",
+                format!("table '{table}' gets a new {check}"),
+                Location::New(check.span),
+            ),
+            Change::IndexAdded { table, name, index } if index.is_unique => violate(
+                "B008",
+                format!("unique index '{name}' is added on '{table}'"),
+                Location::New(index.span),
+            ),
+            Change::ReferenceAdded {
+                table,
+                column,
+                reference,
+            } => violate(
+                "B009",
+                format!("reference {reference} is added on '{table}.{column}'"),
+                Location::New(reference.span),
+            ),
+            _ => {}
+        }
+    }
+
+    violations
+}
+
+/// Whether every value of type `from` fits in type `to`
+fn widens(from: &str, to: &str) -> bool {
+    const WIDENING: &[&[&str]] = &[
+        &["smallint", "int", "integer", "bigint", "number", "numeric"],
+        &["char", "varchar", "string", "text"],
+    ];
+
+    WIDENING.iter().any(|types| {
+        let position = |typ| types.iter().position(|t| *t == typ);
+        matches!((position(from), position(to)), (Some(from), Some(to)) if from < to)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RULES, Violation, check};
+    use crate::ast::Schema;
+    use crate::parser::parse;
+    use crate::rules::{Config, Severity};
+
+    fn assert_violations(old: &str, new: &str, config: &Config) -> Vec<Violation> {
+        let old_schema = &mut parse("old.mecha", old).unwrap();
+        let new_schema = &mut parse("new.mecha", new).unwrap();
+        assert!(old_schema.check().is_ok());
+        assert!(new_schema.check().is_ok());
+        check(old_schema, new_schema, config)
+    }
+
+    fn codes(violations: &[Violation]) -> Vec<&str> {
+        violations.iter().map(|v| v.rule.code).collect()
+    }
+
+    #[test]
+    fn test_breaking_changes() {
+        let old = r"
+            table users {
+                id: uuid primary (ref => posts.user_id),
+                nickname: text,
+                age: bigint,
+                score: int
+            }
+
+            table posts {
+                user_id: uuid
+            }
+
+            table legacy {
+                id: uuid
+            }
+        ";
+        let new = r"
+            table users {
+                id: uuid (ref => posts.user_id),
+                age: int,
+                score: bigint check (score >= 0),
+                email: text check (email <> '')
+            }
+
+            table posts {
+                user_id: uuid
+            }
+        ";
+        let violations = assert_violations(old, new, &Config::new(RULES));
+        assert_eq!(codes(&violations), ["B001", "B003", "B006", "B005", "B008"]);
+        assert_eq!(violations[3].severity, Severity::Error);
+        assert_eq!(violations[4].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_configured_severities() {
+        let old = r"
+            table users {
+                id: uuid,
+                nickname: text
+            }
+        ";
+        let new = r"
+            table users {
+                id: uuid unique
+            }
+        ";
+        let mut config = Config::new(RULES);
+        assert!(config.set("column-removed=off").is_ok());
+        assert!(config.set("B008=error").is_ok());
+        assert!(config.set("B042=error").is_err());
+        assert!(config.set("B001=fatal").is_err());
+
+        let violations = assert_violations(old, new, &config);
+        assert_eq!(codes(&violations), ["B008"]);
+        assert_eq!(violations[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_against_json() {
+        let src = r"
+            table users {
+                id: uuid primary,
+                age: int check (age >= 0)
+            }
+        ";
+        let schema = &mut parse("old.mecha", src).unwrap();
+        assert!(schema.check().is_ok());
+
+        let json = serde_json::to_string(schema).unwrap();
+        let old: Schema = serde_json::from_str(&json).unwrap();
+        assert!(check(&old, schema, &Config::new(RULES)).is_empty());
+    }
+}
@@ -0,0 +1,562 @@
+use crate::lexer::{self, Token};
+use chumsky::span::SimpleSpan;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::mem::discriminant;
+
+/// The nodes of the concrete syntax tree. Expressions, annotation arguments and index
+/// items aren't broken down further, their tokens sit flat in their node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Schema,
+    Table,
+    Annotation,
+    Column,
+    Generated,
+    Check,
+    Reference,
+    Expr,
+    Indexes,
+    Index,
+    /// A token that doesn't fit where it's written
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken<'a> {
+    pub token: Token<'a>,
+    pub text: &'a str,
+    pub span: SimpleSpan,
+}
+
+impl SyntaxToken<'_> {
+    /// Whitespace and `//` comments, `///` docs belong to what they document. A doc that
+    /// documents nothing reads as a comment, as it does for the parser.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.token, Token::Whitespace | Token::Comment(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement<'a> {
+    Node(SyntaxNode<'a>),
+    Token(SyntaxToken<'a>),
+}
+
+/// A node of the tree, its text is every byte of the source it covers. Trivia between
+/// two nodes belongs to their parent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode<'a> {
+    kind: NodeKind,
+    span: SimpleSpan,
+    children: Vec<SyntaxElement<'a>>,
+}
+
+impl<'a> SyntaxNode<'a> {
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    pub fn span(&self) -> SimpleSpan {
+        self.span
+    }
+
+    pub fn children(&self) -> &[SyntaxElement<'a>] {
+        &self.children
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode<'a>> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Every token of the node in source order, trivia included
+    pub fn tokens(&self) -> Vec<&SyntaxToken<'a>> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'n>(&'n self, tokens: &mut Vec<&'n SyntaxToken<'a>>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    /// The token covering a byte offset, as an editor's cursor points at
+    pub fn token_at(&self, offset: usize) -> Option<&SyntaxToken<'a>> {
+        self.tokens()
+            .into_iter()
+            .find(|token| token.span.start <= offset && offset < token.span.end)
+    }
+
+    /// The name of a table or a column, its first identifier outside of its annotations
+    pub fn name(&self) -> Option<&SyntaxToken<'a>> {
+        self.ids().next()
+    }
+
+    /// The identifiers of the node itself, such as the name and the type of a column
+    pub fn ids(&self) -> impl Iterator<Item = &SyntaxToken<'a>> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) if matches!(token.token, Token::Id(_)) => Some(token),
+            _ => None,
+        })
+    }
+
+    /// The `///` lines documenting a table or a column
+    pub fn docs(&self) -> impl Iterator<Item = &'a str> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(SyntaxToken {
+                token: Token::DocComment(line),
+                ..
+            }) => Some(*line),
+            _ => None,
+        })
+    }
+}
+
+impl Display for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for token in self.tokens() {
+            f.write_str(token.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds the tree of any source, errors included, `to_string` gives the source back
+pub fn parse(src: &str) -> SyntaxNode<'_> {
+    let docs: HashSet<usize> = lexer::significant(src)
+        .into_iter()
+        .filter(|(token, _)| matches!(token, Token::DocComment(_)))
+        .map(|(_, span)| span.start)
+        .collect();
+
+    let mut tokens = Vec::new();
+    let mut end = 0;
+    for (token, span) in lexer::tokens(src) {
+        let token = match token {
+            Token::DocComment(_) if !docs.contains(&span.start) => {
+                Token::Comment(&src[span.start + 2..span.end])
+            }
+            token => token,
+        };
+        // the lexer only skips whitespace
+        if end < span.start {
+            tokens.push(SyntaxToken {
+                token: Token::Whitespace,
+                text: &src[end..span.start],
+                span: (end..span.start).into(),
+            });
+        }
+        end = span.end;
+        tokens.push(SyntaxToken {
+            token,
+            text: &src[span.into_range()],
+            span,
+        });
+    }
+    if end < src.len() {
+        tokens.push(SyntaxToken {
+            token: Token::Whitespace,
+            text: &src[end..],
+            span: (end..src.len()).into(),
+        });
+    }
+
+    let mut builder = Builder {
+        tokens,
+        pos: 0,
+        stack: Vec::new(),
+    };
+    builder.schema();
+    builder.root()
+}
+
+struct Builder<'a> {
+    tokens: Vec<SyntaxToken<'a>>,
+    pos: usize,
+    /// Open nodes with the offset they start at
+    stack: Vec<(NodeKind, usize, Vec<SyntaxElement<'a>>)>,
+}
+
+impl<'a> Builder<'a> {
+    fn schema(&mut self) {
+        self.start(NodeKind::Schema);
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::DocComment(_) | Token::At | Token::Abstract | Token::Table => self.table(),
+                _ => self.error(),
+            }
+        }
+        self.trivia();
+    }
+
+    fn table(&mut self) {
+        self.start(NodeKind::Table);
+        self.docs_and_annotations();
+        self.eat(&Token::Abstract);
+        self.eat(&Token::Table);
+        self.eat(&Token::Id(""));
+        if self.eat(&Token::Extends) {
+            self.eat(&Token::Id(""));
+        }
+        if self.eat(&Token::LeftBrace) {
+            self.table_body();
+            if self.at(&Token::Indexes) {
+                self.indexes();
+            }
+            self.eat(&Token::RightBrace);
+        }
+        self.finish();
+    }
+
+    fn table_body(&mut self) {
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::RightBrace | Token::Indexes | Token::Abstract | Token::Table => break,
+                Token::Comma => self.bump(),
+                // `check (` starts a table check, `check:` a column named check
+                Token::Id("check") if self.nth(1) == Some(&Token::LeftParen) => self.check(),
+                Token::DocComment(_) | Token::At | Token::Id(_) => self.column(),
+                _ => self.error(),
+            }
+        }
+    }
+
+    fn column(&mut self) {
+        self.start(NodeKind::Column);
+        self.docs_and_annotations();
+        self.eat(&Token::Id(""));
+        self.eat(&Token::Colon);
+        self.eat(&Token::Id(""));
+        if !self.eat(&Token::Primary) {
+            self.eat(&Token::Unique);
+        }
+        if self.at_keyword("generated") {
+            self.start(NodeKind::Generated);
+            self.bump();
+            self.parenthesized_expr();
+            if !self.eat_keyword("stored") {
+                self.eat_keyword("virtual");
+            }
+            self.finish();
+        }
+        if self.at_keyword("check") {
+            self.check();
+        }
+        if self.at(&Token::LeftParen) {
+            self.reference();
+        }
+        self.finish();
+    }
+
+    fn check(&mut self) {
+        self.start(NodeKind::Check);
+        self.bump();
+        self.parenthesized_expr();
+        self.finish();
+    }
+
+    fn reference(&mut self) {
+        self.start(NodeKind::Reference);
+        self.bump();
+        self.eat(&Token::Ref);
+        let _ = self.eat(&Token::RefOneToMany)
+            || self.eat(&Token::RefOneToOne)
+            || self.eat(&Token::RefManyToMany);
+        self.eat(&Token::Id(""));
+        self.eat(&Token::Dot);
+        self.eat(&Token::Id(""));
+        self.eat(&Token::RightParen);
+        self.finish();
+    }
+
+    /// Reads `( ... )` with the tokens in between as an expression, up to the closing
+    /// parenthesis or the end of the table
+    fn parenthesized_expr(&mut self) {
+        if !self.eat(&Token::LeftParen) {
+            return;
+        }
+        self.start(NodeKind::Expr);
+        let mut depth = 0;
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::RightParen if depth == 0 => break,
+                Token::RightBrace => break,
+                Token::LeftParen => depth += 1,
+                Token::RightParen => depth -= 1,
+                _ => {}
+            }
+            self.bump();
+        }
+        self.finish();
+        self.eat(&Token::RightParen);
+    }
+
+    fn docs_and_annotations(&mut self) {
+        while matches!(self.peek(), Some(Token::DocComment(_))) {
+            self.bump();
+        }
+        while self.at(&Token::At) {
+            self.start(NodeKind::Annotation);
+            self.bump();
+            self.eat(&Token::Id(""));
+            while self.eat(&Token::Dot) {
+                self.eat(&Token::Id(""));
+            }
+            if self.eat(&Token::LeftParen) {
+                while let Some(token) = self.peek().cloned() {
+                    if matches!(token, Token::RightParen | Token::RightBrace) {
+                        break;
+                    }
+                    self.bump();
+                }
+                self.eat(&Token::RightParen);
+            }
+            self.finish();
+        }
+    }
+
+    fn indexes(&mut self) {
+        self.start(NodeKind::Indexes);
+        self.bump();
+        if self.eat(&Token::LeftBrace) {
+            while let Some(token) = self.peek().cloned() {
+                match token {
+                    Token::RightBrace | Token::Abstract | Token::Table => break,
+                    Token::Comma => self.bump(),
+                    _ => self.index(),
+                }
+            }
+            self.eat(&Token::RightBrace);
+        }
+        self.finish();
+    }
+
+    fn index(&mut self) {
+        self.start(NodeKind::Index);
+        let mut depth = 0;
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Comma if depth == 0 => break,
+                Token::RightBrace | Token::Abstract | Token::Table => break,
+                Token::LeftParen => depth += 1,
+                Token::RightParen => depth -= 1,
+                _ => {}
+            }
+            self.bump();
+        }
+        self.finish();
+    }
+
+    fn error(&mut self) {
+        self.start(NodeKind::Error);
+        self.bump();
+        self.finish();
+    }
+
+    /// The next token that isn't trivia, without taking anything
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.nth(0)
+    }
+
+    /// The `n`th token ahead, trivia left out
+    fn nth(&self, n: usize) -> Option<&Token<'a>> {
+        self.tokens[self.pos..]
+            .iter()
+            .filter(|token| !token.is_trivia())
+            .nth(n)
+            .map(|token| &token.token)
+    }
+
+    /// Whether the next token is of the same kind, whatever it holds
+    fn at(&self, token: &Token) -> bool {
+        self.peek()
+            .is_some_and(|next| discriminant(next) == discriminant(token))
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.at(token);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    /// Whether the next token is a contextual keyword, see [`Token::Id`]
+    fn at_keyword(&self, word: &str) -> bool {
+        self.peek() == Some(&Token::Id(word))
+    }
+
+    fn eat_keyword(&mut self, word: &str) -> bool {
+        let found = self.at_keyword(word);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    /// Moves the next token into the open node, along with the trivia before it
+    fn bump(&mut self) {
+        self.trivia();
+        if let Some(token) = self.tokens.get(self.pos).cloned() {
+            self.push(SyntaxElement::Token(token));
+            self.pos += 1;
+        }
+    }
+
+    fn trivia(&mut self) {
+        while let Some(token) = self.tokens.get(self.pos).filter(|t| t.is_trivia()) {
+            let token = token.clone();
+            self.push(SyntaxElement::Token(token));
+            self.pos += 1;
+        }
+    }
+
+    /// Opens a node, the trivia before it stays with the parent
+    fn start(&mut self, kind: NodeKind) {
+        if !self.stack.is_empty() {
+            self.trivia();
+        }
+        let offset = self.tokens.get(self.pos).map_or_else(
+            || self.tokens.last().map_or(0, |token| token.span.end),
+            |token| token.span.start,
+        );
+        self.stack.push((kind, offset, Vec::new()));
+    }
+
+    fn finish(&mut self) {
+        let node = self.close();
+        self.push(SyntaxElement::Node(node));
+    }
+
+    fn close(&mut self) -> SyntaxNode<'a> {
+        let (kind, offset, children) = self.stack.pop().expect("a node is open");
+        let span_of = |child: &SyntaxElement| match child {
+            SyntaxElement::Node(node) => node.span,
+            SyntaxElement::Token(token) => token.span,
+        };
+        let start = children.first().map_or(offset, |c| span_of(c).start);
+        let end = children.last().map_or(offset, |c| span_of(c).end);
+        SyntaxNode {
+            kind,
+            span: (start..end).into(),
+            children,
+        }
+    }
+
+    fn push(&mut self, element: SyntaxElement<'a>) {
+        let (_, _, children) = self.stack.last_mut().expect("a node is open");
+        children.push(element);
+    }
+
+    fn root(mut self) -> SyntaxNode<'a> {
+        self.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NodeKind, parse};
+    use crate::lexer::Token;
+
+    fn assert_lossless(src: &str) {
+        assert_eq!(parse(src).to_string(), src);
+    }
+
+    #[test]
+    fn test_lossless() {
+        assert_lossless("");
+        assert_lossless("  // nothing but a comment\n");
+        assert_lossless(include_str!("../../../examples/store.mecha"));
+        assert_lossless(
+            "/// people\n@db.postgres(type = \"citext\")   table users extends base {\r\n\
+             \tid: uuid primary (ref => posts.user_id), // key\n\
+             check ((a + 1) > 0)\n\
+             indexes { unique idx (id desc) where id != '', id }\n}",
+        );
+        // broken sources keep every byte as well
+        assert_lossless("table { id: , } } ) @ table x { y: z check ( ");
+        assert_lossless("garbage $ ~ table users { id uuid indexes { ( } ");
+    }
+
+    #[test]
+    fn test_structure() {
+        let src = "// header\n@deprecated\ntable users {\n  /// key\n  id: uuid primary, // id\n  \
+                   check (id != ''),\n  email: text (ref => posts.email)\n  indexes { email }\n}\n";
+        let schema = parse(src);
+        assert_eq!(schema.kind(), NodeKind::Schema);
+        assert_eq!(schema.span(), (0..src.len()).into());
+
+        let tables: Vec<_> = schema.child_nodes().collect();
+        assert_eq!(tables.len(), 1);
+        let table = tables[0];
+        assert_eq!(table.kind(), NodeKind::Table);
+        assert_eq!(table.name().unwrap().text, "users");
+        // the comment before the table belongs to the schema
+        assert!(table.to_string().starts_with("@deprecated"));
+
+        let kinds: Vec<NodeKind> = table.child_nodes().map(|n| n.kind()).collect();
+        assert_eq!(
+            kinds,
+            [
+                NodeKind::Annotation,
+                NodeKind::Column,
+                NodeKind::Check,
+                NodeKind::Column,
+                NodeKind::Indexes
+            ]
+        );
+
+        let id = table.child_nodes().nth(1).unwrap();
+        assert_eq!(id.to_string(), "/// key\n  id: uuid primary");
+        assert_eq!(id.name().unwrap().text, "id");
+
+        let email = table.child_nodes().nth(3).unwrap();
+        let reference = email.child_nodes().next().unwrap();
+        assert_eq!(reference.kind(), NodeKind::Reference);
+        assert_eq!(reference.to_string(), "(ref => posts.email)");
+
+        let offset = src.find("posts").unwrap() + 2;
+        let token = schema.token_at(offset).unwrap();
+        assert_eq!(token.token, Token::Id("posts"));
+    }
+
+    #[test]
+    fn test_detached_doc_comments() {
+        let src = "table users {\n  /// key\n  id: uuid\n  /// documents nothing\n}\n";
+        let schema = parse(src);
+        assert_eq!(schema.to_string(), src);
+
+        let table = schema.child_nodes().next().unwrap();
+        let columns: Vec<_> = table.child_nodes().collect();
+        assert_eq!(columns.len(), 1);
+        assert_eq!(columns[0].docs().collect::<Vec<_>>(), [" key"]);
+
+        let tokens = table.tokens();
+        let detached = tokens
+            .iter()
+            .find(|token| token.text == "/// documents nothing")
+            .unwrap();
+        assert!(detached.is_trivia());
+        assert_eq!(detached.token, Token::Comment("/ documents nothing"));
+    }
+
+    #[test]
+    fn test_keywords_as_column_names() {
+        let schema = parse("table t { check: bool check (check), desc: text }");
+        let table = schema.child_nodes().next().unwrap();
+        let kinds: Vec<NodeKind> = table.child_nodes().map(|n| n.kind()).collect();
+        assert_eq!(kinds, [NodeKind::Column, NodeKind::Column]);
+
+        let check = table.child_nodes().next().unwrap();
+        assert_eq!(check.name().unwrap().text, "check");
+        assert_eq!(check.child_nodes().next().unwrap().kind(), NodeKind::Check);
+    }
+}
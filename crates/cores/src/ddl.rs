@@ -0,0 +1,871 @@
+use crate::ast::{
+    Annotation, AnnotationArg, BinaryOp, CheckDef, ColumnAttribute, ColumnDef, Expr, GeneratedDef,
+    GeneratedStorage, Ident, Index, IndexMethod, Literal, RefOperator, Schema, SortOrder, TableDef,
+    UnaryOp,
+};
+use crate::diagnostic::{Diagnostic, UNSUPPORTED_BY_DIALECT};
+use chumsky::span::SimpleSpan;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub(crate) type GenerateResult<T> = Result<T, Vec<Diagnostic>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dialect {
+    Postgres,
+    Mysql,
+    Sqlite,
+}
+
+impl Dialect {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dialect::Postgres => "postgres",
+            Dialect::Mysql => "mysql",
+            Dialect::Sqlite => "sqlite",
+        }
+    }
+
+    pub(crate) fn quote(&self, ident: &str) -> String {
+        match self {
+            Dialect::Mysql => format!("`{ident}`"),
+            Dialect::Postgres | Dialect::Sqlite => format!("\"{ident}\""),
+        }
+    }
+
+    /// Maps the well-known mecha types, anything else is emitted verbatim
+    pub(crate) fn column_type(&self, typ: &str) -> String {
+        let mapped = match (self, typ) {
+            (Dialect::Postgres, "string") => "varchar(255)",
+            (Dialect::Postgres, "timestampz") => "timestamptz",
+            (Dialect::Postgres, "number") => "numeric",
+            (Dialect::Mysql, "uuid") => "char(36)",
+            (Dialect::Mysql, "string") => "varchar(255)",
+            (Dialect::Mysql, "timestampz") => "timestamp",
+            (Dialect::Mysql, "number") => "decimal",
+            (Dialect::Sqlite, "uuid" | "string") => "text",
+            (Dialect::Sqlite, "timestamp" | "timestampz") => "text",
+            (Dialect::Sqlite, "number") => "numeric",
+            (Dialect::Sqlite, "boolean") => "integer",
+            (_, typ) => typ,
+        };
+        mapped.to_string()
+    }
+
+    fn supports_partial_index(&self) -> bool {
+        !matches!(self, Dialect::Mysql)
+    }
+
+    fn supports_included_columns(&self) -> bool {
+        matches!(self, Dialect::Postgres)
+    }
+
+    fn supports_index_method(&self, method: IndexMethod) -> bool {
+        match self {
+            Dialect::Postgres => true,
+            Dialect::Mysql => matches!(method, IndexMethod::Btree | IndexMethod::Hash),
+            Dialect::Sqlite => matches!(method, IndexMethod::Btree),
+        }
+    }
+
+    /// SQLite cannot add constraints to an existing table, so its foreign keys are inlined
+    pub(crate) fn inlines_foreign_keys(&self) -> bool {
+        matches!(self, Dialect::Sqlite)
+    }
+}
+
+pub(crate) struct ForeignKey<'s> {
+    pub(crate) name: String,
+    pub(crate) table: &'s str,
+    pub(crate) column: &'s str,
+    pub(crate) referenced_table: &'s str,
+    pub(crate) referenced_column: &'s str,
+}
+
+/// A check constraint with the name it's created under. Column checks are named after
+/// their column, table checks after the columns they use, e.g. `chk_events_starts_at_ends_at`
+pub(crate) struct CheckConstraint<'s> {
+    pub(crate) name: String,
+    pub(crate) column: Option<&'s str>,
+    pub(crate) check: &'s CheckDef,
+}
+
+/// Generates the DDL of a schema that went through [`Schema::check`], so inherited
+/// columns are already resolved into every concrete table
+pub fn generate(schema: &Schema, dialect: Dialect) -> GenerateResult<String> {
+    let tables: Vec<&TableDef> = schema.tables.iter().filter(|t| !t.is_abstract).collect();
+
+    let mut errs = Vec::new();
+    let mut statements = Vec::new();
+    let foreign_keys = collect_foreign_keys(&tables);
+
+    for table in &tables {
+        let inlined_foreign_keys = if dialect.inlines_foreign_keys() {
+            foreign_keys.get(table.id.name.as_str())
+        } else {
+            None
+        };
+        statements.push(create_table(table, inlined_foreign_keys, dialect));
+
+        if dialect == Dialect::Postgres {
+            statements.extend(comment_on(table, dialect));
+        }
+    }
+
+    for table in &tables {
+        for index in table.indexes.iter().flatten() {
+            match create_index(table, index, dialect) {
+                Ok(statement) => statements.push(statement),
+                Err(index_errs) => errs.extend(index_errs),
+            }
+        }
+    }
+
+    if !dialect.inlines_foreign_keys() {
+        for table in &tables {
+            for fk in foreign_keys
+                .get(table.id.name.as_str())
+                .into_iter()
+                .flatten()
+            {
+                statements.push(format!(
+                    "ALTER TABLE {} ADD {};",
+                    dialect.quote(fk.table),
+                    foreign_key_constraint(fk, dialect)
+                ));
+            }
+        }
+    }
+
+    if !errs.is_empty() {
+        return Err(errs);
+    }
+
+    Ok(statements.join("\n\n") + "\n")
+}
+
+/// Foreign keys grouped by the table owning the constraint. `a.x (ref => b.y)` reads as
+/// "one a to many b", so the constraint lives on `b.y` and points back to `a.x`.
+/// Many-to-many references need a join table and have no constraint of their own.
+pub(crate) fn collect_foreign_keys<'s>(
+    tables: &[&'s TableDef],
+) -> HashMap<&'s str, Vec<ForeignKey<'s>>> {
+    let concrete: HashMap<&str, &TableDef> =
+        tables.iter().map(|t| (t.id.name.as_str(), *t)).collect();
+    let mut foreign_keys: HashMap<&str, Vec<ForeignKey>> = HashMap::new();
+
+    for table in tables {
+        for column in &table.columns {
+            let Some(reference) = &column.reference else {
+                continue;
+            };
+
+            if matches!(reference.operator, RefOperator::ManyToMany) {
+                continue;
+            }
+
+            let Some(owner) = concrete.get(reference.table.name.as_str()) else {
+                continue;
+            };
+
+            let owner_name = owner.id.name.as_str();
+            foreign_keys
+                .entry(owner_name)
+                .or_default()
+                .push(ForeignKey {
+                    name: format!(
+                        "fk_{}_{}_{}",
+                        owner_name, reference.column.name, table.id.name
+                    ),
+                    table: owner_name,
+                    column: reference.column.name.as_str(),
+                    referenced_table: table.id.name.as_str(),
+                    referenced_column: column.id.name.as_str(),
+                });
+        }
+    }
+
+    foreign_keys
+}
+
+pub(crate) fn foreign_key_constraint(fk: &ForeignKey, dialect: Dialect) -> String {
+    format!(
+        "CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} ({})",
+        dialect.quote(&fk.name),
+        dialect.quote(fk.column),
+        dialect.quote(fk.referenced_table),
+        dialect.quote(fk.referenced_column)
+    )
+}
+
+pub(crate) fn create_table(
+    table: &TableDef,
+    foreign_keys: Option<&Vec<ForeignKey>>,
+    dialect: Dialect,
+) -> String {
+    let checks = check_constraints(table);
+    let mut lines: Vec<String> = table
+        .columns
+        .iter()
+        .map(|column| column_definition(table, column, dialect))
+        .collect();
+
+    for constraint in checks.iter().filter(|c| c.column.is_none()) {
+        lines.push(check_constraint(constraint, dialect));
+    }
+
+    for fk in foreign_keys.into_iter().flatten() {
+        lines.push(foreign_key_constraint(fk, dialect));
+    }
+
+    let mut statement = format!(
+        "CREATE TABLE {} (\n    {}\n)",
+        dialect.quote(&table.id.name),
+        lines.join(",\n    ")
+    );
+
+    match (&table.doc, dialect) {
+        (Some(doc), Dialect::Mysql) => {
+            statement.push_str(&format!(" COMMENT = {}", string_literal(doc, dialect)))
+        }
+        (Some(doc), Dialect::Sqlite) => {
+            statement = format!("{}\n{statement}", sql_comment(doc, ""))
+        }
+        _ => {}
+    }
+
+    statement.push(';');
+    statement
+}
+
+/// The definition of a column as it appears in `CREATE TABLE` or `ADD COLUMN`
+pub(crate) fn column_definition(table: &TableDef, column: &ColumnDef, dialect: Dialect) -> String {
+    let mut line = format!(
+        "{} {}",
+        dialect.quote(&column.id.name),
+        column_type(column, dialect)
+    );
+    if let Some(generated) = &column.generated {
+        line.push_str(&generated_column(generated, dialect));
+    }
+    match column.attribute {
+        Some(ColumnAttribute::Primary) => line.push_str(" PRIMARY KEY"),
+        Some(ColumnAttribute::Unique) => line.push_str(" UNIQUE"),
+        None => {}
+    }
+    if let Some(constraint) = check_constraints(table)
+        .iter()
+        .find(|c| c.column == Some(column.id.name.as_str()))
+    {
+        line.push(' ');
+        line.push_str(&check_constraint(constraint, dialect));
+    }
+    match (&column.doc, dialect) {
+        (Some(doc), Dialect::Mysql) => {
+            line.push_str(&format!(" COMMENT {}", string_literal(doc, dialect)))
+        }
+        (Some(doc), Dialect::Sqlite) => line = format!("{}\n    {line}", sql_comment(doc, "    ")),
+        _ => {}
+    }
+    line
+}
+
+/// Column checks come first, names clashing with an earlier one get a `_2`, `_3`... suffix
+pub(crate) fn check_constraints(table: &TableDef) -> Vec<CheckConstraint<'_>> {
+    let mut constraints: Vec<CheckConstraint> = Vec::new();
+
+    let column_checks = table.columns.iter().filter_map(|column| {
+        let check = column.check.as_ref()?;
+        Some((
+            vec![column.id.name.as_str()],
+            Some(column.id.name.as_str()),
+            check,
+        ))
+    });
+    let table_checks = table.checks.iter().map(|check| {
+        let mut columns: Vec<&str> = Vec::new();
+        for id in check.expr.columns() {
+            if !columns.contains(&id.name.as_str()) {
+                columns.push(id.name.as_str());
+            }
+        }
+        (columns, None, check)
+    });
+
+    for (columns, column, check) in column_checks.chain(table_checks) {
+        let mut base = format!("chk_{}", table.id.name);
+        for name in columns {
+            base.push('_');
+            base.push_str(name);
+        }
+
+        let mut name = base.clone();
+        let mut suffix = 1;
+        while constraints.iter().any(|c| c.name == name) {
+            suffix += 1;
+            name = format!("{base}_{suffix}");
+        }
+
+        constraints.push(CheckConstraint {
+            name,
+            column,
+            check,
+        });
+    }
+
+    constraints
+}
+
+pub(crate) fn check_constraint(constraint: &CheckConstraint, dialect: Dialect) -> String {
+    format!(
+        "CONSTRAINT {} CHECK ({})",
+        dialect.quote(&constraint.name),
+        expr(&constraint.check.expr, dialect, false)
+    )
+}
+
+/// Postgres keeps docs out of the table definition, as `COMMENT ON` statements
+fn comment_on(table: &TableDef, dialect: Dialect) -> Vec<String> {
+    let mut statements = Vec::new();
+
+    if let Some(doc) = &table.doc {
+        statements.push(format!(
+            "COMMENT ON TABLE {} IS {};",
+            dialect.quote(&table.id.name),
+            string_literal(doc, dialect)
+        ));
+    }
+
+    statements.extend(
+        table
+            .columns
+            .iter()
+            .filter_map(|column| comment_on_column(table, column, dialect)),
+    );
+
+    statements
+}
+
+pub(crate) fn comment_on_column(
+    table: &TableDef,
+    column: &ColumnDef,
+    dialect: Dialect,
+) -> Option<String> {
+    let doc = column.doc.as_ref()?;
+    Some(format!(
+        "COMMENT ON COLUMN {}.{} IS {};",
+        dialect.quote(&table.id.name),
+        dialect.quote(&column.id.name),
+        string_literal(doc, dialect)
+    ))
+}
+
+/// SQLite has no comment metadata, but keeps the `--` lines of a `CREATE TABLE` in its schema
+fn sql_comment(doc: &str, indent: &str) -> String {
+    let lines: Vec<String> = doc.lines().map(|line| format!("-- {line}")).collect();
+    lines.join(&format!("\n{indent}"))
+}
+
+/// The type of a column, unless overridden with `@db.<dialect>(type = "...")`
+pub(crate) fn column_type(column: &ColumnDef, dialect: Dialect) -> String {
+    let annotation_name = format!("db.{}", dialect.name());
+    let overridden = column
+        .annotations
+        .iter()
+        .filter(|annotation| annotation.name.name == annotation_name)
+        .flat_map(|annotation| &annotation.args)
+        .find_map(|arg| match (&arg.key, &arg.value) {
+            (Some(key), Literal::String(typ)) if key.name == "type" => Some(typ.clone()),
+            _ => None,
+        });
+
+    overridden.unwrap_or_else(|| dialect.column_type(&column.typ.name))
+}
+
+pub(crate) fn generated_column(generated: &GeneratedDef, dialect: Dialect) -> String {
+    // postgres only had stored generated columns before 18, so it's made explicit there
+    let storage = match (generated.storage, dialect) {
+        (Some(GeneratedStorage::Stored), _) | (None, Dialect::Postgres) => " STORED",
+        (Some(GeneratedStorage::Virtual), _) => " VIRTUAL",
+        (None, _) => "",
+    };
+    format!(
+        " GENERATED ALWAYS AS ({}){storage}",
+        expr(&generated.expr, dialect, false)
+    )
+}
+
+/// Maps the types of a schema to SQL types for a dialect, as if every column of a mapped
+/// type were annotated with `@db.<dialect>(type = "...")`. Columns that already carry the
+/// annotation keep it.
+pub fn map_types(schema: &mut Schema, dialect: Dialect, types: &HashMap<String, String>) {
+    let annotation_name = format!("db.{}", dialect.name());
+    for column in schema
+        .tables
+        .iter_mut()
+        .flat_map(|table| &mut table.columns)
+    {
+        let Some(sql) = types.get(&column.typ.name) else {
+            continue;
+        };
+        if column
+            .annotations
+            .iter()
+            .any(|annotation| annotation.name.name == annotation_name)
+        {
+            continue;
+        }
+
+        let span = column.typ.span;
+        column.annotations.push(Annotation {
+            name: Ident {
+                name: annotation_name.clone(),
+                span,
+            },
+            args: vec![AnnotationArg {
+                key: Some(Ident {
+                    name: "type".to_string(),
+                    span,
+                }),
+                value: Literal::String(sql.clone()),
+                span,
+            }],
+            span,
+        });
+    }
+}
+
+/// Name used for indexes declared without one, e.g. `idx_users_email_name`
+pub fn default_index_name(table: &TableDef, index: &Index) -> String {
+    let columns: Vec<&str> = index.columns.iter().map(|c| c.id.name.as_str()).collect();
+    format!("idx_{}_{}", table.id.name, columns.join("_"))
+}
+
+pub(crate) fn index_name(table: &TableDef, index: &Index) -> String {
+    match &index.name {
+        Some(name) => name.name.clone(),
+        None => default_index_name(table, index),
+    }
+}
+
+fn unsupported(span: SimpleSpan, message: String) -> Diagnostic {
+    Diagnostic::error(span, message).with_code(UNSUPPORTED_BY_DIALECT)
+}
+
+pub(crate) fn create_index(
+    table: &TableDef,
+    index: &Index,
+    dialect: Dialect,
+) -> GenerateResult<String> {
+    let name = index_name(table, index);
+
+    let columns: Vec<String> = index
+        .columns
+        .iter()
+        .map(|column| match column.order {
+            Some(SortOrder::Asc) => format!("{} ASC", dialect.quote(&column.id.name)),
+            Some(SortOrder::Desc) => format!("{} DESC", dialect.quote(&column.id.name)),
+            None => dialect.quote(&column.id.name),
+        })
+        .collect();
+
+    let mut errs = Vec::new();
+
+    let mut statement = format!(
+        "CREATE {}INDEX {} ON {}",
+        if index.is_unique { "UNIQUE " } else { "" },
+        dialect.quote(&name),
+        dialect.quote(&table.id.name),
+    );
+
+    if let Some(using) = &index.using
+        && !dialect.supports_index_method(using.method)
+    {
+        errs.push(unsupported(
+            using.span,
+            format!(
+                "index method '{}' is not supported by {}",
+                using.method,
+                dialect.name()
+            ),
+        ));
+    }
+
+    // postgres puts the method before the columns, mysql after them, sqlite has btree only
+    match (&index.using, dialect) {
+        (Some(using), Dialect::Postgres) => {
+            statement.push_str(&format!(" USING {} ({})", using.method, columns.join(", ")))
+        }
+        (Some(using), Dialect::Mysql) => statement.push_str(&format!(
+            " ({}) USING {}",
+            columns.join(", "),
+            using.method.to_string().to_uppercase()
+        )),
+        _ => statement.push_str(&format!(" ({})", columns.join(", "))),
+    }
+
+    if let Some(include) = &index.include {
+        if dialect.supports_included_columns() {
+            let include: Vec<String> = include.iter().map(|id| dialect.quote(&id.name)).collect();
+            statement.push_str(&format!(" INCLUDE ({})", include.join(", ")));
+        } else {
+            let span: SimpleSpan =
+                (include[0].span.start..include[include.len() - 1].span.end).into();
+            errs.push(unsupported(
+                span,
+                format!("included columns are not supported by {}", dialect.name()),
+            ));
+        }
+    }
+
+    if let Some(predicate) = &index.predicate {
+        if dialect.supports_partial_index() {
+            statement.push_str(" WHERE ");
+            statement.push_str(&expr(predicate, dialect, false));
+        } else {
+            errs.push(unsupported(
+                predicate.span(),
+                format!("partial indexes are not supported by {}", dialect.name()),
+            ));
+        }
+    }
+
+    if !errs.is_empty() {
+        return Err(errs);
+    }
+
+    statement.push(';');
+    Ok(statement)
+}
+
+/// Flattens `a || b || c` into `[a, b, c]`
+fn concat_operands(expr_: &Expr) -> Vec<&Expr> {
+    match expr_ {
+        Expr::Binary {
+            op: BinaryOp::Concat,
+            lhs,
+            rhs,
+            ..
+        } => {
+            let mut operands = concat_operands(lhs);
+            operands.extend(concat_operands(rhs));
+            operands
+        }
+        _ => vec![expr_],
+    }
+}
+
+pub(crate) fn string_literal(value: &str, dialect: Dialect) -> String {
+    let mut escaped = value.replace('\'', "''");
+    // mysql reads backslashes as escapes inside string literals by default
+    if dialect == Dialect::Mysql {
+        escaped = escaped.replace('\\', "\\\\");
+    }
+    format!("'{escaped}'")
+}
+
+fn literal(literal: &Literal, dialect: Dialect) -> String {
+    match literal {
+        Literal::Number(value) => value.clone(),
+        Literal::String(value) => string_literal(value, dialect),
+        Literal::Bool(true) => "TRUE".to_string(),
+        Literal::Bool(false) => "FALSE".to_string(),
+        Literal::Null => "NULL".to_string(),
+    }
+}
+
+pub(crate) fn expr(expr_: &Expr, dialect: Dialect, nested: bool) -> String {
+    match expr_ {
+        Expr::Column(id) => dialect.quote(&id.name),
+        Expr::Literal(value, _) => literal(value, dialect),
+        Expr::Unary {
+            op: UnaryOp::Not,
+            expr: inner,
+            ..
+        } => format!("NOT {}", expr(inner, dialect, true)),
+        Expr::Unary {
+            op: UnaryOp::Neg,
+            expr: inner,
+            ..
+        } => format!("-{}", expr(inner, dialect, true)),
+        Expr::IsNull {
+            expr: inner,
+            negated,
+            ..
+        } => {
+            let not = if *negated { " NOT" } else { "" };
+            format!("{} IS{not} NULL", expr(inner, dialect, true))
+        }
+        Expr::Binary {
+            op: BinaryOp::Concat,
+            ..
+        } if dialect == Dialect::Mysql => {
+            // `||` is a logical or in mysql unless PIPES_AS_CONCAT is set
+            let operands: Vec<String> = concat_operands(expr_)
+                .into_iter()
+                .map(|operand| expr(operand, dialect, false))
+                .collect();
+            format!("CONCAT({})", operands.join(", "))
+        }
+        Expr::Binary { op, lhs, rhs, .. } => {
+            let op = match op {
+                BinaryOp::And => "AND",
+                BinaryOp::Or => "OR",
+                BinaryOp::Eq => "=",
+                BinaryOp::NotEq => "<>",
+                BinaryOp::Lt => "<",
+                BinaryOp::LtEq => "<=",
+                BinaryOp::Gt => ">",
+                BinaryOp::GtEq => ">=",
+                BinaryOp::Add => "+",
+                BinaryOp::Sub => "-",
+                BinaryOp::Mul => "*",
+                BinaryOp::Div => "/",
+                BinaryOp::Concat => "||",
+            };
+            let rendered = format!(
+                "{} {op} {}",
+                expr(lhs, dialect, true),
+                expr(rhs, dialect, true)
+            );
+            if nested {
+                format!("({rendered})")
+            } else {
+                rendered
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dialect, generate, map_types};
+    use crate::codegen;
+    use crate::parser::parse;
+
+    fn assert_ddl(src: &str, dialect: Dialect) -> String {
+        let schema = &mut parse("test.mecha", src).unwrap();
+        if let Err(errs) = schema.check() {
+            codegen::report(src, "test.mecha", &errs);
+            panic!("schema validation failed unexpectedly");
+        }
+        match generate(schema, dialect) {
+            Ok(ddl) => ddl,
+            Err(errs) => {
+                codegen::report(src, "test.mecha", &errs);
+                panic!("ddl generation failed unexpectedly");
+            }
+        }
+    }
+
+    #[test]
+    fn test_named_unique_and_partial_indexes() {
+        let src = r"
+            table users {
+                id: uuid primary,
+                email: text,
+                created_at: timestamp,
+                deleted_at: timestamp
+                indexes {
+                    unique idx_email (email),
+                    (created_at desc),
+                    idx_live (email) where deleted_at is null
+                }
+            }
+        ";
+        let ddl = assert_ddl(src, Dialect::Postgres);
+        assert!(ddl.contains(r#"CREATE UNIQUE INDEX "idx_email" ON "users" ("email");"#));
+        assert!(
+            ddl.contains(r#"CREATE INDEX "idx_users_created_at" ON "users" ("created_at" DESC);"#)
+        );
+        assert!(ddl.contains(
+            r#"CREATE INDEX "idx_live" ON "users" ("email") WHERE "deleted_at" IS NULL;"#
+        ));
+    }
+
+    #[test]
+    fn test_partial_index_unsupported_by_mysql() {
+        let src = r"
+            table users {
+                email: text,
+                deleted_at: timestamp
+                indexes {
+                    email where deleted_at is null
+                }
+            }
+        ";
+        let schema = &mut parse("test.mecha", src).unwrap();
+        assert!(schema.check().is_ok());
+        assert!(generate(schema, Dialect::Mysql).is_err());
+    }
+
+    #[test]
+    fn test_foreign_keys() {
+        let src = r"
+            table users {
+                id: uuid primary (ref => posts.user_id)
+            }
+
+            table posts {
+                user_id: uuid
+            }
+        ";
+        let ddl = assert_ddl(src, Dialect::Postgres);
+        assert!(ddl.contains(
+            r#"ALTER TABLE "posts" ADD CONSTRAINT "fk_posts_user_id_users" FOREIGN KEY ("user_id") REFERENCES "users" ("id");"#
+        ));
+
+        let ddl = assert_ddl(src, Dialect::Sqlite);
+        assert!(!ddl.contains("ALTER TABLE"));
+        assert!(ddl.contains(r#"FOREIGN KEY ("user_id") REFERENCES "users" ("id")"#));
+    }
+
+    #[test]
+    fn test_index_method_and_included_columns() {
+        let src = r"
+            table documents {
+                id: uuid primary,
+                body: jsonb,
+                title: text
+                indexes {
+                    idx_body (body) using gin,
+                    idx_title (title) using btree include (id)
+                }
+            }
+        ";
+        let ddl = assert_ddl(src, Dialect::Postgres);
+        assert!(ddl.contains(r#"CREATE INDEX "idx_body" ON "documents" USING gin ("body");"#));
+        assert!(ddl.contains(
+            r#"CREATE INDEX "idx_title" ON "documents" USING btree ("title") INCLUDE ("id");"#
+        ));
+
+        let schema = &mut parse("test.mecha", src).unwrap();
+        assert!(schema.check().is_ok());
+        match generate(schema, Dialect::Mysql) {
+            // gin and include are both reported
+            Err(errs) => assert_eq!(errs.len(), 2),
+            Ok(_) => panic!("mysql should reject gin indexes and included columns"),
+        }
+    }
+
+    #[test]
+    fn test_check_constraints() {
+        let src = r"
+            table events {
+                starts_at: timestamp,
+                ends_at: timestamp,
+                title: text check (title <> 'it\'s'),
+                seats: number check (seats >= 0),
+                check (starts_at < ends_at and not seats = 0)
+            }
+        ";
+        let ddl = assert_ddl(src, Dialect::Postgres);
+        assert!(
+            ddl.contains(
+                r#""title" text CONSTRAINT "chk_events_title" CHECK ("title" <> 'it''s')"#
+            )
+        );
+        assert!(
+            ddl.contains(r#""seats" numeric CONSTRAINT "chk_events_seats" CHECK ("seats" >= 0)"#)
+        );
+        assert!(ddl.contains(
+            r#"CONSTRAINT "chk_events_starts_at_ends_at_seats" CHECK (("starts_at" < "ends_at") AND NOT ("seats" = 0))"#
+        ));
+
+        let ddl = assert_ddl(src, Dialect::Mysql);
+        assert!(ddl.contains("CHECK ((`starts_at` < `ends_at`) AND NOT (`seats` = 0))"));
+    }
+
+    #[test]
+    fn test_generated_columns() {
+        let src = r"
+            table users {
+                first_name: text,
+                last_name: text,
+                full_name: text generated (first_name || ' ' || last_name) stored
+            }
+        ";
+        let generated = |dialect| {
+            assert_ddl(src, dialect)
+                .lines()
+                .find(|line| line.contains("full_name"))
+                .unwrap()
+                .trim()
+                .to_string()
+        };
+        assert_eq!(
+            generated(Dialect::Postgres),
+            r#""full_name" text GENERATED ALWAYS AS (("first_name" || ' ') || "last_name") STORED"#
+        );
+        assert_eq!(
+            generated(Dialect::Mysql),
+            "`full_name` text GENERATED ALWAYS AS (CONCAT(`first_name`, ' ', `last_name`)) STORED"
+        );
+        assert_eq!(
+            generated(Dialect::Sqlite),
+            r#""full_name" text GENERATED ALWAYS AS (("first_name" || ' ') || "last_name") STORED"#
+        );
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let src = r"
+            /// registered users
+            table users {
+                /// it's unique
+                email: text
+            }
+        ";
+        let ddl = assert_ddl(src, Dialect::Postgres);
+        assert!(ddl.contains(r#"COMMENT ON TABLE "users" IS 'registered users';"#));
+        assert!(ddl.contains(r#"COMMENT ON COLUMN "users"."email" IS 'it''s unique';"#));
+
+        let ddl = assert_ddl(src, Dialect::Mysql);
+        assert!(ddl.contains("`email` text COMMENT 'it''s unique'"));
+        assert!(ddl.contains(") COMMENT = 'registered users';"));
+
+        let ddl = assert_ddl(src, Dialect::Sqlite);
+        assert!(ddl.starts_with("-- registered users\nCREATE TABLE"));
+        assert!(ddl.contains("    -- it's unique\n    \"email\" text"));
+    }
+
+    #[test]
+    fn test_dialect_type_annotation() {
+        let src = r#"
+            table users {
+                @db.postgres(type = "citext")
+                email: text
+            }
+        "#;
+        assert!(assert_ddl(src, Dialect::Postgres).contains(r#""email" citext"#));
+        assert!(assert_ddl(src, Dialect::Mysql).contains("`email` text"));
+    }
+
+    #[test]
+    fn test_map_types() {
+        let src = r#"
+            table users {
+                id: uuid primary,
+                @db.postgres(type = "citext")
+                email: string,
+                name: string
+            }
+        "#;
+        let schema = &mut parse("test.mecha", src).unwrap();
+        assert!(schema.check().is_ok());
+        let types = [("string".to_string(), "text".to_string())].into();
+        map_types(schema, Dialect::Postgres, &types);
+
+        let ddl = generate(schema, Dialect::Postgres).unwrap();
+        assert!(ddl.contains(r#""email" citext"#));
+        assert!(ddl.contains(r#""name" text"#));
+        assert!(ddl.contains(r#""id" uuid"#));
+        assert!(
+            generate(schema, Dialect::Mysql)
+                .unwrap()
+                .contains("`name` varchar(255)")
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::ast::SemanticErr;
+use crate::lexer::Token;
+use chumsky::error::Rich;
+use chumsky::span::SimpleSpan;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+/// The code of every parse error, what went wrong is in their message
+pub const SYNTAX_ERROR: &str = "M0001";
+
+/// The code of what a valid schema declares but the generated dialect can't express
+pub const UNSUPPORTED_BY_DIALECT: &str = "M0016";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Label {
+    pub span: SimpleSpan,
+    pub message: String,
+}
+
+/// A problem found in a source, kept as data so that a terminal, JSON or an editor can
+/// each render it their own way
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Identifies the kind of problem across releases, such as `B001`
+    pub code: Option<&'static str>,
+    pub message: String,
+    /// Where the problem is
+    pub primary: Label,
+    /// Related places, such as a previous declaration
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(span: SimpleSpan, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, span, message.into())
+    }
+
+    pub fn warning(span: SimpleSpan, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, span, message.into())
+    }
+
+    fn new(severity: Severity, span: SimpleSpan, message: String) -> Self {
+        Diagnostic {
+            severity,
+            code: None,
+            primary: Label {
+                span,
+                message: message.clone(),
+            },
+            message,
+            secondary: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Replaces the message of the primary label, which repeats the diagnostic's otherwise
+    pub fn with_primary_message(mut self, message: impl Into<String>) -> Self {
+        self.primary.message = message.into();
+        self
+    }
+
+    pub fn with_label(mut self, span: SimpleSpan, message: impl Into<String>) -> Self {
+        self.secondary.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl SemanticErr {
+    pub fn error(self, span: SimpleSpan, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::error(span, message).with_code(self.code())
+    }
+
+    pub fn warning(self, span: SimpleSpan, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::warning(span, message).with_code(self.code())
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// A point of a source, lines and columns count from 1 and columns count characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn at(src: &str, offset: usize) -> Self {
+        let offset = offset.min(src.len());
+        let before = &src[..offset];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Position {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Range {
+    pub fn of(src: &str, span: SimpleSpan) -> Self {
+        Range {
+            start: Position::at(src, span.start),
+            end: Position::at(src, span.end),
+        }
+    }
+}
+
+/// A label resolved against the file it points into
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Location {
+    pub file: String,
+    pub range: Range,
+    pub message: String,
+}
+
+impl Location {
+    fn of(file: &str, src: &str, label: &Label) -> Self {
+        Location {
+            file: file.to_string(),
+            range: Range::of(src, label.span),
+            message: label.message.clone(),
+        }
+    }
+}
+
+/// A diagnostic with its spans resolved into lines and columns, the form tools read
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LocatedDiagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub location: Location,
+    pub related: Vec<Location>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl LocatedDiagnostic {
+    pub fn new(file: &str, src: &str, diagnostic: &Diagnostic) -> Self {
+        LocatedDiagnostic {
+            severity: diagnostic.severity,
+            code: diagnostic.code,
+            message: diagnostic.message.clone(),
+            location: Location::of(file, src, &diagnostic.primary),
+            related: diagnostic
+                .secondary
+                .iter()
+                .map(|label| Location::of(file, src, label))
+                .collect(),
+            notes: diagnostic.notes.clone(),
+            help: diagnostic.help.clone(),
+        }
+    }
+}
+
+/// Parse errors, what the parser was in the middle of becomes a secondary label
+impl From<Rich<'_, Token<'_>>> for Diagnostic {
+    fn from(err: Rich<'_, Token<'_>>) -> Self {
+        let mut diagnostic = Diagnostic::error(*err.span(), err.to_string())
+            .with_code(SYNTAX_ERROR)
+            .with_primary_message(err.reason().to_string());
+        for (pattern, span) in err.contexts() {
+            diagnostic = diagnostic.with_label(*span, format!("while parsing {pattern}"));
+        }
+        diagnostic
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LocatedDiagnostic, Position, Severity};
+    use crate::codegen::{check, render};
+
+    #[test]
+    fn test_parse_error() {
+        let errs = check("table users { id uuid }", "test.mecha").unwrap_err();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].severity, Severity::Error);
+        assert_eq!(errs[0].primary.span, (17..21).into());
+    }
+
+    #[test]
+    fn test_related_locations() {
+        let src = "table users { id: uuid }\ntable users { name: text }";
+        let errs = check(src, "test.mecha").unwrap_err();
+        assert_eq!(errs[0].message, "table users is redeclared");
+        assert_eq!(errs[0].primary.span, (31..36).into());
+        assert_eq!(errs[0].secondary[0].span, (6..11).into());
+
+        let rendered = render(src, "test.mecha", &errs, false);
+        assert!(rendered.contains("table users is redeclared"));
+        assert!(rendered.contains("it's first declared here"));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let (_, warnings) = check("@audited table users { id: uuid }", "test.mecha").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, Severity::Warning);
+        assert_eq!(warnings[0].message, "unknown annotation '@audited'");
+    }
+
+    #[test]
+    fn test_located() {
+        let src = "// café\ntable users { id: uuid }\ntable users { name: text }";
+        let errs = check(src, "test.mecha").unwrap_err();
+        let located = LocatedDiagnostic::new("schemas/test.mecha", src, &errs[0]);
+
+        assert_eq!(located.location.file, "schemas/test.mecha");
+        assert_eq!(
+            located.location.range.start,
+            Position {
+                offset: 40,
+                line: 3,
+                column: 7
+            }
+        );
+        assert_eq!(located.location.range.end.column, 12);
+        assert_eq!(located.related[0].range.start.line, 2);
+        assert_eq!(located.related[0].message, "it's first declared here");
+
+        // columns count characters, é takes two bytes
+        assert_eq!(Position::at(src, 8).column, 8);
+    }
+}
@@ -0,0 +1,715 @@
+use crate::ast::{
+    Annotation, CheckDef, ColumnAttribute, ColumnDef, GeneratedDef, Ident, Index, ReferenceDef,
+    Schema, TableDef,
+};
+use crate::ddl::index_name;
+use crate::diagnostic::Diagnostic;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    TableAdded {
+        table: TableDef,
+    },
+    TableRemoved {
+        table: TableDef,
+    },
+    TableRenamed {
+        from: String,
+        to: String,
+    },
+    ColumnAdded {
+        table: String,
+        column: ColumnDef,
+    },
+    ColumnRenamed {
+        table: String,
+        from: String,
+        to: String,
+    },
+    ColumnRemoved {
+        table: String,
+        column: ColumnDef,
+    },
+    ColumnTypeChanged {
+        table: String,
+        column: String,
+        from: Ident,
+        to: Ident,
+    },
+    ColumnAttributeChanged {
+        table: String,
+        column: String,
+        from: Option<ColumnAttribute>,
+        to: Option<ColumnAttribute>,
+    },
+    ColumnGeneratedChanged {
+        table: String,
+        column: ColumnDef,
+        from: Option<GeneratedDef>,
+        to: Option<GeneratedDef>,
+    },
+    ColumnCheckChanged {
+        table: String,
+        column: String,
+        from: Option<CheckDef>,
+        to: Option<CheckDef>,
+    },
+    ReferenceAdded {
+        table: String,
+        column: String,
+        reference: ReferenceDef,
+    },
+    ReferenceRemoved {
+        table: String,
+        column: String,
+        reference: ReferenceDef,
+    },
+    IndexAdded {
+        table: String,
+        name: String,
+        index: Index,
+    },
+    IndexRemoved {
+        table: String,
+        name: String,
+        index: Index,
+    },
+    CheckAdded {
+        table: String,
+        check: CheckDef,
+    },
+    CheckRemoved {
+        table: String,
+        check: CheckDef,
+    },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+
+        match self {
+            Change::TableAdded { table } => write!(f, "+ table {}", table.id.name),
+            Change::TableRemoved { table } => write!(f, "- table {}", table.id.name),
+            Change::TableRenamed { from, to } => write!(f, "~ table {from} renamed to {to}"),
+            Change::ColumnAdded { table, column } => {
+                write!(
+                    f,
+                    "+ column {table}.{}: {}",
+                    column.id.name, column.typ.name
+                )
+            }
+            Change::ColumnRenamed { table, from, to } => {
+                write!(f, "~ column {table}.{from} renamed to {to}")
+            }
+            Change::ColumnRemoved { table, column } => {
+                write!(
+                    f,
+                    "- column {table}.{}: {}",
+                    column.id.name, column.typ.name
+                )
+            }
+            Change::ColumnTypeChanged {
+                table,
+                column,
+                from,
+                to,
+            } => write!(
+                f,
+                "~ column {table}.{column}: type {} -> {}",
+                from.name, to.name
+            ),
+            Change::ColumnAttributeChanged {
+                table,
+                column,
+                from,
+                to,
+            } => write!(
+                f,
+                "~ column {table}.{column}: attribute {} -> {}",
+                or_none(from.map(|a| a.to_string())),
+                or_none(to.map(|a| a.to_string()))
+            ),
+            Change::ColumnGeneratedChanged {
+                table,
+                column,
+                from,
+                to,
+            } => write!(
+                f,
+                "~ column {table}.{}: {} -> {}",
+                column.id.name,
+                or_none(from.as_ref().map(|g| g.to_string())),
+                or_none(to.as_ref().map(|g| g.to_string()))
+            ),
+            Change::ColumnCheckChanged {
+                table,
+                column,
+                from,
+                to,
+            } => write!(
+                f,
+                "~ column {table}.{column}: {} -> {}",
+                or_none(from.as_ref().map(|c| c.to_string())),
+                or_none(to.as_ref().map(|c| c.to_string()))
+            ),
+            Change::ReferenceAdded {
+                table,
+                column,
+                reference,
+            } => write!(f, "+ reference {table}.{column} {reference}"),
+            Change::ReferenceRemoved {
+                table,
+                column,
+                reference,
+            } => write!(f, "- reference {table}.{column} {reference}"),
+            Change::IndexAdded { table, name, index } => {
+                write!(f, "+ index {name} on {table} {index}")
+            }
+            Change::IndexRemoved { table, name, index } => {
+                write!(f, "- index {name} on {table} {index}")
+            }
+            Change::CheckAdded { table, check } => write!(f, "+ {check} on {table}"),
+            Change::CheckRemoved { table, check } => write!(f, "- {check} on {table}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaDiff {
+    pub changes: Vec<Change>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl Display for SchemaDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two schemas that went through [`Schema::check`]. Only concrete tables are
+/// compared, abstract ones don't exist on their own and their columns already live in
+/// the tables extending them.
+pub fn diff(old: &Schema, new: &Schema) -> SchemaDiff {
+    let old_tables = concrete_tables(old);
+    let new_tables = concrete_tables(new);
+
+    let mut changes = Vec::new();
+    let mut renamed: HashMap<&str, &str> = HashMap::new();
+
+    let removed: Vec<&TableDef> = old
+        .tables
+        .iter()
+        .filter(|t| !t.is_abstract && !new_tables.contains_key(t.id.name.as_str()))
+        .collect();
+    let added: Vec<&TableDef> = new
+        .tables
+        .iter()
+        .filter(|t| !t.is_abstract && !old_tables.contains_key(t.id.name.as_str()))
+        .collect();
+
+    // a table marked with @renamed_from takes the place of the removed table it names, the
+    // marker is read from the old schema as well so reverting a rename is a rename too
+    for new_table in &added {
+        if let Some((from, _)) = new_table.renamed_from()
+            && removed.iter().any(|old_table| old_table.id.name == from)
+        {
+            renamed.insert(from, new_table.id.name.as_str());
+        }
+    }
+    for old_table in &removed {
+        if let Some((from, _)) = old_table.renamed_from()
+            && added.iter().any(|new_table| new_table.id.name == from)
+        {
+            renamed.insert(old_table.id.name.as_str(), from);
+        }
+    }
+
+    for old_table in &removed {
+        if !renamed.contains_key(old_table.id.name.as_str()) {
+            changes.push(Change::TableRemoved {
+                table: (*old_table).clone(),
+            });
+        }
+    }
+
+    let renamed_to: HashMap<&str, &str> = renamed.iter().map(|(from, to)| (*to, *from)).collect();
+
+    for new_table in new.tables.iter().filter(|t| !t.is_abstract) {
+        let new_name = new_table.id.name.as_str();
+
+        if let Some(old_name) = renamed_to.get(new_name) {
+            changes.push(Change::TableRenamed {
+                from: old_name.to_string(),
+                to: new_name.to_string(),
+            });
+            diff_table(old_tables[old_name], new_table, &mut changes);
+            continue;
+        }
+
+        match old_tables.get(new_name) {
+            Some(old_table) => diff_table(old_table, new_table, &mut changes),
+            None => changes.push(Change::TableAdded {
+                table: new_table.clone(),
+            }),
+        }
+    }
+
+    SchemaDiff { changes }
+}
+
+/// Warnings for `@renamed_from` markers whose rename `old` already has, which happens once
+/// the migration with the rename has been applied: the target name is there and the old
+/// one isn't. Both schemas went through [`Schema::check`].
+pub fn applied_renames(old: &Schema, new: &Schema) -> Vec<Diagnostic> {
+    let old_tables = concrete_tables(old);
+    let mut warnings = Vec::new();
+    // inherited columns are copies sharing the span of the parent column
+    let mut warned: HashSet<(usize, usize)> = HashSet::new();
+
+    let mut warn = |annotation: &Annotation, message: String| {
+        if warned.insert((annotation.span.start, annotation.span.end)) {
+            warnings.push(Diagnostic::warning(
+                annotation.span,
+                format!("{message}, '@renamed_from' can be removed"),
+            ));
+        }
+    };
+
+    for new_table in new.tables.iter().filter(|t| !t.is_abstract) {
+        let name = new_table.id.name.as_str();
+        let mut old_table = old_tables.get(name);
+
+        if let Some((from, annotation)) = new_table.renamed_from() {
+            match old_tables.get(from) {
+                Some(table) => old_table = Some(table),
+                None if old_table.is_some() => warn(
+                    annotation,
+                    format!("table '{name}' is already renamed from '{from}'"),
+                ),
+                None => {}
+            }
+        }
+
+        for column in &new_table.columns {
+            let Some((from, annotation)) = column.renamed_from() else {
+                continue;
+            };
+            let has_column = |column_name: &str| {
+                old_table.is_some_and(|t| t.columns.iter().any(|c| c.id.name == column_name))
+            };
+            let column_name = column.id.name.as_str();
+            if has_column(column_name) && !has_column(from) {
+                warn(
+                    annotation,
+                    format!("column '{name}.{column_name}' is already renamed from '{from}'"),
+                );
+            }
+        }
+    }
+
+    warnings
+}
+
+fn concrete_tables(schema: &Schema) -> HashMap<&str, &TableDef> {
+    schema
+        .tables
+        .iter()
+        .filter(|t| !t.is_abstract)
+        .map(|t| (t.id.name.as_str(), t))
+        .collect()
+}
+
+/// Diffs the content of two versions of a table, changes are reported under the new name
+fn diff_table(old: &TableDef, new: &TableDef, changes: &mut Vec<Change>) {
+    let table = new.id.name.clone();
+
+    let old_columns: HashMap<&str, &ColumnDef> = old
+        .columns
+        .iter()
+        .map(|c| (c.id.name.as_str(), c))
+        .collect();
+    let new_columns: HashMap<&str, &ColumnDef> = new
+        .columns
+        .iter()
+        .map(|c| (c.id.name.as_str(), c))
+        .collect();
+
+    // columns marked with @renamed_from take the place of the old column they name, in
+    // either schema like tables do, new -> old
+    let mut renamed: HashMap<&str, &str> = HashMap::new();
+    for column in &new.columns {
+        if let Some((from, _)) = column.renamed_from()
+            && old_columns.contains_key(from)
+            && !new_columns.contains_key(from)
+        {
+            renamed.insert(column.id.name.as_str(), from);
+        }
+    }
+    for column in &old.columns {
+        if let Some((from, _)) = column.renamed_from()
+            && new_columns.contains_key(from)
+            && !old_columns.contains_key(from)
+        {
+            renamed.insert(from, column.id.name.as_str());
+        }
+    }
+    let renamed_from: HashSet<&str> = renamed.values().copied().collect();
+
+    for old_column in &old.columns {
+        let name = old_column.id.name.as_str();
+        if !new_columns.contains_key(name) && !renamed_from.contains(name) {
+            changes.push(Change::ColumnRemoved {
+                table: table.clone(),
+                column: old_column.clone(),
+            });
+        }
+    }
+
+    for new_column in &new.columns {
+        let name = new_column.id.name.as_str();
+        let old_name = renamed.get(name).copied().unwrap_or(name);
+        if old_name != name {
+            changes.push(Change::ColumnRenamed {
+                table: table.clone(),
+                from: old_name.to_string(),
+                to: name.to_string(),
+            });
+        }
+
+        match old_columns.get(old_name) {
+            Some(old_column) => diff_column(&table, old_column, new_column, changes),
+            None => changes.push(Change::ColumnAdded {
+                table: table.clone(),
+                column: new_column.clone(),
+            }),
+        }
+    }
+
+    let old_checks: HashSet<String> = old.checks.iter().map(|c| c.to_string()).collect();
+    let new_checks: HashSet<String> = new.checks.iter().map(|c| c.to_string()).collect();
+
+    for check in &old.checks {
+        if !new_checks.contains(&check.to_string()) {
+            changes.push(Change::CheckRemoved {
+                table: table.clone(),
+                check: check.clone(),
+            });
+        }
+    }
+
+    for check in &new.checks {
+        if !old_checks.contains(&check.to_string()) {
+            changes.push(Change::CheckAdded {
+                table: table.clone(),
+                check: check.clone(),
+            });
+        }
+    }
+
+    diff_indexes(old, new, changes);
+}
+
+fn diff_column(table: &str, old: &ColumnDef, new: &ColumnDef, changes: &mut Vec<Change>) {
+    let column = new.id.name.clone();
+
+    if old.typ.name != new.typ.name {
+        changes.push(Change::ColumnTypeChanged {
+            table: table.to_string(),
+            column: column.clone(),
+            from: old.typ.clone(),
+            to: new.typ.clone(),
+        });
+    }
+
+    if old.attribute != new.attribute {
+        changes.push(Change::ColumnAttributeChanged {
+            table: table.to_string(),
+            column: column.clone(),
+            from: old.attribute,
+            to: new.attribute,
+        });
+    }
+
+    let rendered = |value: Option<String>| value.unwrap_or_default();
+
+    if rendered(old.generated.as_ref().map(|g| g.to_string()))
+        != rendered(new.generated.as_ref().map(|g| g.to_string()))
+    {
+        changes.push(Change::ColumnGeneratedChanged {
+            table: table.to_string(),
+            column: new.clone(),
+            from: old.generated.clone(),
+            to: new.generated.clone(),
+        });
+    }
+
+    if rendered(old.check.as_ref().map(|c| c.to_string()))
+        != rendered(new.check.as_ref().map(|c| c.to_string()))
+    {
+        changes.push(Change::ColumnCheckChanged {
+            table: table.to_string(),
+            column: column.clone(),
+            from: old.check.clone(),
+            to: new.check.clone(),
+        });
+    }
+
+    if rendered(old.reference.as_ref().map(|r| r.to_string()))
+        != rendered(new.reference.as_ref().map(|r| r.to_string()))
+    {
+        if let Some(reference) = &old.reference {
+            changes.push(Change::ReferenceRemoved {
+                table: table.to_string(),
+                column: column.clone(),
+                reference: reference.clone(),
+            });
+        }
+        if let Some(reference) = &new.reference {
+            changes.push(Change::ReferenceAdded {
+                table: table.to_string(),
+                column,
+                reference: reference.clone(),
+            });
+        }
+    }
+}
+
+/// Indexes are matched by their DDL name, a redefined index shows up as removed then added
+fn diff_indexes(old: &TableDef, new: &TableDef, changes: &mut Vec<Change>) {
+    let table = new.id.name.clone();
+
+    let named = |table: &TableDef| -> Vec<(String, Index)> {
+        table
+            .indexes
+            .iter()
+            .flatten()
+            .map(|index| (index_name(table, index), index.clone()))
+            .collect()
+    };
+
+    let old_indexes = named(old);
+    let new_indexes = named(new);
+
+    let old_map: HashMap<&str, String> = old_indexes
+        .iter()
+        .map(|(name, index)| (name.as_str(), index.to_string()))
+        .collect();
+    let new_map: HashMap<&str, String> = new_indexes
+        .iter()
+        .map(|(name, index)| (name.as_str(), index.to_string()))
+        .collect();
+
+    for (name, index) in &old_indexes {
+        if new_map.get(name.as_str()) != Some(&index.to_string()) {
+            changes.push(Change::IndexRemoved {
+                table: table.clone(),
+                name: name.clone(),
+                index: index.clone(),
+            });
+        }
+    }
+
+    for (name, index) in &new_indexes {
+        if old_map.get(name.as_str()) != Some(&index.to_string()) {
+            changes.push(Change::IndexAdded {
+                table: table.clone(),
+                name: name.clone(),
+                index: index.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SchemaDiff, applied_renames, diff};
+    use crate::parser::parse;
+
+    fn assert_diff(old: &str, new: &str) -> SchemaDiff {
+        let old_schema = &mut parse("old.mecha", old).unwrap();
+        let new_schema = &mut parse("new.mecha", new).unwrap();
+        assert!(old_schema.check().is_ok());
+        assert!(new_schema.check().is_ok());
+        diff(old_schema, new_schema)
+    }
+
+    #[test]
+    fn test_identical_schemas() {
+        let src = r"
+            abstract table common {
+                created_at: timestamp
+            }
+
+            table users extends common {
+                id: uuid primary,
+                email: text
+                indexes {
+                    unique email
+                }
+            }
+        ";
+        assert!(assert_diff(src, src).is_empty());
+    }
+
+    #[test]
+    fn test_table_and_column_changes() {
+        let old = r"
+            table users {
+                id: uuid primary,
+                nickname: text,
+                age: int
+            }
+
+            table legacy {
+                id: uuid
+            }
+        ";
+        let new = r"
+            table users {
+                id: uuid primary,
+                age: number,
+                email: text unique
+            }
+
+            table posts {
+                id: uuid,
+                title: text
+            }
+        ";
+        let diff = assert_diff(old, new);
+        let rendered = diff.to_string();
+        assert_eq!(
+            rendered,
+            "- table legacy\n\
+             - column users.nickname: text\n\
+             ~ column users.age: type int -> number\n\
+             + column users.email: text\n\
+             + table posts\n"
+        );
+    }
+
+    #[test]
+    fn test_same_columns_are_not_a_rename() {
+        let old = r"
+            table audit_logs {
+                id: uuid,
+                note: text
+            }
+        ";
+        let new = r"
+            table invoices {
+                note: text,
+                id: uuid
+            }
+        ";
+        let diff = assert_diff(old, new);
+        assert_eq!(diff.to_string(), "- table audit_logs\n+ table invoices\n");
+    }
+
+    #[test]
+    fn test_index_and_reference_changes() {
+        let old = r"
+            table users {
+                id: uuid (ref => posts.user_id),
+                email: text
+                indexes {
+                    email
+                }
+            }
+
+            table posts {
+                user_id: uuid
+            }
+        ";
+        let new = r"
+            table users {
+                id: uuid,
+                email: text
+                indexes {
+                    unique email
+                }
+            }
+
+            table posts {
+                user_id: uuid
+            }
+        ";
+        let diff = assert_diff(old, new);
+        assert_eq!(
+            diff.to_string(),
+            "- reference users.id (ref => posts.user_id)\n\
+             - index idx_users_email on users (email)\n\
+             + index idx_users_email on users unique (email)\n"
+        );
+    }
+
+    #[test]
+    fn test_renamed_from() {
+        let old = r"
+            table people {
+                id: uuid,
+                mail: text
+            }
+        ";
+        let new = r#"
+            @renamed_from("people")
+            table users {
+                id: uuid,
+                @renamed_from("mail")
+                email: text,
+                age: int
+            }
+        "#;
+        let diff = assert_diff(old, new);
+        assert_eq!(
+            diff.to_string(),
+            "~ table people renamed to users\n\
+             ~ column users.mail renamed to email\n\
+             + column users.age: int\n"
+        );
+    }
+
+    #[test]
+    fn test_applied_renames() {
+        let src = r#"
+            @renamed_from("people")
+            table users {
+                id: uuid,
+                @renamed_from("mail")
+                email: text
+            }
+        "#;
+        let old_schema = &mut parse("old.mecha", src).unwrap();
+        let new_schema = &mut parse("new.mecha", src).unwrap();
+        assert!(old_schema.check().is_ok());
+        assert!(new_schema.check().is_ok());
+        assert_eq!(applied_renames(old_schema, new_schema).len(), 2);
+        assert!(diff(old_schema, new_schema).is_empty());
+
+        // against an empty snapshot nothing is renamed yet, nor already
+        let empty = crate::ast::Schema {
+            name: String::new(),
+            tables: Vec::new(),
+            span: Default::default(),
+        };
+        assert!(applied_renames(&empty, new_schema).is_empty());
+
+        // a column whose table is new too
+        let old_schema = &mut parse("old.mecha", "table posts {\n    id: uuid\n}").unwrap();
+        assert!(old_schema.check().is_ok());
+        assert!(applied_renames(old_schema, new_schema).is_empty());
+    }
+}
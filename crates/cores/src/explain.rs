@@ -0,0 +1,106 @@
+/// The long-form description of every diagnostic code, each with an erroneous example and
+/// a fixed one, in the order the codes were given out
+const EXPLANATIONS: &[(&str, &str)] = &[
+    ("M0001", include_str!("explain/M0001.md")),
+    ("M0002", include_str!("explain/M0002.md")),
+    ("M0003", include_str!("explain/M0003.md")),
+    ("M0004", include_str!("explain/M0004.md")),
+    ("M0005", include_str!("explain/M0005.md")),
+    ("M0006", include_str!("explain/M0006.md")),
+    ("M0007", include_str!("explain/M0007.md")),
+    ("M0008", include_str!("explain/M0008.md")),
+    ("M0009", include_str!("explain/M0009.md")),
+    ("M0010", include_str!("explain/M0010.md")),
+    ("M0011", include_str!("explain/M0011.md")),
+    ("M0012", include_str!("explain/M0012.md")),
+    ("M0013", include_str!("explain/M0013.md")),
+    ("M0014", include_str!("explain/M0014.md")),
+    ("M0015", include_str!("explain/M0015.md")),
+    ("M0016", include_str!("explain/M0016.md")),
+];
+
+/// Describes a code, which is matched regardless of case
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
+
+/// Every code along with its one-line summary
+pub fn summaries() -> impl Iterator<Item = (&'static str, &'static str)> {
+    EXPLANATIONS
+        .iter()
+        .map(|(code, explanation)| (*code, explanation.lines().next().unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EXPLANATIONS, explain};
+    use crate::ast::SemanticErr;
+    use crate::codegen::check;
+    use crate::ddl::{self, Dialect};
+    use crate::diagnostic::Diagnostic;
+    use clap::ValueEnum;
+
+    /// The mecha code blocks of an explanation
+    fn examples(explanation: &str) -> Vec<String> {
+        explanation
+            .split("```mecha\n")
+            .skip(1)
+            .map(|block| block.split("```").next().unwrap().to_string())
+            .collect()
+    }
+
+    /// What a source is reported for, by checking it and generating every dialect
+    fn diagnostics(src: &str) -> Vec<Diagnostic> {
+        match check(src, "example.mecha") {
+            Err(errs) => errs,
+            Ok((schema, mut warnings)) => {
+                for dialect in Dialect::value_variants() {
+                    if let Err(errs) = ddl::generate(&schema, *dialect) {
+                        warnings.extend(errs);
+                    }
+                }
+                warnings
+            }
+        }
+    }
+
+    #[test]
+    fn test_examples() {
+        for (code, explanation) in EXPLANATIONS {
+            let examples = examples(explanation);
+            assert_eq!(
+                examples.len(),
+                2,
+                "{code} has an erroneous and a fixed example"
+            );
+
+            let reported = diagnostics(&examples[0]);
+            assert!(
+                reported.iter().any(|d| d.code == Some(code)),
+                "the erroneous example of {code} is reported for {:?}",
+                reported.iter().map(|d| &d.message).collect::<Vec<_>>()
+            );
+
+            let reported = diagnostics(&examples[1]);
+            assert!(
+                reported.is_empty(),
+                "the fixed example of {code} is reported for {:?}",
+                reported.iter().map(|d| &d.message).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_every_code_is_explained() {
+        assert!(explain("m0003").is_some());
+        assert!(explain("M9999").is_none());
+
+        let codes: Vec<&str> = EXPLANATIONS.iter().map(|(code, _)| *code).collect();
+        assert_eq!(codes.len(), 16);
+        assert_eq!(SemanticErr::UnknownAnnotation.code(), "M0015");
+        assert!(codes.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
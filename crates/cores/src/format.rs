@@ -0,0 +1,629 @@
+use crate::ast::{
+    Annotation, BinaryOp, ColumnDef, Expr, GeneratedStorage, Index, Schema, TableDef, UnaryOp,
+};
+use crate::cst::{self, NodeKind, SyntaxElement, SyntaxNode, SyntaxToken};
+use crate::diagnostic::Diagnostic;
+use crate::lexer::Token;
+use crate::parser::parse;
+use chumsky::span::SimpleSpan;
+use std::collections::HashMap;
+
+const INDENT: &str = "    ";
+
+/// Renders a schema as .mecha source, tables separated by a blank line
+pub fn format(schema: &Schema) -> String {
+    render(schema, &Comments::none(schema))
+}
+
+/// Formats .mecha source into its canonical layout, keeping its `//` comments. The source
+/// has to parse, its errors are returned otherwise.
+pub fn format_source(filename: &str, src: &str) -> Result<String, Vec<Diagnostic>> {
+    let schema = parse(filename, src)?;
+    let comments = Comments::attach(&schema, src);
+    Ok(render(&schema, &comments))
+}
+
+/// A `//` comment on its own line, along with whether a blank line follows it
+struct Comment<'a> {
+    text: &'a str,
+    blank_after: bool,
+}
+
+#[derive(Default)]
+struct Attached<'a> {
+    leading: Vec<Comment<'a>>,
+    /// The comment ending the line, for a table the line of its opening brace
+    trailing: Option<&'a str>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Member {
+    Column(usize),
+    Check(usize),
+    Index(usize),
+}
+
+#[derive(Default)]
+struct TableComments<'a> {
+    table: Attached<'a>,
+    members: HashMap<Member, Attached<'a>>,
+    /// Comments after the last member, kept before the closing brace
+    dangling: Vec<Comment<'a>>,
+    closing: Option<&'a str>,
+}
+
+/// The comments of a source, attached to the tables and members they're written around
+/// since the AST leaves them out
+struct Comments<'a> {
+    tables: Vec<TableComments<'a>>,
+    /// Comments after the last table
+    end: Vec<Comment<'a>>,
+}
+
+impl<'a> Comments<'a> {
+    fn none(schema: &Schema) -> Self {
+        Comments {
+            tables: schema
+                .tables
+                .iter()
+                .map(|_| TableComments::default())
+                .collect(),
+            end: Vec::new(),
+        }
+    }
+
+    /// Walks the concrete syntax tree of the source, where every comment sits in the table
+    /// or member it's written in, or between them
+    fn attach(schema: &Schema, src: &'a str) -> Self {
+        let mut comments = Comments::none(schema);
+        let mut position = 0;
+        for child in cst::parse(src).children() {
+            match child {
+                SyntaxElement::Node(node) if node.kind() == NodeKind::Table => {
+                    if let Some(table) = comments.tables.get_mut(position) {
+                        table.attach(node, src);
+                    }
+                    position += 1;
+                }
+                SyntaxElement::Node(_) => {}
+                SyntaxElement::Token(token) => {
+                    let Some(comment) = comment(token, src) else {
+                        continue;
+                    };
+                    // a comment ending the line of a table's closing brace
+                    if let Some(previous) = position.checked_sub(1)
+                        && ends_line(src, token.span)
+                        && comments.tables[previous].closing.is_none()
+                    {
+                        comments.tables[previous].closing = Some(comment.text);
+                        continue;
+                    }
+                    match comments.tables.get_mut(position) {
+                        Some(table) => table.table.leading.push(comment),
+                        None => comments.end.push(comment),
+                    }
+                }
+            }
+        }
+        comments
+    }
+}
+
+impl<'a> TableComments<'a> {
+    /// Comments before the opening brace lead the table. Past it, a comment ending a line
+    /// trails what's before it on the line, and one on its own line leads the next member.
+    fn attach(&mut self, table: &SyntaxNode, src: &'a str) {
+        let mut opened = false;
+        let mut last: Option<Member> = None;
+        let mut pending: Vec<Comment<'a>> = Vec::new();
+        let (mut columns, mut checks) = (0, 0);
+
+        for child in table.children() {
+            let node = match child {
+                SyntaxElement::Token(token) if token.token == Token::LeftBrace => {
+                    opened = true;
+                    continue;
+                }
+                SyntaxElement::Token(token) => {
+                    if let Some(comment) = comment(token, src) {
+                        if !opened {
+                            self.table.leading.push(comment);
+                        } else if !(ends_line(src, token.span) && self.trail(last, comment.text)) {
+                            pending.push(comment);
+                        }
+                    }
+                    continue;
+                }
+                SyntaxElement::Node(node) => node,
+            };
+
+            let member = match node.kind() {
+                NodeKind::Column => {
+                    columns += 1;
+                    Member::Column(columns - 1)
+                }
+                NodeKind::Check => {
+                    checks += 1;
+                    Member::Check(checks - 1)
+                }
+                NodeKind::Indexes => {
+                    self.attach_indexes(node, &mut pending, src);
+                    continue;
+                }
+                // the annotations of the table
+                _ => {
+                    self.table.leading.extend(within(node, src));
+                    continue;
+                }
+            };
+            self.lead(member, &mut pending, node, src);
+            last = Some(member);
+        }
+
+        // comments after the last member stay before the closing brace
+        self.dangling.append(&mut pending);
+    }
+
+    fn attach_indexes(
+        &mut self,
+        indexes: &SyntaxNode,
+        pending: &mut Vec<Comment<'a>>,
+        src: &'a str,
+    ) {
+        let mut last: Option<Member> = None;
+        let mut count = 0;
+
+        for child in indexes.children() {
+            match child {
+                SyntaxElement::Token(token) => {
+                    if let Some(comment) = comment(token, src)
+                        && !(last.is_some()
+                            && ends_line(src, token.span)
+                            && self.trail(last, comment.text))
+                    {
+                        pending.push(comment);
+                    }
+                }
+                SyntaxElement::Node(node) => {
+                    let member = Member::Index(count);
+                    count += 1;
+                    self.lead(member, pending, node, src);
+                    last = Some(member);
+                }
+            }
+        }
+    }
+
+    /// The comments waiting for a member lead it, along with the ones written within it,
+    /// such as one splitting a column over lines
+    fn lead(
+        &mut self,
+        member: Member,
+        pending: &mut Vec<Comment<'a>>,
+        node: &SyntaxNode,
+        src: &'a str,
+    ) {
+        let leading = &mut self.members.entry(member).or_default().leading;
+        leading.append(pending);
+        leading.extend(within(node, src));
+    }
+
+    /// Trails the last member, or the opening brace of the table before any, `false` when
+    /// it already has a comment
+    fn trail(&mut self, last: Option<Member>, text: &'a str) -> bool {
+        let trailing = match last {
+            Some(member) => &mut self.members.entry(member).or_default().trailing,
+            None => &mut self.table.trailing,
+        };
+        trailing.replace(text).is_none()
+    }
+}
+
+/// The comment of a token, `//` left out
+fn comment<'a>(token: &SyntaxToken, src: &'a str) -> Option<Comment<'a>> {
+    if !matches!(token.token, Token::Comment(_)) {
+        return None;
+    }
+    let span = token.span;
+    let rest = &src[span.end..];
+    let gap = &rest[..rest.len() - rest.trim_start().len()];
+    Some(Comment {
+        text: src[span.start + 2..span.end].trim_end(),
+        blank_after: gap.matches('\n').count() > 1,
+    })
+}
+
+fn within<'a>(node: &SyntaxNode, src: &'a str) -> Vec<Comment<'a>> {
+    node.tokens()
+        .into_iter()
+        .filter_map(|token| comment(token, src))
+        .collect()
+}
+
+/// Whether something comes before the token on its line
+fn ends_line(src: &str, span: SimpleSpan) -> bool {
+    let line_start = src[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    !src[line_start..span.start].trim().is_empty()
+}
+
+fn render(schema: &Schema, comments: &Comments) -> String {
+    let tables: Vec<String> = schema
+        .tables
+        .iter()
+        .zip(&comments.tables)
+        .map(|(t, comments)| table(t, comments))
+        .collect();
+    let mut out = tables.join("\n");
+    if !comments.end.is_empty() {
+        out.push('\n');
+        leading(&mut out, &comments.end, "", false);
+    }
+    out
+}
+
+fn table(table: &TableDef, comments: &TableComments) -> String {
+    let mut out = String::new();
+    leading(&mut out, &comments.table.leading, "", true);
+    doc(&mut out, table.doc.as_deref(), "");
+    annotations(&mut out, &table.annotations, "");
+
+    if table.is_abstract {
+        out.push_str("abstract ");
+    }
+    out.push_str(&format!("table {}", table.id.name));
+    if let Some(parent) = &table.extended_by {
+        out.push_str(&format!(" extends {}", parent.name));
+    }
+    out.push_str(" {");
+    trailing(&mut out, comments.table.trailing);
+    out.push('\n');
+
+    // types line up across the columns of a table
+    let width = table
+        .columns
+        .iter()
+        .map(|c| c.id.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut items: Vec<(Member, String)> = table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (Member::Column(i), column(c, width)))
+        .collect();
+    items.extend(table.checks.iter().enumerate().map(|(i, check)| {
+        (
+            Member::Check(i),
+            format!("{INDENT}check ({})", expr(&check.expr)),
+        )
+    }));
+    members_list(&mut out, items, comments, INDENT);
+
+    if let Some(indexes) = &table.indexes {
+        out.push_str(&format!("{INDENT}indexes {{\n"));
+        let indent = format!("{INDENT}{INDENT}");
+        let items = indexes
+            .iter()
+            .enumerate()
+            .map(|(i, index)| (Member::Index(i), format!("{indent}{}", self::index(index))))
+            .collect();
+        members_list(&mut out, items, comments, &indent);
+        out.push_str(&format!("{INDENT}}}\n"));
+    }
+
+    leading(&mut out, &comments.dangling, INDENT, false);
+    out.push('}');
+    trailing(&mut out, comments.closing);
+    out.push('\n');
+    out
+}
+
+/// Writes comma separated members, each between its leading and trailing comments
+fn members_list(
+    out: &mut String,
+    items: Vec<(Member, String)>,
+    comments: &TableComments,
+    indent: &str,
+) {
+    let count = items.len();
+    for (i, (member, item)) in items.into_iter().enumerate() {
+        let attached = comments.members.get(&member);
+        if let Some(attached) = attached {
+            leading(out, &attached.leading, indent, true);
+        }
+        out.push_str(&item);
+        if i + 1 < count {
+            out.push(',');
+        }
+        trailing(out, attached.and_then(|a| a.trailing));
+        out.push('\n');
+    }
+}
+
+fn leading(out: &mut String, comments: &[Comment], indent: &str, keep_blank_lines: bool) {
+    for comment in comments {
+        out.push_str(&format!("{indent}//{}\n", comment.text));
+        if keep_blank_lines && comment.blank_after {
+            out.push('\n');
+        }
+    }
+}
+
+fn trailing(out: &mut String, comment: Option<&str>) {
+    if let Some(comment) = comment {
+        out.push_str(&format!(" //{comment}"));
+    }
+}
+
+fn column(column: &ColumnDef, width: usize) -> String {
+    let mut out = String::new();
+    doc(&mut out, column.doc.as_deref(), INDENT);
+    annotations(&mut out, &column.annotations, INDENT);
+
+    let padding = " ".repeat(width - column.id.name.len());
+    out.push_str(&format!(
+        "{INDENT}{}:{padding} {}",
+        column.id.name, column.typ.name
+    ));
+    if let Some(attribute) = column.attribute {
+        out.push_str(&format!(" {attribute}"));
+    }
+    if let Some(generated) = &column.generated {
+        out.push_str(&format!(" generated ({})", expr(&generated.expr)));
+        match generated.storage {
+            Some(GeneratedStorage::Stored) => out.push_str(" stored"),
+            Some(GeneratedStorage::Virtual) => out.push_str(" virtual"),
+            None => {}
+        }
+    }
+    if let Some(check) = &column.check {
+        out.push_str(&format!(" check ({})", expr(&check.expr)));
+    }
+    if let Some(reference) = &column.reference {
+        out.push_str(&format!(" {reference}"));
+    }
+    out
+}
+
+fn index(index: &Index) -> String {
+    // a lone column keeps the shorthand
+    let plain = index.annotations.is_empty()
+        && !index.is_unique
+        && index.name.is_none()
+        && index.using.is_none()
+        && index.include.is_none()
+        && index.predicate.is_none();
+    if let [column] = index.columns.as_slice()
+        && plain
+    {
+        return column.to_string();
+    }
+
+    let mut out: Vec<String> = index.annotations.iter().map(annotation).collect();
+    if index.is_unique {
+        out.push("unique".to_string());
+    }
+    if let Some(name) = &index.name {
+        out.push(name.name.clone());
+    }
+    let columns: Vec<String> = index.columns.iter().map(|c| c.to_string()).collect();
+    out.push(format!("({})", columns.join(", ")));
+    if let Some(using) = &index.using {
+        out.push(format!("using {}", using.method));
+    }
+    if let Some(include) = &index.include {
+        let include: Vec<&str> = include.iter().map(|id| id.name.as_str()).collect();
+        out.push(format!("include ({})", include.join(", ")));
+    }
+    if let Some(predicate) = &index.predicate {
+        out.push(format!("where {}", expr(predicate)));
+    }
+    out.join(" ")
+}
+
+fn doc(out: &mut String, doc: Option<&str>, indent: &str) {
+    for line in doc.into_iter().flat_map(str::lines) {
+        match line {
+            "" => out.push_str(&format!("{indent}///\n")),
+            line => out.push_str(&format!("{indent}/// {line}\n")),
+        }
+    }
+}
+
+fn annotations(out: &mut String, annotations: &[Annotation], indent: &str) {
+    for a in annotations {
+        out.push_str(&format!("{indent}{}\n", annotation(a)));
+    }
+}
+
+fn annotation(annotation: &Annotation) -> String {
+    if annotation.args.is_empty() {
+        return format!("@{}", annotation.name.name);
+    }
+    let args: Vec<String> = annotation
+        .args
+        .iter()
+        .map(|arg| match &arg.key {
+            Some(key) => format!("{} = {}", key.name, arg.value),
+            None => arg.value.to_string(),
+        })
+        .collect();
+    format!("@{}({})", annotation.name.name, args.join(", "))
+}
+
+/// Binding strength of an expression, following the grammar of `expr_parser`
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Binary { op, .. } => match op {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq
+            | BinaryOp::NotEq
+            | BinaryOp::Lt
+            | BinaryOp::LtEq
+            | BinaryOp::Gt
+            | BinaryOp::GtEq => 5,
+            BinaryOp::Concat => 6,
+            BinaryOp::Add | BinaryOp::Sub => 7,
+            BinaryOp::Mul | BinaryOp::Div => 8,
+        },
+        Expr::Unary {
+            op: UnaryOp::Not, ..
+        } => 3,
+        Expr::IsNull { .. } => 4,
+        Expr::Unary {
+            op: UnaryOp::Neg, ..
+        } => 9,
+        Expr::Column(_) | Expr::Literal(..) => 10,
+    }
+}
+
+/// Renders an expression with only the parentheses its structure needs, unlike the
+/// fully parenthesized `Display` of [`Expr`]
+pub(crate) fn expr(e: &Expr) -> String {
+    let operand = |operand: &Expr, min: u8| {
+        if precedence(operand) < min {
+            format!("({})", expr(operand))
+        } else {
+            expr(operand)
+        }
+    };
+
+    match e {
+        Expr::Column(id) => id.name.clone(),
+        Expr::Literal(literal, _) => literal.to_string(),
+        Expr::Unary {
+            op, expr: inner, ..
+        } => {
+            format!("{op}{}", operand(inner, precedence(e)))
+        }
+        Expr::IsNull {
+            expr: inner,
+            negated,
+            ..
+        } => {
+            let not = if *negated { " not" } else { "" };
+            format!("{} is{not} null", operand(inner, 5))
+        }
+        Expr::Binary { op, lhs, rhs, .. } => {
+            // chains are left-associative, so only the right operand needs a tighter binding
+            let precedence = precedence(e);
+            format!(
+                "{} {op} {}",
+                operand(lhs, precedence),
+                operand(rhs, precedence + 1)
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format, format_source};
+    use crate::parser::parse;
+
+    fn assert_format(src: &str, expected: &str) {
+        let schema = parse("test.mecha", src).unwrap();
+        let formatted = format(&schema);
+        assert_eq!(formatted, expected);
+
+        // formatting is stable once applied
+        let reparsed = parse("test.mecha", &formatted).unwrap();
+        assert_eq!(format(&reparsed), expected);
+    }
+
+    #[test]
+    fn test_format() {
+        let src = r#"
+            /// people
+            @deprecated abstract table base { id: uuid primary }
+            table users extends base {
+              /// contact
+              @pii @db.postgres(type = "citext")
+              email: text unique check (email != ''),
+              age: int check ((age >= 0 and age < 200) or age is null),
+              check (-(age) * (2 + 1) > 0),
+              full: text generated (email || 'x') stored
+              indexes { unique idx_email (email desc) using btree where not (age is null), age }
+            }
+        "#;
+        let expected = r#"/// people
+@deprecated
+abstract table base {
+    id: uuid primary
+}
+
+table users extends base {
+    /// contact
+    @pii
+    @db.postgres(type = 'citext')
+    email: text unique check (email != ''),
+    age:   int check (age >= 0 and age < 200 or age is null),
+    full:  text generated (email || 'x') stored,
+    check (-age * (2 + 1) > 0)
+    indexes {
+        unique idx_email (email desc) using btree where not age is null,
+        age
+    }
+}
+"#;
+        assert_format(src, expected);
+    }
+
+    #[test]
+    fn test_format_comments() {
+        let src = r#"// accounts of the app
+
+// people
+table users { // one per login
+  id: uuid primary, // from the auth service
+  // contact
+  @pii email: text
+    // lowercase only
+    check (email != '')
+  indexes { email // lookups
+  // more to come
+  }
+} // users
+
+// the end
+"#;
+        let expected = r#"// accounts of the app
+
+// people
+table users { // one per login
+    id:    uuid primary, // from the auth service
+    // contact
+    // lowercase only
+    @pii
+    email: text check (email != '')
+    indexes {
+        email // lookups
+    }
+    // more to come
+} // users
+
+// the end
+"#;
+        let formatted = format_source("test.mecha", src).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format_source("test.mecha", &formatted).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_detached_doc_comments() {
+        let src = r#"table users {
+  /// key
+  id: uuid primary
+  /// documents nothing
+}
+"#;
+        let expected = r#"table users {
+    /// key
+    id: uuid primary
+    /// documents nothing
+}
+"#;
+        assert_eq!(format_source("test.mecha", src).unwrap(), expected);
+    }
+}
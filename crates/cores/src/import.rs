@@ -0,0 +1,2162 @@
+use crate::ast::{
+    Annotation, AnnotationArg, BinaryOp, CheckDef, ColumnAttribute, ColumnDef, Expr, GeneratedDef,
+    GeneratedStorage, Ident, Index, IndexColumn, IndexMethod, IndexUsing, Literal, RefOperator,
+    ReferenceDef, Schema, SortOrder, TableDef, UnaryOp,
+};
+use crate::ddl::{Dialect, default_index_name};
+use crate::diagnostic::Diagnostic;
+use crate::lexer::is_ident;
+use crate::naming::{Convention, convert};
+use crate::sqlite::TableInfo;
+use chumsky::span::SimpleSpan;
+use logos::Logos;
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+
+/// Tokens of SQL DDL, keywords stay plain words compared case-insensitively
+#[derive(Logos, Clone, PartialEq, Debug)]
+#[logos(skip r"[ \t\r\n\f]+")]
+#[logos(skip r"--[^\n]*")]
+#[logos(skip r"/\*([^*]|\*+[^*/])*\*+/")]
+enum Sql<'s> {
+    #[regex(r"[A-Za-z_][A-Za-z0-9_$]*")]
+    Word(&'s str),
+
+    #[regex(r#""([^"]|"")*""#, unquote)]
+    #[regex(r"`([^`]|``)*`", unquote)]
+    Quoted(String),
+
+    #[regex(r"'([^']|'')*'", unquote)]
+    Str(String),
+
+    #[regex(r"[0-9]+(\.[0-9]+)?([eE][+-]?[0-9]+)?")]
+    Number(&'s str),
+
+    #[regex(r"\$[A-Za-z_]?[A-Za-z0-9_]*\$", dollar_quoted)]
+    DollarQuoted,
+
+    #[regex(r"<>|!=|<=|>=|::|\|\||[(),;.=<>+\-*/%\[\]:\\]")]
+    Symbol(&'s str),
+}
+
+/// Strips the surrounding quotes, where a doubled quote stands for itself
+fn unquote<'s>(lex: &mut logos::Lexer<'s, Sql<'s>>) -> String {
+    let slice = lex.slice();
+    let quote = &slice[..1];
+    slice[1..slice.len() - 1].replace(&quote.repeat(2), quote)
+}
+
+/// Reads a `$tag$ ... $tag$` body, such as a function's, as a single token
+fn dollar_quoted<'s>(lex: &mut logos::Lexer<'s, Sql<'s>>) -> bool {
+    let tag = lex.slice();
+    match lex.remainder().find(tag) {
+        Some(end) => {
+            lex.bump(end + tag.len());
+            true
+        }
+        None => false,
+    }
+}
+
+/// Something the import leaves out, along with where it is in the SQL
+type Unsupported = (SimpleSpan, String);
+
+enum Constraint {
+    PrimaryKey(Vec<Ident>),
+    Unique(Vec<Ident>),
+    Check(CheckDef),
+    ForeignKey(ForeignKey),
+}
+
+struct ForeignKey {
+    columns: Vec<Ident>,
+    referenced_table: Ident,
+    referenced_columns: Vec<Ident>,
+    span: SimpleSpan,
+}
+
+/// Reads the tables of SQL DDL, such as the output of `pg_dump --schema-only`, into a
+/// schema. What mecha can't express is left out, with a warning pointing into the SQL.
+pub fn sql(name: &str, src: &str, dialect: Dialect) -> (Schema, Vec<Diagnostic>) {
+    let tokens = Sql::lexer(src)
+        .spanned()
+        .map(|(token, span)| {
+            let token = token.unwrap_or(Sql::Symbol(&src[span.clone()]));
+            (token, span.into())
+        })
+        .collect::<Vec<_>>();
+
+    let mut importer = Importer {
+        src,
+        limit: tokens.len(),
+        tokens,
+        pos: 0,
+        dialect,
+        tables: Vec::new(),
+        foreign_keys: Vec::new(),
+        not_null: Vec::new(),
+        warnings: Vec::new(),
+    };
+    importer.statements();
+    importer.resolve_foreign_keys();
+    importer.rename_identifiers();
+
+    if let Some(span) = importer.not_null.first() {
+        let message = format!(
+            "NOT NULL isn't modeled by mecha, it's dropped from {} column(s)",
+            importer.not_null.len()
+        );
+        importer.warnings.push((*span, message));
+    }
+
+    let mut warnings = importer.warnings;
+    warnings.sort_by_key(|(span, _)| span.start);
+
+    let schema = Schema {
+        name: name.to_string(),
+        tables: importer.tables,
+        span: (0..src.len()).into(),
+    };
+    let warnings = warnings
+        .into_iter()
+        .map(|(span, message)| Diagnostic::warning(span, message))
+        .collect();
+    (schema, warnings)
+}
+
+/// Reads the tables of a SQLite database into a schema from what its pragmas report.
+/// Check constraints and the expressions of generated columns aren't part of it, the
+/// warnings say what's left out.
+pub fn sqlite(name: &str, conn: &Connection) -> anyhow::Result<(Schema, Vec<String>)> {
+    let mut importer = Importer {
+        src: "",
+        tokens: Vec::new(),
+        pos: 0,
+        limit: 0,
+        dialect: Dialect::Sqlite,
+        tables: Vec::new(),
+        foreign_keys: Vec::new(),
+        not_null: Vec::new(),
+        warnings: Vec::new(),
+    };
+    for table in crate::sqlite::tables(conn)? {
+        importer.table_info(table);
+    }
+    importer.resolve_foreign_keys();
+    importer.rename_identifiers();
+
+    let schema = Schema {
+        name: name.to_string(),
+        tables: importer.tables,
+        span: SimpleSpan::default(),
+    };
+    let warnings = importer
+        .warnings
+        .into_iter()
+        .map(|(_, message)| message)
+        .collect();
+    Ok((schema, warnings))
+}
+
+struct Importer<'s> {
+    src: &'s str,
+    tokens: Vec<(Sql<'s>, SimpleSpan)>,
+    pos: usize,
+    /// Tokens from here on are out of reach, used to read an expression up to a bound
+    limit: usize,
+    dialect: Dialect,
+    tables: Vec<TableDef>,
+    /// Resolved once every table is read, they may point to tables declared later
+    foreign_keys: Vec<(String, ForeignKey)>,
+    not_null: Vec<SimpleSpan>,
+    warnings: Vec<Unsupported>,
+}
+
+impl<'s> Importer<'s> {
+    fn statements(&mut self) {
+        while self.pos < self.tokens.len() {
+            if self.eat_symbol(";") {
+                continue;
+            }
+            if self.is_symbol("\\") {
+                self.skip_line();
+                continue;
+            }
+            if self.is_session_statement() {
+                self.skip_statement();
+                continue;
+            }
+
+            match self.statement() {
+                Err(unsupported) => self.warnings.push(unsupported),
+                Ok(()) if !self.at_statement_end() => {
+                    let unexpected = self.unexpected("the end of the statement");
+                    self.warnings.push((
+                        unexpected.0,
+                        format!("{}, the rest is skipped", unexpected.1),
+                    ));
+                }
+                Ok(()) => {}
+            }
+            self.skip_statement();
+        }
+    }
+
+    fn statement(&mut self) -> Result<(), Unsupported> {
+        let first = self.pos;
+
+        if self.eat_word("create") {
+            self.eat_word("unlogged");
+            if self.eat_word("table") {
+                return self.create_table();
+            }
+            let is_unique = self.eat_word("unique");
+            if self.eat_word("index") {
+                return self.create_index(is_unique);
+            }
+        } else if self.eat_word("alter") {
+            if self.eat_word("table") {
+                return self.alter_table();
+            }
+        } else if self.eat_word("comment")
+            && self.eat_word("on")
+            && (self.is_word("table") || self.is_word("column"))
+        {
+            return self.comment();
+        }
+
+        // names the statement by its leading words, e.g. `CREATE SEQUENCE`
+        let last = self.pos.min(self.tokens.len() - 1);
+        let span: SimpleSpan = (self.tokens[first].1.start..self.tokens[last].1.end).into();
+        Err((
+            span,
+            format!(
+                "'{}' isn't supported, the statement is skipped",
+                self.text(span)
+            ),
+        ))
+    }
+
+    /// Statements that only configure the session replaying a dump
+    fn is_session_statement(&self) -> bool {
+        const SESSION: &[&str] = &[
+            "set", "reset", "begin", "start", "commit", "end", "rollback", "pragma",
+        ];
+        SESSION.iter().any(|word| self.is_word(word))
+            || (self.is_word("select") && self.is_word_at(1, "pg_catalog"))
+    }
+
+    fn create_table(&mut self) -> Result<(), Unsupported> {
+        if self.eat_word("if") {
+            self.expect_word("not")?;
+            self.expect_word("exists")?;
+        }
+        let id = self.qualified_name()?;
+        if self.is_word("as") {
+            return Err((
+                id.span,
+                format!("table '{}' is created from a query, it's skipped", id.name),
+            ));
+        }
+        self.expect_symbol("(")?;
+
+        let mut table = TableDef {
+            id,
+            doc: None,
+            annotations: Vec::new(),
+            is_abstract: false,
+            extended_by: None,
+            columns: Vec::new(),
+            checks: Vec::new(),
+            indexes: None,
+            span: SimpleSpan::default(),
+        };
+        let mut constraints = Vec::new();
+        loop {
+            if let Err(unsupported) = self.table_element(&mut table, &mut constraints) {
+                self.warnings.push(unsupported);
+                self.skip_element();
+            }
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        self.expect_symbol(")")?;
+
+        if !self.at_statement_end() {
+            let start = self.span().start;
+            while !self.at_statement_end() {
+                self.pos += 1;
+            }
+            let span = self.since(start);
+            self.warnings.push((
+                span,
+                format!(
+                    "table options '{}' of '{}' are skipped",
+                    self.text(span),
+                    table.id.name
+                ),
+            ));
+        }
+
+        if table.columns.is_empty() {
+            return Err((
+                table.id.span,
+                format!("table '{}' has no columns, it's skipped", table.id.name),
+            ));
+        }
+        if self.table(&table.id.name).is_some() {
+            return Err((
+                table.id.span,
+                format!(
+                    "table '{}' is already declared, it's skipped",
+                    table.id.name
+                ),
+            ));
+        }
+
+        let name = table.id.name.clone();
+        self.tables.push(table);
+        for (span, constraint) in constraints {
+            self.constrain(&name, span, constraint);
+        }
+        Ok(())
+    }
+
+    fn table_element(
+        &mut self,
+        table: &mut TableDef,
+        constraints: &mut Vec<(SimpleSpan, Constraint)>,
+    ) -> Result<(), Unsupported> {
+        if self.is_table_constraint() {
+            constraints.push(self.table_constraint()?);
+            return Ok(());
+        }
+
+        // `KEY` and `INDEX` are reserved in mysql, a column by that name is always quoted
+        if self.dialect == Dialect::Mysql && (self.is_word("key") || self.is_word("index")) {
+            let start = self.span().start;
+            self.pos += 1;
+            let name = (!self.is_symbol("(")).then(|| self.name()).transpose()?;
+            let columns = self.index_columns()?;
+            let using = self.index_using();
+            table.indexes.get_or_insert_with(Vec::new).push(Index {
+                annotations: Vec::new(),
+                name,
+                is_unique: false,
+                columns,
+                using,
+                include: None,
+                predicate: None,
+                span: self.since(start),
+            });
+            return Ok(());
+        }
+
+        let (column, column_constraints) = self.column_definition()?;
+        table.columns.push(column);
+        constraints.extend(column_constraints);
+        Ok(())
+    }
+
+    fn is_table_constraint(&self) -> bool {
+        ["constraint", "primary", "check", "foreign"]
+            .iter()
+            .any(|word| self.is_word(word))
+            || (self.is_word("unique")
+                && (self.is_symbol_at(1, "(")
+                    || self.is_word_at(1, "key")
+                    || self.is_word_at(1, "index")))
+    }
+
+    fn column_definition(
+        &mut self,
+    ) -> Result<(ColumnDef, Vec<(SimpleSpan, Constraint)>), Unsupported> {
+        let id = self.name()?;
+
+        let start = self.span().start;
+        let typ = self.column_type();
+        let typ_span = self.since(start);
+        let (typ, exact) = if typ.is_empty() {
+            self.warnings.push((
+                id.span,
+                format!("column '{}' has no type, it's imported as 'blob'", id.name),
+            ));
+            ("blob".to_string(), None)
+        } else {
+            mecha_type(&typ, self.dialect)
+        };
+
+        let mut column = ColumnDef {
+            id,
+            doc: None,
+            annotations: Vec::new(),
+            typ: Ident {
+                name: typ,
+                span: typ_span,
+            },
+            attribute: None,
+            generated: None,
+            check: None,
+            reference: None,
+            span: SimpleSpan::default(),
+        };
+        if let Some(exact) = exact {
+            column
+                .annotations
+                .push(self.type_annotation(exact, typ_span));
+        }
+
+        let mut constraints = Vec::new();
+        loop {
+            let start = self.span().start;
+            let name = column.id.name.clone();
+
+            if self.eat_word("constraint") {
+                self.name()?;
+            } else if self.eat_word("not") {
+                self.expect_word("null")?;
+                self.not_null.push(self.since(start));
+            } else if self.eat_word("null") {
+            } else if self.eat_word("primary") {
+                self.expect_word("key")?;
+                if !self.eat_word("asc") {
+                    self.eat_word("desc");
+                }
+                constraints.push((
+                    self.since(start),
+                    Constraint::PrimaryKey(vec![column.id.clone()]),
+                ));
+            } else if self.eat_word("unique") {
+                self.eat_word("key");
+                constraints.push((
+                    self.since(start),
+                    Constraint::Unique(vec![column.id.clone()]),
+                ));
+            } else if self.eat_word("check") {
+                let (expr, span) = self.parenthesized_expr()?;
+                match expr {
+                    Some(expr) => {
+                        constraints.push((span, Constraint::Check(CheckDef { expr, span })))
+                    }
+                    None => self.warnings.push((
+                        span,
+                        format!("check of '{name}' isn't supported, it's dropped"),
+                    )),
+                }
+            } else if self.eat_word("references") {
+                let fk = self.references(vec![column.id.clone()], start)?;
+                constraints.push((fk.span, Constraint::ForeignKey(fk)));
+            } else if self.eat_word("default") {
+                self.skip_default();
+                self.warnings.push((
+                    self.since(start),
+                    format!("default of '{name}' isn't supported, it's dropped"),
+                ));
+            } else if self.is_word("generated") || self.is_word("as") {
+                self.generated(&mut column, start)?;
+            } else if self.eat_word("collate") {
+                self.name()?;
+                self.warnings.push((
+                    self.since(start),
+                    format!("collation of '{name}' isn't supported, it's dropped"),
+                ));
+            } else if self.eat_word("auto_increment") || self.eat_word("autoincrement") {
+                self.warnings.push((
+                    self.since(start),
+                    format!("auto increment of '{name}' isn't supported, it's dropped"),
+                ));
+            } else if self.eat_word("comment") {
+                match self.peek() {
+                    Some(Sql::Str(doc)) => {
+                        column.doc = Some(doc.clone());
+                        self.pos += 1;
+                    }
+                    _ => return Err(self.unexpected("a string")),
+                }
+            } else if self.eat_word("on") {
+                self.expect_word("update")?;
+                self.skip_default();
+                self.warnings.push((
+                    self.since(start),
+                    format!("'ON UPDATE' of '{name}' isn't supported, it's dropped"),
+                ));
+            } else {
+                break;
+            }
+        }
+
+        Ok((column, constraints))
+    }
+
+    /// `GENERATED ALWAYS AS (expr) [STORED | VIRTUAL]`, or the short `AS (expr)` of sqlite
+    /// and mysql. Identity columns are left as plain columns.
+    fn generated(&mut self, column: &mut ColumnDef, start: usize) -> Result<(), Unsupported> {
+        if self.eat_word("generated") && !self.eat_word("always") {
+            self.expect_word("by")?;
+            self.expect_word("default")?;
+        }
+        self.expect_word("as")?;
+
+        if self.eat_word("identity") {
+            if self.is_symbol("(") {
+                self.skip_token();
+            }
+            self.warnings.push((
+                self.since(start),
+                format!(
+                    "identity of '{}' isn't supported, it's dropped",
+                    column.id.name
+                ),
+            ));
+            return Ok(());
+        }
+
+        let (expr, span) = self.parenthesized_expr()?;
+        let storage = if self.eat_word("stored") {
+            Some(GeneratedStorage::Stored)
+        } else if self.eat_word("virtual") {
+            Some(GeneratedStorage::Virtual)
+        } else {
+            None
+        };
+        match expr {
+            Some(expr) => {
+                column.generated = Some(GeneratedDef {
+                    expr,
+                    storage,
+                    span: self.since(start),
+                })
+            }
+            None => self.warnings.push((
+                span,
+                format!(
+                    "generated expression of '{}' isn't supported, it's imported as a plain column",
+                    column.id.name
+                ),
+            )),
+        }
+        Ok(())
+    }
+
+    /// Reads the type of a column as written, up to its first constraint
+    fn column_type(&mut self) -> String {
+        let mut typ = String::new();
+        let mut depth = 0;
+        // whether the previous token asks for a space before a word, as in `double precision`
+        let mut spaced = false;
+
+        while let Some(token) = self.peek() {
+            let text = match token {
+                _ if depth == 0
+                    && (self.is_symbol(",")
+                        || self.is_symbol(")")
+                        || self.is_column_constraint()) =>
+                {
+                    break;
+                }
+                Sql::Word(word) => word.to_lowercase(),
+                Sql::Quoted(name) => name.clone(),
+                Sql::Str(value) => format!("'{}'", value.replace('\'', "''")),
+                Sql::Number(value) => value.to_string(),
+                Sql::Symbol(symbol) => {
+                    match *symbol {
+                        "(" => depth += 1,
+                        ")" => depth -= 1,
+                        _ => {}
+                    }
+                    let symbol = symbol.to_string();
+                    typ.push_str(&symbol);
+                    spaced = symbol == "," || symbol == ")";
+                    self.pos += 1;
+                    continue;
+                }
+                Sql::DollarQuoted => String::new(),
+            };
+            if spaced {
+                typ.push(' ');
+            }
+            typ.push_str(&text);
+            spaced = true;
+            self.pos += 1;
+        }
+
+        typ
+    }
+
+    fn is_column_constraint(&self) -> bool {
+        const CONSTRAINTS: &[&str] = &[
+            "constraint",
+            "not",
+            "null",
+            "primary",
+            "unique",
+            "check",
+            "references",
+            "default",
+            "generated",
+            "as",
+            "collate",
+            "auto_increment",
+            "autoincrement",
+            "comment",
+            "on",
+        ];
+        CONSTRAINTS.iter().any(|word| self.is_word(word))
+    }
+
+    fn type_annotation(&self, typ: String, span: SimpleSpan) -> Annotation {
+        Annotation {
+            name: Ident {
+                name: format!("db.{}", self.dialect.name()),
+                span,
+            },
+            args: vec![AnnotationArg {
+                key: Some(Ident {
+                    name: "type".to_string(),
+                    span,
+                }),
+                value: Literal::String(typ),
+                span,
+            }],
+            span,
+        }
+    }
+
+    fn table_constraint(&mut self) -> Result<(SimpleSpan, Constraint), Unsupported> {
+        let start = self.span().start;
+        if self.eat_word("constraint") {
+            self.name()?;
+        }
+
+        let constraint = if self.eat_word("primary") {
+            self.expect_word("key")?;
+            Constraint::PrimaryKey(self.column_list()?)
+        } else if self.eat_word("unique") {
+            if !self.eat_word("key") {
+                self.eat_word("index");
+            }
+            // mysql names the index backing the constraint
+            if !self.is_symbol("(") {
+                self.name()?;
+            }
+            Constraint::Unique(self.column_list()?)
+        } else if self.eat_word("check") {
+            let (expr, span) = self.parenthesized_expr()?;
+            let Some(expr) = expr else {
+                return Err((span, "check isn't supported, it's dropped".to_string()));
+            };
+            Constraint::Check(CheckDef { expr, span })
+        } else if self.eat_word("foreign") {
+            self.expect_word("key")?;
+            let columns = self.column_list()?;
+            self.expect_word("references")?;
+            Constraint::ForeignKey(self.references(columns, start)?)
+        } else {
+            let unsupported = self.unexpected("a constraint");
+            return Err((
+                unsupported.0,
+                format!("{}, the constraint is skipped", unsupported.1),
+            ));
+        };
+
+        Ok((self.since(start), constraint))
+    }
+
+    fn references(&mut self, columns: Vec<Ident>, start: usize) -> Result<ForeignKey, Unsupported> {
+        let referenced_table = self.qualified_name()?;
+        let referenced_columns = if self.is_symbol("(") {
+            self.column_list()?
+        } else {
+            Vec::new()
+        };
+
+        loop {
+            let action_start = self.span().start;
+            if self.eat_word("on") {
+                if !self.eat_word("delete") {
+                    self.expect_word("update")?;
+                }
+                // `NO ACTION` is what a foreign key does anyway
+                let is_default = self.is_word("no");
+                let two_words = self.is_word("set") || self.is_word("no");
+                self.pos += if two_words { 2 } else { 1 };
+                if !is_default {
+                    let span = self.since(action_start);
+                    self.warnings.push((
+                        span,
+                        format!("'{}' isn't supported, it's dropped", self.text(span)),
+                    ));
+                }
+            } else if self.eat_word("match") || self.eat_word("initially") {
+                self.pos += 1;
+            } else if self.is_word("not") && self.is_word_at(1, "deferrable") {
+                self.pos += 2;
+            } else if !self.eat_word("deferrable") {
+                break;
+            }
+        }
+
+        Ok(ForeignKey {
+            columns,
+            referenced_table,
+            referenced_columns,
+            span: self.since(start),
+        })
+    }
+
+    fn create_index(&mut self, is_unique: bool) -> Result<(), Unsupported> {
+        let start = self.span().start;
+        self.eat_word("concurrently");
+        if self.eat_word("if") {
+            self.expect_word("not")?;
+            self.expect_word("exists")?;
+        }
+        let name = if self.is_word("on") {
+            None
+        } else {
+            Some(self.qualified_name()?)
+        };
+        self.expect_word("on")?;
+        self.eat_word("only");
+        let table = self.qualified_name()?;
+
+        let mut using = self.index_using();
+        let columns = self.index_columns()?;
+        if using.is_none() {
+            using = self.index_using();
+        }
+        let include = if self.eat_word("include") {
+            Some(self.column_list()?)
+        } else {
+            None
+        };
+        if self.eat_word("with") {
+            let start = self.span().start;
+            self.skip_token();
+            let span = self.since(start);
+            self.warnings.push((
+                span,
+                "index storage parameters aren't supported, they're dropped".to_string(),
+            ));
+        }
+        if self.eat_word("tablespace") {
+            self.name()?;
+        }
+        let predicate = if self.eat_word("where") {
+            let start = self.span().start;
+            let end = self.statement_end();
+            let predicate = self.expr_until(end);
+            let Some(predicate) = predicate else {
+                return Err((
+                    self.since(start),
+                    "index predicate isn't supported, the index is skipped".to_string(),
+                ));
+            };
+            Some(predicate)
+        } else {
+            None
+        };
+
+        let index = Index {
+            annotations: Vec::new(),
+            name,
+            is_unique,
+            columns,
+            using,
+            include,
+            predicate,
+            span: self.since(start),
+        };
+        let Some(table_def) = self.table_mut(&table.name) else {
+            return Err((
+                table.span,
+                format!("table '{}' doesn't exist, the index is skipped", table.name),
+            ));
+        };
+        push_index(table_def, index);
+        Ok(())
+    }
+
+    fn index_using(&mut self) -> Option<IndexUsing> {
+        let start = self.span().start;
+        if !self.eat_word("using") {
+            return None;
+        }
+        let method = match self.peek() {
+            Some(Sql::Word(word)) => word.to_lowercase(),
+            _ => return None,
+        };
+        self.pos += 1;
+        let span = self.since(start);
+
+        let method = match method.as_str() {
+            "btree" => IndexMethod::Btree,
+            "hash" => IndexMethod::Hash,
+            "gin" => IndexMethod::Gin,
+            "gist" => IndexMethod::Gist,
+            "brin" => IndexMethod::Brin,
+            method => {
+                self.warnings.push((
+                    span,
+                    format!("index method '{method}' isn't supported, it's dropped"),
+                ));
+                return None;
+            }
+        };
+        Some(IndexUsing { method, span })
+    }
+
+    fn index_columns(&mut self) -> Result<Vec<IndexColumn>, Unsupported> {
+        self.expect_symbol("(")?;
+        let mut columns = Vec::new();
+
+        loop {
+            let start = self.span().start;
+            let expression = |importer: &mut Self| {
+                importer.skip_element();
+                (
+                    importer.since(start),
+                    "expression indexes aren't supported, the index is skipped".to_string(),
+                )
+            };
+
+            if self.is_symbol("(") {
+                return Err(expression(self));
+            }
+            let id = self.name()?;
+            let mut order = None;
+            loop {
+                let modifier_start = self.span().start;
+                if self.eat_word("asc") {
+                    order = Some(SortOrder::Asc);
+                    continue;
+                }
+                if self.eat_word("desc") {
+                    order = Some(SortOrder::Desc);
+                    continue;
+                }
+                if self.eat_word("nulls") || self.eat_word("collate") {
+                    self.pos += 1;
+                } else if matches!(self.peek(), Some(Sql::Word(_))) {
+                    // an operator class, e.g. `varchar_pattern_ops`
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+                let span = self.since(modifier_start);
+                self.warnings.push((
+                    span,
+                    format!(
+                        "'{}' of indexed column '{}' isn't supported, it's dropped",
+                        self.text(span),
+                        id.name
+                    ),
+                ));
+            }
+            if !self.is_symbol(",") && !self.is_symbol(")") {
+                return Err(expression(self));
+            }
+
+            columns.push(IndexColumn {
+                id,
+                order,
+                span: self.since(start),
+            });
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+
+        self.expect_symbol(")")?;
+        Ok(columns)
+    }
+
+    fn alter_table(&mut self) -> Result<(), Unsupported> {
+        if self.eat_word("if") {
+            self.expect_word("exists")?;
+        }
+        self.eat_word("only");
+        let table = self.qualified_name()?;
+        if self.table(&table.name).is_none() {
+            return Err((
+                table.span,
+                format!(
+                    "table '{}' doesn't exist, the statement is skipped",
+                    table.name
+                ),
+            ));
+        }
+
+        loop {
+            // ownership isn't part of the schema
+            if self.eat_word("owner") {
+                self.pos = self.statement_end();
+                return Ok(());
+            }
+            if !self.eat_word("add") {
+                let span = self.span();
+                return Err((
+                    span,
+                    format!(
+                        "'ALTER TABLE ... {}' isn't supported, the rest of the statement is skipped",
+                        self.text(span)
+                    ),
+                ));
+            }
+
+            if self.is_table_constraint() {
+                let (span, constraint) = self.table_constraint()?;
+                self.constrain(&table.name, span, constraint);
+            } else {
+                self.eat_word("column");
+                if self.eat_word("if") {
+                    self.expect_word("not")?;
+                    self.expect_word("exists")?;
+                }
+                let (column, constraints) = self.column_definition()?;
+                if let Some(table_def) = self.table_mut(&table.name) {
+                    table_def.columns.push(column);
+                }
+                for (span, constraint) in constraints {
+                    self.constrain(&table.name, span, constraint);
+                }
+            }
+
+            if !self.eat_symbol(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    /// `COMMENT ON TABLE t IS '...'` and `COMMENT ON COLUMN t.c IS '...'` become docs
+    fn comment(&mut self) -> Result<(), Unsupported> {
+        let on_table = self.eat_word("table");
+        if !on_table {
+            self.expect_word("column")?;
+        }
+        let mut names = vec![self.name()?];
+        while self.eat_symbol(".") {
+            names.push(self.name()?);
+        }
+        self.expect_word("is")?;
+        let doc = match self.peek() {
+            Some(Sql::Str(doc)) => Some(doc.clone()),
+            Some(Sql::Word(word)) if word.eq_ignore_ascii_case("null") => None,
+            _ => return Err(self.unexpected("a string")),
+        };
+        self.pos += 1;
+
+        let (table, column) = match (on_table, names.as_slice()) {
+            (true, [.., table]) => (table, None),
+            (false, [.., table, column]) => (table, Some(column)),
+            _ => return Err(self.unexpected("a column name")),
+        };
+        let missing = |name: &Ident| {
+            (
+                name.span,
+                format!("'{}' doesn't exist, the comment is skipped", name.name),
+            )
+        };
+        let Some(table_def) = self.table_mut(&table.name) else {
+            return Err(missing(table));
+        };
+        match column {
+            None => table_def.doc = doc,
+            Some(column) => match table_def
+                .columns
+                .iter_mut()
+                .find(|c| c.id.name == column.name)
+            {
+                Some(column_def) => column_def.doc = doc,
+                None => return Err(missing(column)),
+            },
+        }
+        Ok(())
+    }
+
+    /// Adds a table as SQLite's pragmas report it, nothing of it points into a source
+    fn table_info(&mut self, info: TableInfo) {
+        let span = SimpleSpan::default();
+        let ident = |name: &str| Ident {
+            name: name.to_string(),
+            span,
+        };
+        let table = info.name.as_str();
+
+        let mut columns = Vec::new();
+        for column in &info.columns {
+            let (typ, exact) = if column.typ.is_empty() {
+                self.warnings.push((
+                    span,
+                    format!(
+                        "column '{table}.{}' has no type, it's imported as 'blob'",
+                        column.name
+                    ),
+                ));
+                ("blob".to_string(), None)
+            } else {
+                mecha_type(&column.typ, self.dialect)
+            };
+            if column.generated {
+                self.warnings.push((
+                    span,
+                    format!(
+                        "SQLite doesn't report the expression of '{table}.{}', it's imported as a plain column",
+                        column.name
+                    ),
+                ));
+            }
+            columns.push(ColumnDef {
+                id: ident(&column.name),
+                doc: None,
+                annotations: exact
+                    .map(|exact| self.type_annotation(exact, span))
+                    .into_iter()
+                    .collect(),
+                typ: ident(&typ),
+                attribute: None,
+                generated: None,
+                check: None,
+                reference: None,
+                span,
+            });
+        }
+        self.tables.push(TableDef {
+            id: ident(table),
+            doc: None,
+            annotations: Vec::new(),
+            is_abstract: false,
+            extended_by: None,
+            columns,
+            checks: Vec::new(),
+            indexes: None,
+            span,
+        });
+
+        let primary_key: Vec<Ident> = info
+            .columns
+            .iter()
+            .filter(|c| c.primary_key)
+            .map(|c| ident(&c.name))
+            .collect();
+        if !primary_key.is_empty() {
+            self.constrain(table, span, Constraint::PrimaryKey(primary_key.clone()));
+        }
+
+        for index in info.indexes {
+            let Some(name) = index.name else {
+                // backs a UNIQUE or PRIMARY KEY constraint, the latter is imported already
+                let is_primary_key = index.columns.len() == primary_key.len()
+                    && index
+                        .columns
+                        .iter()
+                        .all(|c| primary_key.iter().any(|key| &key.name == c));
+                if !is_primary_key {
+                    let columns = index.columns.iter().map(|c| ident(c)).collect();
+                    self.constrain(table, span, Constraint::Unique(columns));
+                }
+                continue;
+            };
+            if index.partial || index.expression {
+                let keys = if index.partial {
+                    "a predicate"
+                } else {
+                    "expression keys"
+                };
+                self.warnings.push((
+                    span,
+                    format!("index '{name}' has {keys} SQLite doesn't report, it's skipped"),
+                ));
+                continue;
+            }
+
+            let index = Index {
+                annotations: Vec::new(),
+                name: Some(ident(&name)),
+                is_unique: index.unique,
+                columns: index
+                    .columns
+                    .iter()
+                    .map(|c| IndexColumn {
+                        id: ident(c),
+                        order: None,
+                        span,
+                    })
+                    .collect(),
+                using: None,
+                include: None,
+                predicate: None,
+                span,
+            };
+            if let Some(table_def) = self.table_mut(table) {
+                push_index(table_def, index);
+            }
+        }
+
+        // the columns of a composite key follow each other, each key starting at 0
+        let mut foreign_keys: Vec<ForeignKey> = Vec::new();
+        for fk in info.foreign_keys {
+            let column = ident(&fk.column);
+            let referenced_column = fk.referenced_column.as_deref().map(ident);
+            match foreign_keys.last_mut() {
+                Some(last) if fk.seq > 0 => {
+                    last.columns.push(column);
+                    last.referenced_columns.extend(referenced_column);
+                }
+                _ => foreign_keys.push(ForeignKey {
+                    columns: vec![column],
+                    referenced_table: ident(&fk.referenced_table),
+                    referenced_columns: referenced_column.into_iter().collect(),
+                    span,
+                }),
+            }
+        }
+        for fk in foreign_keys {
+            self.constrain(table, span, Constraint::ForeignKey(fk));
+        }
+    }
+
+    /// Applies a key or a check to a table, foreign keys wait for every table to be read
+    fn constrain(&mut self, table: &str, span: SimpleSpan, constraint: Constraint) {
+        match constraint {
+            Constraint::PrimaryKey(columns) if columns.len() == 1 => {
+                self.set_attribute(table, &columns[0], ColumnAttribute::Primary)
+            }
+            Constraint::Unique(columns) if columns.len() == 1 => {
+                self.set_attribute(table, &columns[0], ColumnAttribute::Unique)
+            }
+            Constraint::PrimaryKey(columns) => {
+                self.warnings.push((
+                    span,
+                    format!(
+                        "composite primary key of '{table}' isn't supported, it's imported as a unique index"
+                    ),
+                ));
+                self.add_unique_index(table, columns, span);
+            }
+            Constraint::Unique(columns) => self.add_unique_index(table, columns, span),
+            Constraint::Check(check) => {
+                let Some(table_def) = self.table_mut(table) else {
+                    return;
+                };
+                let columns = check.expr.columns();
+                let single = columns
+                    .first()
+                    .filter(|first| columns.iter().all(|c| c.name == first.name))
+                    .map(|first| first.name.clone());
+                let column = single.and_then(|name| {
+                    table_def
+                        .columns
+                        .iter_mut()
+                        .find(|c| c.id.name == name && c.check.is_none())
+                });
+                match column {
+                    Some(column) => column.check = Some(check),
+                    None => table_def.checks.push(check),
+                }
+            }
+            Constraint::ForeignKey(fk) => self.foreign_keys.push((table.to_string(), fk)),
+        }
+    }
+
+    fn set_attribute(&mut self, table: &str, column: &Ident, attribute: ColumnAttribute) {
+        let Some(column_def) = self.column_mut(table, &column.name) else {
+            self.warnings.push((
+                column.span,
+                format!("column '{table}.{}' doesn't exist", column.name),
+            ));
+            return;
+        };
+        // a primary key is unique already
+        if column_def.attribute != Some(ColumnAttribute::Primary) {
+            column_def.attribute = Some(attribute);
+        }
+    }
+
+    fn add_unique_index(&mut self, table: &str, columns: Vec<Ident>, span: SimpleSpan) {
+        if let Some(missing) = columns
+            .iter()
+            .find(|c| self.column(table, &c.name).is_none())
+        {
+            self.warnings.push((
+                missing.span,
+                format!("column '{table}.{}' doesn't exist", missing.name),
+            ));
+            return;
+        }
+        let Some(table_def) = self.table_mut(table) else {
+            return;
+        };
+        let columns = columns
+            .into_iter()
+            .map(|id| IndexColumn {
+                span: id.span,
+                id,
+                order: None,
+            })
+            .collect();
+        table_def.indexes.get_or_insert_with(Vec::new).push(Index {
+            annotations: Vec::new(),
+            name: None,
+            is_unique: true,
+            columns,
+            using: None,
+            include: None,
+            predicate: None,
+            span,
+        });
+    }
+
+    /// Turns `posts.user_id REFERENCES users (id)` into `users.id (ref => posts.user_id)`
+    fn resolve_foreign_keys(&mut self) {
+        for (table, fk) in std::mem::take(&mut self.foreign_keys) {
+            let skip = |importer: &mut Self, reason: String| {
+                importer
+                    .warnings
+                    .push((fk.span, format!("{reason}, the foreign key is skipped")));
+            };
+
+            let [column] = fk.columns.as_slice() else {
+                skip(self, "composite foreign keys aren't supported".to_string());
+                continue;
+            };
+            let Some(holder) = self.column(&table, &column.name) else {
+                skip(
+                    self,
+                    format!("column '{table}.{}' doesn't exist", column.name),
+                );
+                continue;
+            };
+            let operator = match holder.attribute {
+                Some(_) => RefOperator::OneToOne,
+                None => RefOperator::OneToMany,
+            };
+
+            let referenced_table = fk.referenced_table.name.as_str();
+            let referenced_column = match fk.referenced_columns.as_slice() {
+                [referenced] => Some(referenced.name.clone()),
+                [] => self.table(referenced_table).and_then(|t| {
+                    t.columns
+                        .iter()
+                        .find(|c| c.attribute == Some(ColumnAttribute::Primary))
+                        .map(|c| c.id.name.clone())
+                }),
+                _ => None,
+            };
+            let Some(referenced_column) = referenced_column else {
+                skip(
+                    self,
+                    format!("'{referenced_table}' has no single referenced column"),
+                );
+                continue;
+            };
+
+            let Some(target) = self.column_mut(referenced_table, &referenced_column) else {
+                skip(
+                    self,
+                    format!("column '{referenced_table}.{referenced_column}' doesn't exist"),
+                );
+                continue;
+            };
+            if let Some(existing) = &target.reference {
+                let reason = format!(
+                    "'{referenced_table}.{referenced_column}' is already referenced by '{}.{}' and mecha keeps one reference per column",
+                    existing.table.name, existing.column.name
+                );
+                skip(self, reason);
+                continue;
+            }
+            target.reference = Some(ReferenceDef {
+                operator,
+                table: Ident {
+                    name: table,
+                    span: fk.span,
+                },
+                column: column.clone(),
+                span: fk.span,
+            });
+        }
+    }
+
+    /// SQL names may be quoted or clash with mecha keywords, such names are renamed to valid
+    /// identifiers along with every place naming them, and each renaming is reported
+    fn rename_identifiers(&mut self) {
+        let mut renamed = Vec::new();
+        let mut table_names: HashSet<String> =
+            self.tables.iter().map(|t| t.id.name.clone()).collect();
+        let mut index_names: HashSet<String> = self
+            .tables
+            .iter()
+            .flat_map(|t| t.indexes.iter().flatten())
+            .filter_map(|index| index.name.as_ref().map(|name| name.name.clone()))
+            .collect();
+        let mut tables: HashMap<String, String> = HashMap::new();
+        let mut columns: HashMap<(String, String), String> = HashMap::new();
+
+        for table in &mut self.tables {
+            let table_name = table.id.name.clone();
+            if let Some(name) = rename(&mut table.id, "table", &mut table_names, &mut renamed) {
+                tables.insert(table_name.clone(), name);
+            }
+
+            let mut column_names: HashSet<String> =
+                table.columns.iter().map(|c| c.id.name.clone()).collect();
+            let mut own: HashMap<String, String> = HashMap::new();
+            for column in &mut table.columns {
+                let column_name = column.id.name.clone();
+                if let Some(name) =
+                    rename(&mut column.id, "column", &mut column_names, &mut renamed)
+                {
+                    columns.insert((table_name.clone(), column_name.clone()), name.clone());
+                    own.insert(column_name, name);
+                }
+            }
+            if !own.is_empty() {
+                rename_columns(table, &own);
+            }
+
+            for name in table.indexes.iter_mut().flatten().flat_map(|i| &mut i.name) {
+                rename(name, "index", &mut index_names, &mut renamed);
+            }
+        }
+
+        // references name the columns of other tables
+        for reference in self
+            .tables
+            .iter_mut()
+            .flat_map(|t| &mut t.columns)
+            .flat_map(|c| &mut c.reference)
+        {
+            let key = (reference.table.name.clone(), reference.column.name.clone());
+            if let Some(name) = columns.get(&key) {
+                reference.column.name = name.clone();
+            }
+            if let Some(name) = tables.get(&key.0) {
+                reference.table.name = name.clone();
+            }
+        }
+
+        self.warnings.extend(renamed);
+    }
+
+    fn table(&self, name: &str) -> Option<&TableDef> {
+        self.tables.iter().find(|t| t.id.name == name)
+    }
+
+    fn table_mut(&mut self, name: &str) -> Option<&mut TableDef> {
+        self.tables.iter_mut().find(|t| t.id.name == name)
+    }
+
+    fn column(&self, table: &str, column: &str) -> Option<&ColumnDef> {
+        self.table(table)?
+            .columns
+            .iter()
+            .find(|c| c.id.name == column)
+    }
+
+    fn column_mut(&mut self, table: &str, column: &str) -> Option<&mut ColumnDef> {
+        self.table_mut(table)?
+            .columns
+            .iter_mut()
+            .find(|c| c.id.name == column)
+    }
+
+    /// Reads `( expr )`, the expression is `None` when mecha can't express it
+    fn parenthesized_expr(&mut self) -> Result<(Option<Expr>, SimpleSpan), Unsupported> {
+        let start = self.span().start;
+        if !self.is_symbol("(") {
+            return Err(self.unexpected("'('"));
+        }
+        let close = self.closing_paren()?;
+        self.pos += 1;
+        let expr = self.expr_until(close);
+        self.pos = close + 1;
+        Ok((expr, self.since(start)))
+    }
+
+    /// Reads an expression spanning every token up to `end`
+    fn expr_until(&mut self, end: usize) -> Option<Expr> {
+        let limit = std::mem::replace(&mut self.limit, end);
+        let expr = self.or_expr().filter(|_| self.pos == end);
+        self.limit = limit;
+        self.pos = end;
+        expr
+    }
+
+    fn binary(
+        &mut self,
+        operand: fn(&mut Self) -> Option<Expr>,
+        operator: fn(&Sql) -> Option<BinaryOp>,
+    ) -> Option<Expr> {
+        let start = self.span().start;
+        let mut lhs = operand(self)?;
+        while let Some(op) = self.peek().and_then(operator) {
+            self.pos += 1;
+            let rhs = operand(self)?;
+            lhs = Expr::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                span: self.since(start),
+            };
+        }
+        Some(lhs)
+    }
+
+    fn or_expr(&mut self) -> Option<Expr> {
+        self.binary(Self::and_expr, |token| {
+            is_keyword(token, "or").then_some(BinaryOp::Or)
+        })
+    }
+
+    fn and_expr(&mut self) -> Option<Expr> {
+        self.binary(Self::not_expr, |token| {
+            is_keyword(token, "and").then_some(BinaryOp::And)
+        })
+    }
+
+    fn not_expr(&mut self) -> Option<Expr> {
+        let start = self.span().start;
+        if !self.eat_word("not") {
+            return self.null_test();
+        }
+        let expr = self.not_expr()?;
+        Some(Expr::Unary {
+            op: UnaryOp::Not,
+            expr: Box::new(expr),
+            span: self.since(start),
+        })
+    }
+
+    fn null_test(&mut self) -> Option<Expr> {
+        let start = self.span().start;
+        let expr = self.comparison()?;
+        if !self.eat_word("is") {
+            return Some(expr);
+        }
+        let negated = self.eat_word("not");
+        if !self.eat_word("null") {
+            return None;
+        }
+        Some(Expr::IsNull {
+            expr: Box::new(expr),
+            negated,
+            span: self.since(start),
+        })
+    }
+
+    fn comparison(&mut self) -> Option<Expr> {
+        self.binary(Self::concat, |token| match token {
+            Sql::Symbol("=") => Some(BinaryOp::Eq),
+            Sql::Symbol("<>" | "!=") => Some(BinaryOp::NotEq),
+            Sql::Symbol("<") => Some(BinaryOp::Lt),
+            Sql::Symbol("<=") => Some(BinaryOp::LtEq),
+            Sql::Symbol(">") => Some(BinaryOp::Gt),
+            Sql::Symbol(">=") => Some(BinaryOp::GtEq),
+            _ => None,
+        })
+    }
+
+    fn concat(&mut self) -> Option<Expr> {
+        self.binary(Self::sum, |token| match token {
+            Sql::Symbol("||") => Some(BinaryOp::Concat),
+            _ => None,
+        })
+    }
+
+    fn sum(&mut self) -> Option<Expr> {
+        self.binary(Self::product, |token| match token {
+            Sql::Symbol("+") => Some(BinaryOp::Add),
+            Sql::Symbol("-") => Some(BinaryOp::Sub),
+            _ => None,
+        })
+    }
+
+    fn product(&mut self) -> Option<Expr> {
+        self.binary(Self::negation, |token| match token {
+            Sql::Symbol("*") => Some(BinaryOp::Mul),
+            Sql::Symbol("/") => Some(BinaryOp::Div),
+            _ => None,
+        })
+    }
+
+    fn negation(&mut self) -> Option<Expr> {
+        let start = self.span().start;
+        if !self.eat_symbol("-") {
+            return self.atom();
+        }
+        let expr = self.negation()?;
+        Some(Expr::Unary {
+            op: UnaryOp::Neg,
+            expr: Box::new(expr),
+            span: self.since(start),
+        })
+    }
+
+    fn atom(&mut self) -> Option<Expr> {
+        // words that read as columns but are functions or SQL syntax mecha doesn't have
+        const RESERVED: &[&str] = &[
+            "and",
+            "or",
+            "not",
+            "is",
+            "in",
+            "like",
+            "ilike",
+            "between",
+            "case",
+            "when",
+            "then",
+            "else",
+            "end",
+            "any",
+            "all",
+            "some",
+            "exists",
+            "select",
+            "array",
+            "cast",
+            "current_date",
+            "current_time",
+            "current_timestamp",
+            "localtime",
+            "localtimestamp",
+            "current_user",
+            "session_user",
+            "user",
+        ];
+
+        let span = self.span();
+        let literal = match self.peek()? {
+            // mecha numbers have no exponent
+            Sql::Number(value) if !value.contains(['e', 'E']) => {
+                Some(Literal::Number(value.to_string()))
+            }
+            Sql::Str(value) => Some(Literal::String(value.clone())),
+            Sql::Word(word) if word.eq_ignore_ascii_case("true") => Some(Literal::Bool(true)),
+            Sql::Word(word) if word.eq_ignore_ascii_case("false") => Some(Literal::Bool(false)),
+            Sql::Word(word) if word.eq_ignore_ascii_case("null") => Some(Literal::Null),
+            Sql::Word(word) if RESERVED.iter().any(|r| word.eq_ignore_ascii_case(r)) => {
+                return None;
+            }
+            Sql::Word(_) | Sql::Quoted(_) => None,
+            Sql::Symbol("(") => {
+                self.pos += 1;
+                let expr = self.or_expr()?;
+                return self.eat_symbol(")").then_some(expr);
+            }
+            _ => return None,
+        };
+        if let Some(literal) = literal {
+            self.pos += 1;
+            return Some(Expr::Literal(literal, span));
+        }
+
+        let column = self.qualified_name().ok()?;
+        // function calls aren't expressions of mecha
+        if self.is_symbol("(") {
+            return None;
+        }
+        Some(Expr::Column(column))
+    }
+
+    fn name(&mut self) -> Result<Ident, Unsupported> {
+        let span = self.span();
+        let name = match self.peek() {
+            // postgres folds unquoted names to lower case
+            Some(Sql::Word(word)) if self.dialect == Dialect::Postgres => word.to_lowercase(),
+            Some(Sql::Word(word)) => word.to_string(),
+            Some(Sql::Quoted(name)) => name.clone(),
+            _ => return Err(self.unexpected("a name")),
+        };
+        self.pos += 1;
+        Ok(Ident { name, span })
+    }
+
+    /// Reads `schema.table`, mecha has no schemas so only the last name is kept
+    fn qualified_name(&mut self) -> Result<Ident, Unsupported> {
+        let mut name = self.name()?;
+        while self.eat_symbol(".") {
+            name = self.name()?;
+        }
+        Ok(name)
+    }
+
+    fn column_list(&mut self) -> Result<Vec<Ident>, Unsupported> {
+        self.expect_symbol("(")?;
+        let mut columns = Vec::new();
+        loop {
+            columns.push(self.name()?);
+            if !self.eat_word("asc") {
+                self.eat_word("desc");
+            }
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        self.expect_symbol(")")?;
+        Ok(columns)
+    }
+
+    /// Skips a default value up to the next column constraint. The value itself may be a
+    /// word such as `NULL` or `CURRENT_TIMESTAMP`, so its first token is always skipped.
+    fn skip_default(&mut self) {
+        self.skip_token();
+        while self.peek().is_some()
+            && !self.is_symbol(",")
+            && !self.is_symbol(")")
+            && !self.is_column_constraint()
+        {
+            self.skip_token();
+        }
+    }
+
+    /// Skips the rest of a table element, up to its `,` or the closing `)`
+    fn skip_element(&mut self) {
+        while self.peek().is_some() && !self.is_symbol(",") && !self.is_symbol(")") {
+            self.skip_token();
+        }
+    }
+
+    /// Skips a token, or a whole parenthesized group
+    fn skip_token(&mut self) {
+        match self.closing_paren() {
+            Ok(close) if self.is_symbol("(") => self.pos = close + 1,
+            _ => self.pos += 1,
+        }
+    }
+
+    fn skip_statement(&mut self) {
+        self.pos = self.statement_end();
+        self.pos += 1;
+    }
+
+    /// Skips a psql meta-command such as `\connect`, which ends with its line
+    fn skip_line(&mut self) {
+        let start = self.span().start;
+        while let Some((_, span)) = self.tokens.get(self.pos) {
+            if self.src[start..span.start].contains('\n') {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn statement_end(&self) -> usize {
+        (self.pos..self.tokens.len())
+            .find(|&i| self.tokens[i].0 == Sql::Symbol(";"))
+            .unwrap_or(self.tokens.len())
+    }
+
+    fn at_statement_end(&self) -> bool {
+        self.pos >= self.tokens.len() || self.is_symbol(";")
+    }
+
+    fn closing_paren(&self) -> Result<usize, Unsupported> {
+        let mut depth = 0;
+        for i in self.pos..self.tokens.len() {
+            match self.tokens[i].0 {
+                Sql::Symbol("(") => depth += 1,
+                Sql::Symbol(")") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(i);
+                    }
+                }
+                Sql::Symbol(";") => break,
+                _ => {}
+            }
+        }
+        Err((self.span(), "unclosed '('".to_string()))
+    }
+
+    fn peek(&self) -> Option<&Sql<'s>> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Sql<'s>> {
+        let pos = self.pos + offset;
+        (pos < self.limit)
+            .then(|| self.tokens.get(pos).map(|(token, _)| token))
+            .flatten()
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        self.is_word_at(0, word)
+    }
+
+    fn is_word_at(&self, offset: usize, word: &str) -> bool {
+        self.peek_at(offset)
+            .is_some_and(|token| is_keyword(token, word))
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let is_word = self.is_word(word);
+        if is_word {
+            self.pos += 1;
+        }
+        is_word
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), Unsupported> {
+        if self.eat_word(word) {
+            return Ok(());
+        }
+        Err(self.unexpected(&format!("'{}'", word.to_uppercase())))
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        self.is_symbol_at(0, symbol)
+    }
+
+    fn is_symbol_at(&self, offset: usize, symbol: &str) -> bool {
+        matches!(self.peek_at(offset), Some(Sql::Symbol(s)) if *s == symbol)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let is_symbol = self.is_symbol(symbol);
+        if is_symbol {
+            self.pos += 1;
+        }
+        is_symbol
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), Unsupported> {
+        if self.eat_symbol(symbol) {
+            return Ok(());
+        }
+        Err(self.unexpected(&format!("'{symbol}'")))
+    }
+
+    fn unexpected(&self, expected: &str) -> Unsupported {
+        let found = match self.peek() {
+            Some(_) => format!("'{}'", self.text(self.span())),
+            None => "the end of the statement".to_string(),
+        };
+        (self.span(), format!("expected {expected}, found {found}"))
+    }
+
+    /// Span of the current token, or the empty span after the last one
+    fn span(&self) -> SimpleSpan {
+        match self.tokens.get(self.pos) {
+            Some((_, span)) if self.pos < self.limit => *span,
+            _ => {
+                let end = self.pos.min(self.tokens.len());
+                let offset = match end {
+                    0 => 0,
+                    end => self.tokens[end - 1].1.end,
+                };
+                (offset..offset).into()
+            }
+        }
+    }
+
+    /// Span from `start` to the end of the last token read
+    fn since(&self, start: usize) -> SimpleSpan {
+        let end = match self.pos {
+            0 => start,
+            pos => self.tokens[pos.min(self.tokens.len()) - 1].1.end.max(start),
+        };
+        (start..end).into()
+    }
+
+    fn text(&self, span: SimpleSpan) -> &'s str {
+        &self.src[span.into_range()]
+    }
+}
+
+/// Adds an index to a table, leaving out the name mecha would give it anyway
+fn push_index(table: &mut TableDef, mut index: Index) {
+    if index
+        .name
+        .as_ref()
+        .is_some_and(|name| name.name == default_index_name(table, &index))
+    {
+        index.name = None;
+    }
+    table.indexes.get_or_insert_with(Vec::new).push(index);
+}
+
+fn is_keyword(token: &Sql, keyword: &str) -> bool {
+    matches!(token, Sql::Word(word) if word.eq_ignore_ascii_case(keyword))
+}
+
+/// Renames an identifier that isn't a valid mecha one to a name not taken yet, the new name
+/// is returned
+fn rename(
+    id: &mut Ident,
+    kind: &str,
+    taken: &mut HashSet<String>,
+    renamed: &mut Vec<Unsupported>,
+) -> Option<String> {
+    if is_ident(&id.name) {
+        return None;
+    }
+    let ident = valid_ident(&id.name, kind);
+    let name = if taken.contains(&ident) {
+        (2..)
+            .map(|n| format!("{ident}_{n}"))
+            .find(|name| !taken.contains(name))
+            .expect("a free name")
+    } else {
+        ident
+    };
+    taken.insert(name.clone());
+    renamed.push((
+        id.span,
+        format!(
+            "'{}' isn't a valid mecha identifier, it's imported as '{name}'",
+            id.name
+        ),
+    ));
+    id.name = name.clone();
+    Some(name)
+}
+
+/// The snake_case identifier closest to a SQL name, e.g. `display_name` for `Display Name`.
+/// A name that doesn't start with a letter is prefixed with its kind, and a keyword gets a
+/// trailing underscore.
+fn valid_ident(name: &str, kind: &str) -> String {
+    let words: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let mut ident = convert(&words, Convention::SnakeCase);
+    if ident.is_empty() {
+        ident = kind.to_string();
+    } else if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        ident = format!("{kind}_{ident}");
+    }
+    if !is_ident(&ident) {
+        ident.push('_');
+    }
+    ident
+}
+
+/// Renames the columns of a table where the table names them, in its checks, generated
+/// columns and indexes
+fn rename_columns(table: &mut TableDef, renamed: &HashMap<String, String>) {
+    let rename = |id: &mut Ident| {
+        if let Some(name) = renamed.get(&id.name) {
+            id.name = name.clone();
+        }
+    };
+
+    for column in &mut table.columns {
+        if let Some(check) = &mut column.check {
+            rename_expr(&mut check.expr, &rename);
+        }
+        if let Some(generated) = &mut column.generated {
+            rename_expr(&mut generated.expr, &rename);
+        }
+    }
+    for check in &mut table.checks {
+        rename_expr(&mut check.expr, &rename);
+    }
+    for index in table.indexes.iter_mut().flatten() {
+        for column in &mut index.columns {
+            rename(&mut column.id);
+        }
+        for id in index.include.iter_mut().flatten() {
+            rename(id);
+        }
+        if let Some(predicate) = &mut index.predicate {
+            rename_expr(predicate, &rename);
+        }
+    }
+}
+
+fn rename_expr(expr: &mut Expr, rename: &impl Fn(&mut Ident)) {
+    match expr {
+        Expr::Column(id) => rename(id),
+        Expr::Literal(..) => {}
+        Expr::Unary { expr, .. } | Expr::IsNull { expr, .. } => rename_expr(expr, rename),
+        Expr::Binary { lhs, rhs, .. } => {
+            rename_expr(lhs, rename);
+            rename_expr(rhs, rename);
+        }
+    }
+}
+
+/// Maps a SQL type to a mecha one, along with the exact type when the dialect wouldn't
+/// generate it back, e.g. `varchar(100)` becomes `varchar` and `@db.postgres(type = ...)`.
+/// A type that isn't a mecha identifier gets the closest one, the exact type keeps it.
+fn mecha_type(typ: &str, dialect: Dialect) -> (String, Option<String>) {
+    let typ = canonical_type(typ);
+
+    let reversed = |typ: &str| -> Option<&'static str> {
+        let mecha = match (dialect, typ) {
+            (Dialect::Postgres | Dialect::Mysql, "varchar(255)") => "string",
+            (Dialect::Postgres, "timestamptz") => "timestampz",
+            (Dialect::Postgres | Dialect::Sqlite, "numeric") => "number",
+            (Dialect::Postgres, "smallserial") => "smallint",
+            (Dialect::Postgres, "serial") => "integer",
+            (Dialect::Postgres, "bigserial") => "bigint",
+            (Dialect::Mysql, "decimal") => "number",
+            (Dialect::Mysql, "char(36)") => "uuid",
+            _ => return None,
+        };
+        Some(mecha)
+    };
+
+    // the type without its parameters and array brackets, e.g. `numeric` of `numeric(10, 2)`
+    let mut bare = String::new();
+    let mut depth = 0;
+    for c in typ.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            c if depth == 0 => bare.push(c),
+            _ => {}
+        }
+    }
+    let bare = bare.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let mecha = reversed(&typ)
+        .or_else(|| reversed(&bare))
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            // `double` of `double precision`, `status` of `public.status`
+            let first = bare.split(' ').next().unwrap_or_default();
+            let name = first.rsplit('.').next().unwrap_or_default();
+            if is_ident(name) {
+                name.to_string()
+            } else {
+                valid_ident(name, "type")
+            }
+        });
+    let exact = (dialect.column_type(&mecha) != typ).then_some(typ);
+    (mecha, exact)
+}
+
+/// Folds the long spellings of postgres types into the ones it accepts in short
+fn canonical_type(typ: &str) -> String {
+    let mut typ = typ.replace("character varying", "varchar");
+    if typ == "character" || typ.starts_with("character(") {
+        typ = typ.replacen("character", "char", 1);
+    }
+    if let Some(zoned) = typ.strip_suffix(" without time zone") {
+        typ = zoned.to_string();
+    } else if let Some(zoned) = typ.strip_suffix(" with time zone") {
+        // `timestamp(3) with time zone` is `timestamptz(3)`
+        typ = match zoned.split_once('(') {
+            Some((name, precision)) => format!("{name}tz({precision}"),
+            None => format!("{zoned}tz"),
+        };
+    }
+    typ
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sql, sqlite};
+    use crate::ddl::Dialect;
+    use crate::format::format;
+    use rusqlite::Connection;
+
+    fn assert_import(src: &str, dialect: Dialect, expected: &str, expected_warnings: &[&str]) {
+        let (schema, warnings) = sql("schema.sql", src, dialect);
+        assert_eq!(format(&schema), expected);
+
+        let warnings: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(warnings, expected_warnings);
+    }
+
+    #[test]
+    fn test_postgres_dump() {
+        let src = r#"
+            SET statement_timeout = 0;
+            SELECT pg_catalog.set_config('search_path', '', false);
+
+            CREATE TABLE public.users (
+                id uuid NOT NULL,
+                email character varying(255) NOT NULL,
+                "displayName" text,
+                age integer DEFAULT 0,
+                created_at timestamp with time zone,
+                CONSTRAINT users_age_check CHECK ((age >= 0))
+            );
+
+            ALTER TABLE public.users OWNER TO postgres;
+
+            CREATE TABLE public.posts (
+                id bigint NOT NULL,
+                user_id uuid REFERENCES public.users ON DELETE CASCADE,
+                price numeric(10, 2),
+                title text,
+                status public.post_status,
+                slug text GENERATED ALWAYS AS (lower(title)) STORED,
+                CHECK (price > 0 OR title IS NOT NULL)
+            );
+
+            COMMENT ON TABLE public.posts IS 'blog posts';
+            COMMENT ON COLUMN public.posts.title IS 'shown in lists';
+
+            CREATE SEQUENCE public.posts_id_seq;
+
+            ALTER TABLE ONLY public.users
+                ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+            ALTER TABLE ONLY public.users
+                ADD CONSTRAINT users_email_key UNIQUE (email);
+            ALTER TABLE ONLY public.posts
+                ADD CONSTRAINT posts_pkey PRIMARY KEY (id);
+
+            CREATE INDEX idx_posts_user_id ON public.posts USING btree (user_id);
+            CREATE UNIQUE INDEX posts_title ON public.posts (title DESC) WHERE (title <> '');
+            CREATE INDEX posts_lower_title ON public.posts (lower(title));
+        "#;
+        let expected = r#"table users {
+    id:          uuid primary (ref => posts.user_id),
+    email:       string unique,
+    displayName: text,
+    age:         integer check (age >= 0),
+    created_at:  timestampz
+}
+
+/// blog posts
+table posts {
+    id:      bigint primary,
+    user_id: uuid,
+    @db.postgres(type = 'numeric(10, 2)')
+    price:   number,
+    /// shown in lists
+    title:   text,
+    @db.postgres(type = 'public.post_status')
+    status:  post_status,
+    slug:    text,
+    check (price > 0 or title is not null)
+    indexes {
+        (user_id) using btree,
+        unique posts_title (title desc) where title != ''
+    }
+}
+"#;
+        assert_import(
+            src,
+            Dialect::Postgres,
+            expected,
+            &[
+                "NOT NULL isn't modeled by mecha, it's dropped from 3 column(s)",
+                "default of 'age' isn't supported, it's dropped",
+                "'ON DELETE CASCADE' isn't supported, it's dropped",
+                "generated expression of 'slug' isn't supported, it's imported as a plain column",
+                "'CREATE SEQUENCE' isn't supported, the statement is skipped",
+                "expression indexes aren't supported, the index is skipped",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_invalid_identifiers() {
+        let src = r#"
+            CREATE TABLE "User Accounts" (
+                id bigserial PRIMARY KEY,
+                "Display Name" text CHECK ("Display Name" <> ''),
+                display_name text,
+                "table" "order-status"
+            );
+            CREATE TABLE posts (
+                author_id bigint REFERENCES "User Accounts" (id),
+                "2fa" boolean
+            );
+            CREATE INDEX "by name" ON "User Accounts" ("Display Name");
+        "#;
+        let expected = r#"table user_accounts {
+    @db.postgres(type = 'bigserial')
+    id:             bigint primary (ref => posts.author_id),
+    display_name_2: text check (display_name_2 != ''),
+    display_name:   text,
+    @db.postgres(type = 'order-status')
+    table_:         order_status
+    indexes {
+        by_name (display_name_2)
+    }
+}
+
+table posts {
+    author_id:  bigint,
+    column_2fa: boolean
+}
+"#;
+        assert_import(
+            src,
+            Dialect::Postgres,
+            expected,
+            &[
+                "'User Accounts' isn't a valid mecha identifier, it's imported as 'user_accounts'",
+                "'Display Name' isn't a valid mecha identifier, it's imported as 'display_name_2'",
+                "'table' isn't a valid mecha identifier, it's imported as 'table_'",
+                "'2fa' isn't a valid mecha identifier, it's imported as 'column_2fa'",
+                "'by name' isn't a valid mecha identifier, it's imported as 'by_name'",
+            ],
+        );
+
+        // the output is a valid schema
+        let (schema, _) = sql("schema.sql", src, Dialect::Postgres);
+        let output = format(&schema);
+        assert!(crate::codegen::check(&output, "schema.mecha").is_ok());
+    }
+
+    #[test]
+    fn test_sqlite_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE users (id integer PRIMARY KEY, email text UNIQUE, name varchar(80));
+            CREATE TABLE posts (
+                id text PRIMARY KEY,
+                user_id integer REFERENCES users,
+                slug text GENERATED ALWAYS AS (id || 'x'),
+                score numeric(5, 2),
+                data
+            );
+            CREATE TABLE tags (post_id text, name text, PRIMARY KEY (post_id, name));
+            CREATE INDEX idx_posts_score ON posts (score);
+            CREATE INDEX posts_recent ON posts (user_id, slug);
+            CREATE INDEX posts_lower ON posts (lower(slug));
+            "#,
+        )
+        .unwrap();
+
+        let (schema, warnings) = sqlite("app.db", &conn).unwrap();
+        let expected = r#"table users {
+    id:    integer primary (ref => posts.user_id),
+    email: text unique,
+    @db.sqlite(type = 'varchar(80)')
+    name:  varchar
+}
+
+table posts {
+    id:      text primary,
+    user_id: integer,
+    slug:    text,
+    @db.sqlite(type = 'numeric(5, 2)')
+    score:   number,
+    data:    blob
+    indexes {
+        score,
+        posts_recent (user_id, slug)
+    }
+}
+
+table tags {
+    post_id: text,
+    name:    text
+    indexes {
+        unique (post_id, name)
+    }
+}
+"#;
+        assert_eq!(format(&schema), expected);
+        assert_eq!(
+            warnings,
+            [
+                "SQLite doesn't report the expression of 'posts.slug', it's imported as a plain column",
+                "column 'posts.data' has no type, it's imported as 'blob'",
+                "index 'posts_lower' has expression keys SQLite doesn't report, it's skipped",
+                "composite primary key of 'tags' isn't supported, it's imported as a unique index",
+            ]
+        );
+    }
+}
@@ -14,9 +14,21 @@ pub enum Token<'a> {
     #[token("extends")]
     Extends,
 
+    /// Words such as `desc`, `where`, `check` or `null` are keywords only where the parser
+    /// expects them, they lex as ids so that columns may still be named after them
     #[regex(r"[a-zA-Z][a-zA-Z0-9_]*")]
     Id(&'a str),
 
+    #[regex(r"[0-9]+(\.[0-9]+)?")]
+    Number(&'a str),
+    #[regex(r#"'([^'\\]|\\.)*'"#, unquote)]
+    #[regex(r#""([^"\\]|\\.)*""#, unquote)]
+    Str(&'a str),
+    #[token("true")]
+    True,
+    #[token("false")]
+    False,
+
     #[token("{")]
     LeftBrace,
     #[token("}")]
@@ -43,6 +55,8 @@ pub enum Token<'a> {
 
     #[token(".")]
     Dot,
+    #[token("@")]
+    At,
     #[token(",")]
     Comma,
     #[token(":")]
@@ -50,12 +64,37 @@ pub enum Token<'a> {
 
     #[token("indexes")]
     Indexes,
+    #[token("=")]
+    Eq,
+    #[token("!=")]
+    NotEq,
+    #[token("<")]
+    Lt,
+    #[token("<=")]
+    LtEq,
+    #[token(">")]
+    Gt,
+    #[token(">=")]
+    GtEq,
+    #[token("-")]
+    Minus,
+    #[token("+")]
+    Plus,
+    #[token("*")]
+    Star,
+    #[token("/")]
+    Slash,
+    #[token("||")]
+    Concat,
 
     #[regex(r"[ \t\f\n]+", logos::skip)]
     Whitespace,
 
-    #[regex(r"//[^\n]*", logos::skip)]
-    Comment,
+    #[regex(r"//[^\n]*", |lex| &lex.slice()[2..])]
+    Comment(&'a str),
+
+    #[regex(r"///[^\n]*", |lex| &lex.slice()[3..])]
+    DocComment(&'a str),
 }
 
 impl<'a> Display for Token<'a> {
@@ -66,6 +105,10 @@ impl<'a> Display for Token<'a> {
             Token::Table => write!(f, "table"),
             Token::Extends => write!(f, "extends"),
             Token::Id(name) => write!(f, "Id<{name}>"),
+            Token::Number(value) => write!(f, "{value}"),
+            Token::Str(value) => write!(f, "'{value}'"),
+            Token::True => write!(f, "true"),
+            Token::False => write!(f, "false"),
             Token::LeftBrace => write!(f, "{{"),
             Token::RightBrace => write!(f, "}}"),
             Token::LeftParen => write!(f, "("),
@@ -77,23 +120,94 @@ impl<'a> Display for Token<'a> {
             Token::RefOneToOne => write!(f, "=="),
             Token::RefManyToMany => write!(f, "<>"),
             Token::Dot => write!(f, "."),
+            Token::At => write!(f, "@"),
             Token::Comma => write!(f, ","),
             Token::Colon => write!(f, ":"),
             Token::Indexes => write!(f, "indexes"),
+            Token::Eq => write!(f, "="),
+            Token::NotEq => write!(f, "!="),
+            Token::Lt => write!(f, "<"),
+            Token::LtEq => write!(f, "<="),
+            Token::Gt => write!(f, ">"),
+            Token::GtEq => write!(f, ">="),
+            Token::Minus => write!(f, "-"),
+            Token::Plus => write!(f, "+"),
+            Token::Star => write!(f, "*"),
+            Token::Slash => write!(f, "/"),
+            Token::Concat => write!(f, "||"),
             Token::Whitespace => write!(f, "<whitespace>"),
-            Token::Comment => write!(f, "<comment>"),
+            Token::Comment(_) => write!(f, "<comment>"),
+            Token::DocComment(_) => write!(f, "<doc comment>"),
         }
     }
 }
 
-pub fn lex(src: &'_ str) -> impl ValueInput<'_, Token = Token<'_>, Span = SimpleSpan> {
-    let token_iter = Token::lexer(src).spanned().map(|(tok, span)| match tok {
+/// Strips the surrounding quotes of a string literal, escapes are kept as written
+fn unquote<'a>(lex: &mut logos::Lexer<'a, Token<'a>>) -> &'a str {
+    let slice = lex.slice();
+    &slice[1..slice.len() - 1]
+}
+
+/// Every token of the source along with its span, comments included. Only whitespace is
+/// left out, it's what lies between the spans.
+pub fn tokens(src: &'_ str) -> impl Iterator<Item = (Token<'_>, SimpleSpan)> {
+    Token::lexer(src).spanned().map(|(tok, span)| match tok {
         Ok(tok) => {
             let simple_span: SimpleSpan = span.into();
             (tok, simple_span)
         }
         Err(()) => (Token::Err, span.into()),
-    });
+    })
+}
+
+pub fn lex(src: &'_ str) -> impl ValueInput<'_, Token = Token<'_>, Span = SimpleSpan> {
+    Stream::from_iter(significant(src)).map((0..src.len()).into(), |(t, s): (_, _)| (t, s))
+}
+
+/// The tokens the parser reads. Comments are left out, and so are doc comments that don't
+/// document a table or a column, e.g. after the last column, which read as comments.
+pub(crate) fn significant(src: &'_ str) -> Vec<(Token<'_>, SimpleSpan)> {
+    let tokens: Vec<_> = tokens(src)
+        .filter(|(tok, _)| !matches!(tok, Token::Comment(_)))
+        .collect();
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(i, (tok, _))| !matches!(tok, Token::DocComment(_)) || documents(&tokens[i + 1..]))
+        .map(|(_, token)| token.clone())
+        .collect()
+}
+
+/// Whether the tokens after a doc comment start a table or a column, the annotations of
+/// either included
+fn documents(rest: &[(Token, SimpleSpan)]) -> bool {
+    let mut rest = rest
+        .iter()
+        .map(|(tok, _)| tok)
+        .filter(|tok| !matches!(tok, Token::DocComment(_)))
+        .peekable();
+    while let Some(tok) = rest.next() {
+        match tok {
+            Token::Abstract | Token::Table => return true,
+            Token::Id(_) => return rest.next() == Some(&Token::Colon),
+            Token::At => {
+                // a name such as `db.postgres`, then the arguments if any
+                rest.next();
+                while rest.next_if_eq(&&Token::Dot).is_some() {
+                    rest.next();
+                }
+                if rest.next_if_eq(&&Token::LeftParen).is_some() {
+                    rest.find(|tok| **tok == Token::RightParen);
+                }
+            }
+            _ => return false,
+        }
+    }
+    false
+}
 
-    Stream::from_iter(token_iter).map((0..src.len()).into(), |(t, s): (_, _)| (t, s))
+/// Whether a name lexes as a single identifier, rather than a keyword or anything else
+pub(crate) fn is_ident(name: &str) -> bool {
+    let mut tokens = Token::lexer(name);
+    matches!(tokens.next(), Some(Ok(Token::Id(id))) if id == name) && tokens.next().is_none()
 }
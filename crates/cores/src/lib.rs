@@ -1,5 +1,20 @@
 pub mod ast;
 pub mod codegen;
+pub mod compat;
+pub mod cst;
+pub mod ddl;
+pub mod diagnostic;
+pub mod diff;
+pub mod explain;
+pub mod format;
+pub mod import;
 pub mod lexer;
+pub mod lint;
+pub mod merge;
+pub mod migrate;
+pub mod naming;
 pub mod parser;
+pub mod rules;
+pub mod sarif;
 pub mod semantic;
+pub mod sqlite;
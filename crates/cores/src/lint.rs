@@ -0,0 +1,533 @@
+use crate::ast::{
+    Annotation, ColumnAttribute, Ident, Index, IndexColumn, IndexMethod, Literal, RefOperator,
+    Schema, SortOrder, TableDef,
+};
+use crate::diagnostic::{self, Diagnostic};
+use crate::naming::{self, Convention};
+use crate::rules::{self, Config, Rule, Severity};
+use chumsky::span::SimpleSpan;
+use std::collections::{HashMap, HashSet};
+
+/// Schema designs that compile, but are likely mistakes or go against a convention
+pub const RULES: &[Rule] = &[
+    Rule {
+        code: "L001",
+        name: "missing-primary-key",
+        severity: Severity::Warning,
+        summary: "a table has no primary key",
+    },
+    Rule {
+        code: "L002",
+        name: "unindexed-foreign-key",
+        severity: Severity::Warning,
+        summary: "a foreign key column has no index starting with it",
+    },
+    Rule {
+        code: "L003",
+        name: "prefix-index",
+        severity: Severity::Warning,
+        summary: "an index is a prefix of another index of the table, which covers it",
+    },
+    Rule {
+        code: "L004",
+        name: "unused-abstract-table",
+        severity: Severity::Warning,
+        summary: "an abstract table isn't extended by any table",
+    },
+    Rule {
+        code: "L005",
+        name: "naming-convention",
+        severity: Severity::Warning,
+        summary: "a name isn't snake_case, or a table name isn't plural like the others",
+    },
+];
+
+/// Looks a rule up by its code or its name, e.g. `L001` or `missing-primary-key`
+pub fn rule(code_or_name: &str) -> Option<&'static Rule> {
+    rules::find(RULES, code_or_name)
+}
+
+/// Runs every rule that isn't turned off over a schema that went through
+/// [`Schema::check`]. A rule is skipped for a table, a column or an index annotated with
+/// `@allow("<code|name>")`, a table allows it for everything in it.
+pub fn lint(schema: &Schema, config: &Config) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        config,
+        diagnostics: Vec::new(),
+    };
+
+    missing_primary_key(schema, &mut linter);
+    unindexed_foreign_keys(schema, &mut linter);
+    prefix_indexes(schema, &mut linter);
+    unused_abstract_tables(schema, &mut linter);
+    naming_convention(schema, &mut linter);
+
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.primary.span.start);
+    diagnostics
+}
+
+struct Linter<'c> {
+    config: &'c Config,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    /// Reports a rule with the severity it's configured with, unless it's turned off or
+    /// allowed by any of the annotations in scope
+    fn report(&mut self, code: &str, scope: &[&[Annotation]], mut diagnostic: Diagnostic) {
+        let rule = rule(code).expect("lint rules report their own code");
+        if scope.iter().any(|annotations| allows(annotations, rule)) {
+            return;
+        }
+
+        diagnostic.severity = match self.config.severity(rule) {
+            Severity::Off => return,
+            Severity::Error => diagnostic::Severity::Error,
+            Severity::Warning => diagnostic::Severity::Warning,
+        };
+        self.diagnostics.push(diagnostic.with_code(rule.code));
+    }
+}
+
+fn allows(annotations: &[Annotation], rule: &Rule) -> bool {
+    annotations
+        .iter()
+        .filter(|annotation| annotation.name.name == "allow")
+        .flat_map(|annotation| &annotation.args)
+        .any(|arg| matches!(&arg.value, Literal::String(name) if name == rule.code || name == rule.name))
+}
+
+/// Whether a member lies in the table's own source, rather than being copied from a parent
+fn declared_in(table: &TableDef, span: SimpleSpan) -> bool {
+    table.span.start <= span.start && span.end <= table.span.end
+}
+
+fn missing_primary_key(schema: &Schema, linter: &mut Linter) {
+    for table in schema.tables.iter().filter(|t| !t.is_abstract) {
+        let has_primary_key = table
+            .columns
+            .iter()
+            .any(|column| column.attribute == Some(ColumnAttribute::Primary));
+        if !has_primary_key {
+            linter.report(
+                "L001",
+                &[&table.annotations],
+                Diagnostic::warning(
+                    table.id.span,
+                    format!("table '{}' has no primary key", table.id.name),
+                )
+                .with_help("mark a column as `primary`"),
+            );
+        }
+    }
+}
+
+/// `a.x (ref => b.y)` puts the foreign key on `b.y`, which is looked up through `b` by
+/// every delete or update of `a`
+fn unindexed_foreign_keys(schema: &Schema, linter: &mut Linter) {
+    let tables: HashMap<&str, &TableDef> = schema
+        .tables
+        .iter()
+        .map(|table| (table.id.name.as_str(), table))
+        .collect();
+
+    for table in &schema.tables {
+        for column in &table.columns {
+            let Some(reference) = &column.reference else {
+                continue;
+            };
+            if matches!(reference.operator, RefOperator::ManyToMany)
+                || !declared_in(table, column.span)
+            {
+                continue;
+            }
+            let Some(owner) = tables.get(reference.table.name.as_str()) else {
+                continue;
+            };
+            if owner.is_abstract || leads_an_index(owner, &reference.column.name) {
+                continue;
+            }
+
+            linter.report(
+                "L002",
+                &[&table.annotations, &column.annotations],
+                Diagnostic::warning(
+                    reference.span,
+                    format!(
+                        "foreign key column '{}.{}' isn't indexed",
+                        owner.id.name, reference.column.name
+                    ),
+                )
+                .with_label(owner.id.span, "the foreign key is on this table")
+                .with_help(format!(
+                    "add `{}` to the indexes of '{}'",
+                    reference.column.name, owner.id.name
+                )),
+            );
+        }
+    }
+}
+
+/// Whether a column is the first of the primary key, of a unique column or of an index
+fn leads_an_index(table: &TableDef, column: &str) -> bool {
+    let primary: Vec<&str> = table
+        .columns
+        .iter()
+        .filter(|c| c.attribute == Some(ColumnAttribute::Primary))
+        .map(|c| c.id.name.as_str())
+        .collect();
+    if primary.first() == Some(&column) {
+        return true;
+    }
+
+    let unique = table
+        .columns
+        .iter()
+        .any(|c| c.id.name == column && c.attribute == Some(ColumnAttribute::Unique));
+    unique
+        || table.indexes.iter().flatten().any(|index| {
+            index.predicate.is_none() && index.columns.first().is_some_and(|c| c.id.name == column)
+        })
+}
+
+/// An index is covered by another one starting with the same columns in the same order,
+/// unless it's unique, partial, or of another method
+fn prefix_indexes(schema: &Schema, linter: &mut Linter) {
+    let method = |index: &Index| {
+        index
+            .using
+            .as_ref()
+            .map_or(IndexMethod::Btree, |u| u.method)
+    };
+    let descending = |column: &IndexColumn| column.order == Some(SortOrder::Desc);
+
+    for table in &schema.tables {
+        let indexes = table.indexes.as_deref().unwrap_or_default();
+        for index in indexes {
+            if index.is_unique || index.predicate.is_some() {
+                continue;
+            }
+
+            let covering = indexes.iter().find(|other| {
+                other.columns.len() > index.columns.len()
+                    && other.predicate.is_none()
+                    && method(other) == method(index)
+                    && index
+                        .columns
+                        .iter()
+                        .zip(&other.columns)
+                        .all(|(a, b)| a.id.name == b.id.name && descending(a) == descending(b))
+            });
+            let Some(covering) = covering else {
+                continue;
+            };
+
+            linter.report(
+                "L003",
+                &[&table.annotations, &index.annotations],
+                Diagnostic::warning(
+                    index.span,
+                    format!("index {index} is a prefix of {covering}"),
+                )
+                .with_label(covering.span, "this index covers it"),
+            );
+        }
+    }
+}
+
+fn unused_abstract_tables(schema: &Schema, linter: &mut Linter) {
+    let extended: HashSet<&str> = schema
+        .tables
+        .iter()
+        .filter_map(|table| table.extended_by.as_ref())
+        .map(|parent| parent.name.as_str())
+        .collect();
+
+    for table in schema.tables.iter().filter(|t| t.is_abstract) {
+        if !extended.contains(table.id.name.as_str()) {
+            linter.report(
+                "L004",
+                &[&table.annotations],
+                Diagnostic::warning(
+                    table.id.span,
+                    format!("abstract table '{}' isn't extended", table.id.name),
+                )
+                .with_help("extend it, or remove it"),
+            );
+        }
+    }
+}
+
+/// Names are snake_case, and table names follow whether most tables are plural or singular
+fn naming_convention(schema: &Schema, linter: &mut Linter) {
+    let mut snake_case = |scope: &[&[Annotation]], kind: &str, id: &Ident| {
+        if !is_snake_case(&id.name) {
+            linter.report(
+                "L005",
+                scope,
+                Diagnostic::warning(id.span, format!("{kind} '{}' isn't snake_case", id.name))
+                    .with_help(format!(
+                        "rename it to `{}`",
+                        naming::convert(&id.name, Convention::SnakeCase)
+                    )),
+            );
+        }
+    };
+
+    for table in &schema.tables {
+        snake_case(&[&table.annotations], "table", &table.id);
+        for column in &table.columns {
+            if declared_in(table, column.span) {
+                snake_case(
+                    &[&table.annotations, &column.annotations],
+                    "column",
+                    &column.id,
+                );
+            }
+        }
+        for index in table.indexes.iter().flatten() {
+            if let Some(name) = &index.name {
+                snake_case(&[&table.annotations, &index.annotations], "index", name);
+            }
+        }
+    }
+
+    // abstract tables describe a part of a row, such as `timestamps` or `metadata`
+    let concrete: Vec<&TableDef> = schema.tables.iter().filter(|t| !t.is_abstract).collect();
+    let plurals = concrete.iter().filter(|t| is_plural(&t.id.name)).count();
+    let singulars = concrete.len() - plurals;
+    if plurals == singulars {
+        return;
+    }
+    let most_plural = plurals > singulars;
+
+    for table in concrete {
+        if is_plural(&table.id.name) != most_plural {
+            let (is, most) = if most_plural {
+                ("singular", "plural")
+            } else {
+                ("plural", "singular")
+            };
+            linter.report(
+                "L005",
+                &[&table.annotations],
+                Diagnostic::warning(
+                    table.id.span,
+                    format!(
+                        "table '{}' is {is}, while most tables are {most}",
+                        table.id.name
+                    ),
+                ),
+            );
+        }
+    }
+}
+
+fn is_snake_case(name: &str) -> bool {
+    name.split('_').all(|word| {
+        !word.is_empty()
+            && word
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    }) && !name.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Whether the last word of a name is plural, as far as an English ending tells
+fn is_plural(name: &str) -> bool {
+    let word = name.rsplit('_').next().unwrap_or(name).to_lowercase();
+    if matches!(word.as_str(), "people" | "children" | "data" | "media") {
+        return true;
+    }
+    word.ends_with('s') && !word.ends_with("ss") && !word.ends_with("us") && !word.ends_with("is")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RULES, lint};
+    use crate::diagnostic::{self, Diagnostic};
+    use crate::parser::parse;
+    use crate::rules::{Config, Severity};
+
+    fn assert_lint(src: &str, config: &Config) -> Vec<Diagnostic> {
+        let schema = &mut parse("test.mecha", src).unwrap();
+        assert!(schema.check().is_ok());
+        lint(schema, config)
+    }
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<&str> {
+        diagnostics.iter().filter_map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_missing_primary_key() {
+        let src = r"
+            abstract table keyed {
+                id: uuid primary
+            }
+
+            table users extends keyed {
+                email: text
+            }
+
+            table logs {
+                message: text
+            }
+        ";
+        let diagnostics = assert_lint(src, &Config::new(RULES));
+        assert_eq!(codes(&diagnostics), vec!["L001"]);
+        assert_eq!(diagnostics[0].message, "table 'logs' has no primary key");
+    }
+
+    #[test]
+    fn test_unindexed_foreign_key() {
+        let src = r"
+            table users {
+                id: uuid primary (ref => posts.author_id)
+            }
+
+            table posts {
+                id: uuid primary,
+                author_id: uuid
+            }
+        ";
+        let diagnostics = assert_lint(src, &Config::new(RULES));
+        assert_eq!(codes(&diagnostics), vec!["L002"]);
+        assert_eq!(
+            diagnostics[0].message,
+            "foreign key column 'posts.author_id' isn't indexed"
+        );
+
+        let indexed = r"
+            table users {
+                id: uuid primary (ref => posts.author_id)
+            }
+
+            table posts {
+                id: uuid primary,
+                author_id: uuid
+                indexes {
+                    (author_id, id)
+                }
+            }
+        ";
+        assert!(assert_lint(indexed, &Config::new(RULES)).is_empty());
+    }
+
+    #[test]
+    fn test_prefix_index() {
+        let src = r"
+            table users {
+                id: uuid primary,
+                email: text,
+                name: text
+                indexes {
+                    email,
+                    (email, name),
+                    unique email_desc (email desc),
+                    name desc
+                }
+            }
+        ";
+        let diagnostics = assert_lint(src, &Config::new(RULES));
+        assert_eq!(codes(&diagnostics), vec!["L003"]);
+        assert_eq!(
+            diagnostics[0].message,
+            "index (email) is a prefix of (email, name)"
+        );
+        assert_eq!(diagnostics[0].secondary[0].message, "this index covers it");
+    }
+
+    #[test]
+    fn test_unused_abstract_table() {
+        let src = r"
+            abstract table timestamps {
+                created_at: timestamp
+            }
+
+            table users {
+                id: uuid primary
+            }
+        ";
+        let diagnostics = assert_lint(src, &Config::new(RULES));
+        assert_eq!(codes(&diagnostics), vec!["L004"]);
+    }
+
+    #[test]
+    fn test_naming_convention() {
+        let src = r"
+            table users {
+                id: uuid primary,
+                createdAt: timestamp
+            }
+
+            table posts {
+                id: uuid primary
+            }
+
+            table user_vehicle {
+                id: uuid primary
+                indexes {
+                    ByID (id)
+                }
+            }
+        ";
+        let diagnostics = assert_lint(src, &Config::new(RULES));
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "column 'createdAt' isn't snake_case",
+                "table 'user_vehicle' is singular, while most tables are plural",
+                "index 'ByID' isn't snake_case",
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].help.as_deref(),
+            Some("rename it to `created_at`")
+        );
+    }
+
+    #[test]
+    fn test_allow_and_config() {
+        let src = r#"
+            @allow("missing-primary-key")
+            table logs {
+                message: text
+            }
+
+            table events {
+                @allow("L005")
+                occurredAt: timestamp
+            }
+        "#;
+        let diagnostics = assert_lint(src, &Config::new(RULES));
+        assert_eq!(codes(&diagnostics), vec!["L001"]);
+        assert_eq!(diagnostics[0].message, "table 'events' has no primary key");
+        assert_eq!(diagnostics[0].severity, diagnostic::Severity::Warning);
+
+        let mut config = Config::new(RULES);
+        config.set("L001=error").unwrap();
+        let diagnostics = assert_lint(src, &config);
+        assert_eq!(diagnostics[0].severity, diagnostic::Severity::Error);
+
+        config
+            .set_rule("missing-primary-key", Severity::Off)
+            .unwrap();
+        assert!(assert_lint(src, &config).is_empty());
+        assert!(config.set("L999=off").is_err());
+    }
+
+    #[test]
+    fn test_allow_unknown_rule() {
+        let src = r#"
+            @allow("no-such-rule")
+            table logs {
+                message: text
+            }
+        "#;
+        let schema = &mut parse("test.mecha", src).unwrap();
+        let errs = schema.check().unwrap_err();
+        assert_eq!(errs[0].message, "unknown lint rule 'no-such-rule'");
+    }
+}
@@ -0,0 +1,190 @@
+use crate::ast::Schema;
+use crate::diagnostic::{Diagnostic, Label, Position};
+use crate::parser::parse;
+use chumsky::span::SimpleSpan;
+
+/// A source file taking part in a merged schema
+#[derive(Debug, Clone)]
+pub struct File {
+    pub filename: String,
+    pub src: String,
+    /// Where the file starts in the joined source
+    start: usize,
+}
+
+/// Several source files joined into one, so that tables may reference each other across
+/// them. Spans of the merged schema point into the joined source, [`Merged::split`] gives
+/// diagnostics back to the files they are in.
+#[derive(Debug, Clone)]
+pub struct Merged {
+    pub files: Vec<File>,
+    src: String,
+}
+
+impl Merged {
+    pub fn new(files: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut src = String::new();
+        let files = files
+            .into_iter()
+            .map(|(filename, file_src)| {
+                if !src.is_empty() {
+                    src.push('\n');
+                }
+                let start = src.len();
+                src.push_str(&file_src);
+                File {
+                    filename,
+                    src: file_src,
+                    start,
+                }
+            })
+            .collect();
+        Merged { files, src }
+    }
+
+    /// Parses and checks the files as one schema, the schema comes with its warnings. Each
+    /// file is parsed on its own first, so that the parse errors of all of them are found.
+    pub fn check(&self, name: &str) -> Result<(Schema, Vec<Diagnostic>), Vec<Diagnostic>> {
+        let errs: Vec<Diagnostic> = self
+            .files
+            .iter()
+            .filter_map(|file| {
+                parse(&file.filename, &file.src)
+                    .err()
+                    .map(|errs| (file, errs))
+            })
+            .flat_map(|(file, errs)| {
+                errs.into_iter()
+                    .map(|err| shift(err, |span| offset(span, file.start as isize)))
+            })
+            .collect();
+        if !errs.is_empty() {
+            return Err(errs);
+        }
+
+        let mut schema = parse(name, &self.src)?;
+        schema.check()?;
+        let warnings = schema.warnings();
+        Ok((schema, warnings))
+    }
+
+    /// Sorts diagnostics of the merged schema into the files they are in, with spans
+    /// pointing into each file. What a diagnostic relates to in another file becomes a note.
+    pub fn split(&self, diagnostics: &[Diagnostic]) -> Vec<(&File, Vec<Diagnostic>)> {
+        let mut split: Vec<(&File, Vec<Diagnostic>)> =
+            self.files.iter().map(|file| (file, Vec::new())).collect();
+
+        for diagnostic in diagnostics {
+            let index = self.file_at(diagnostic.primary.span.start);
+            let file = &self.files[index];
+
+            let mut secondary = Vec::new();
+            let mut notes = Vec::new();
+            for label in &diagnostic.secondary {
+                let other = &self.files[self.file_at(label.span.start)];
+                if other.start == file.start {
+                    secondary.push(label.clone());
+                } else {
+                    let position = Position::at(&other.src, label.span.start - other.start);
+                    notes.push(format!(
+                        "{} at {}:{}:{}",
+                        label.message, other.filename, position.line, position.column
+                    ));
+                }
+            }
+
+            let mut diagnostic = Diagnostic {
+                secondary,
+                ..diagnostic.clone()
+            };
+            diagnostic.notes.splice(0..0, notes);
+            split[index].1.push(shift(diagnostic, |span| {
+                offset(span, -(file.start as isize))
+            }));
+        }
+        split
+    }
+
+    /// The file a position of the joined source is in, the end of a file included
+    fn file_at(&self, position: usize) -> usize {
+        self.files
+            .iter()
+            .rposition(|file| file.start <= position)
+            .unwrap_or_default()
+    }
+}
+
+fn offset(span: SimpleSpan, by: isize) -> SimpleSpan {
+    let start = span.start.saturating_add_signed(by);
+    let end = span.end.saturating_add_signed(by);
+    SimpleSpan::from(start..end)
+}
+
+fn shift(diagnostic: Diagnostic, by: impl Fn(SimpleSpan) -> SimpleSpan) -> Diagnostic {
+    let label = |label: Label| Label {
+        span: by(label.span),
+        message: label.message,
+    };
+    Diagnostic {
+        primary: label(diagnostic.primary),
+        secondary: diagnostic.secondary.into_iter().map(label).collect(),
+        ..diagnostic
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Merged;
+    use crate::ddl::{self, Dialect};
+
+    fn merged(files: &[(&str, &str)]) -> Merged {
+        Merged::new(
+            files
+                .iter()
+                .map(|(filename, src)| (filename.to_string(), src.to_string())),
+        )
+    }
+
+    #[test]
+    fn test_references_across_files() {
+        let merged = merged(&[
+            ("users.mecha", "table users { id: uuid primary }"),
+            (
+                "posts.mecha",
+                "table posts { author_id: uuid (ref => users.id) }",
+            ),
+        ]);
+        let (schema, _) = merged.check("schema").unwrap();
+        let ddl = ddl::generate(&schema, Dialect::Postgres).unwrap();
+        assert!(ddl.contains(r#"CREATE TABLE "users""#));
+        assert!(ddl.contains(r#"CREATE TABLE "posts""#));
+    }
+
+    #[test]
+    fn test_split() {
+        let merged = merged(&[
+            ("a.mecha", "table users { id: uuid }"),
+            ("b.mecha", "table users { name: text }"),
+        ]);
+        let errs = merged.check("schema").unwrap_err();
+        let split = merged.split(&errs);
+
+        assert!(split[0].1.is_empty());
+        let (file, errs) = &split[1];
+        assert_eq!(file.filename, "b.mecha");
+        assert_eq!(errs[0].message, "table users is redeclared");
+        assert_eq!(errs[0].primary.span.start, 6);
+        assert!(errs[0].secondary.is_empty());
+        assert_eq!(errs[0].notes[0], "it's first declared here at a.mecha:1:7");
+    }
+
+    #[test]
+    fn test_parse_errors_of_every_file() {
+        let merged = merged(&[("a.mecha", "table {"), ("b.mecha", "table b {")]);
+        let errs = merged.check("schema").unwrap_err();
+        let split = merged.split(&errs);
+        assert!(!split[0].1.is_empty());
+        assert!(!split[1].1.is_empty());
+        assert!(split[1].1.iter().all(|err| err.primary.span.end <= 9));
+    }
+}
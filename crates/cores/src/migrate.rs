@@ -0,0 +1,985 @@
+use crate::ast::{ColumnAttribute, ColumnDef, Schema, TableDef};
+use crate::ddl::{
+    CheckConstraint, Dialect, ForeignKey, GenerateResult, check_constraint, check_constraints,
+    collect_foreign_keys, column_definition, column_type, comment_on_column, create_index,
+    create_table, foreign_key_constraint, generated_column, string_literal,
+};
+use crate::diff::{Change, diff};
+use std::collections::{HashMap, HashSet};
+
+/// Generates the statements migrating a database from the `from` schema to the `to` one,
+/// both having gone through [`Schema::check`]. The down migration is the same call with
+/// the two schemas swapped.
+///
+/// Statements are ordered so every one of them applies to the state left by the previous
+/// ones: foreign keys, indexes and constraints are dropped before the tables and columns
+/// they depend on, tables are created parents first following the reference graph, and
+/// foreign keys are added last. SQLite can't alter columns or constraints in place, so
+/// its tables are rebuilt instead.
+pub fn generate(from: &Schema, to: &Schema, dialect: Dialect) -> GenerateResult<String> {
+    let changes = diff(from, to).changes;
+
+    let from_tables = concrete_tables(from);
+    let to_tables = concrete_tables(to);
+    let from_by_name: HashMap<&str, &TableDef> = from_tables
+        .iter()
+        .map(|t| (t.id.name.as_str(), *t))
+        .collect();
+
+    // new name -> old name, statements before the renames use the old names
+    let mut renamed: HashMap<&str, &str> = HashMap::new();
+    let mut renamed_columns: HashMap<(&str, &str), &str> = HashMap::new();
+    let mut dropped: Vec<&TableDef> = Vec::new();
+    let mut created: Vec<&str> = Vec::new();
+    for change in &changes {
+        match change {
+            Change::TableRenamed { from, to } => {
+                renamed.insert(to.as_str(), from.as_str());
+            }
+            Change::ColumnRenamed { table, from, to } => {
+                renamed_columns.insert((table.as_str(), to.as_str()), from.as_str());
+            }
+            Change::TableRemoved { table } => dropped.push(from_by_name[table.id.name.as_str()]),
+            Change::TableAdded { table } => created.push(table.id.name.as_str()),
+            _ => {}
+        }
+    }
+
+    let from_foreign_keys = collect_foreign_keys(&from_tables);
+    let to_foreign_keys = collect_foreign_keys(&to_tables);
+    let removed_foreign_keys =
+        missing_foreign_keys(&from_tables, &from_foreign_keys, &to_foreign_keys);
+    let added_foreign_keys = missing_foreign_keys(&to_tables, &to_foreign_keys, &from_foreign_keys);
+
+    let rebuilt = if dialect == Dialect::Sqlite {
+        rebuilt_tables(
+            &changes,
+            &removed_foreign_keys,
+            &added_foreign_keys,
+            &renamed,
+        )
+    } else {
+        HashSet::new()
+    };
+
+    let mut errs = Vec::new();
+    let mut statements = Vec::new();
+
+    if !dialect.inlines_foreign_keys() {
+        for fk in &removed_foreign_keys {
+            if dropped.iter().any(|t| t.id.name == fk.table) {
+                continue;
+            }
+            let drop = match dialect {
+                Dialect::Mysql => "DROP FOREIGN KEY",
+                _ => "DROP CONSTRAINT",
+            };
+            statements.push(format!(
+                "ALTER TABLE {} {drop} {};",
+                dialect.quote(fk.table),
+                dialect.quote(&fk.name)
+            ));
+        }
+    }
+
+    for change in &changes {
+        let Change::IndexRemoved { table, name, .. } = change else {
+            continue;
+        };
+        if rebuilt.contains(table) {
+            continue;
+        }
+        statements.push(match dialect {
+            Dialect::Mysql => format!(
+                "DROP INDEX {} ON {};",
+                dialect.quote(name),
+                dialect.quote(old_name(&renamed, table))
+            ),
+            _ => format!("DROP INDEX {};", dialect.quote(name)),
+        });
+    }
+
+    // every change below is part of a rebuild in sqlite
+    if dialect != Dialect::Sqlite {
+        for new_table in &to_tables {
+            let Some(old_table) = from_by_name.get(old_name(&renamed, &new_table.id.name)) else {
+                continue;
+            };
+            let table = old_table.id.name.as_str();
+            let columns = column_changes(&changes, &new_table.id.name);
+
+            for constraint in check_constraints(old_table) {
+                let dropped_with_column = constraint
+                    .column
+                    .is_some_and(|column| columns.removed.contains(column));
+                if dropped_with_column || has_check(new_table, &constraint) {
+                    continue;
+                }
+                let drop = match dialect {
+                    Dialect::Mysql => "DROP CHECK",
+                    _ => "DROP CONSTRAINT",
+                };
+                statements.push(format!(
+                    "ALTER TABLE {} {drop} {};",
+                    dialect.quote(table),
+                    dialect.quote(&constraint.name)
+                ));
+            }
+
+            for change in &changes {
+                let Change::ColumnAttributeChanged {
+                    table: changed,
+                    column,
+                    from: Some(attribute),
+                    ..
+                } = change
+                else {
+                    continue;
+                };
+                if *changed != new_table.id.name {
+                    continue;
+                }
+                let column = old_column(&renamed_columns, changed, column);
+                let drop = match (attribute, dialect) {
+                    (ColumnAttribute::Primary, Dialect::Mysql) => "DROP PRIMARY KEY".to_string(),
+                    (ColumnAttribute::Unique, Dialect::Mysql) => {
+                        format!("DROP INDEX {}", dialect.quote(column))
+                    }
+                    (attribute, _) => format!(
+                        "DROP CONSTRAINT {}",
+                        dialect.quote(&attribute_constraint_name(table, column, *attribute))
+                    ),
+                };
+                statements.push(format!("ALTER TABLE {} {drop};", dialect.quote(table)));
+            }
+        }
+    }
+
+    for table in dependency_order(dropped, &from_foreign_keys).iter().rev() {
+        statements.push(format!("DROP TABLE {};", dialect.quote(&table.id.name)));
+    }
+
+    for new_table in &to_tables {
+        let new_name = new_table.id.name.as_str();
+        if let Some(old) = renamed.get(new_name) {
+            statements.push(format!(
+                "ALTER TABLE {} RENAME TO {};",
+                dialect.quote(old),
+                dialect.quote(new_name)
+            ));
+        }
+    }
+
+    for change in &changes {
+        if let Change::ColumnRenamed { table, from, to } = change {
+            statements.push(format!(
+                "ALTER TABLE {} RENAME COLUMN {} TO {};",
+                dialect.quote(table),
+                dialect.quote(from),
+                dialect.quote(to)
+            ));
+        }
+    }
+
+    // postgres names primary and unique constraints after their table and column, mysql
+    // names unique indexes after their column, and neither follows renames
+    for new_table in &to_tables {
+        let name = new_table.id.name.as_str();
+        let Some(old_table) = from_by_name.get(old_name(&renamed, name)) else {
+            continue;
+        };
+
+        for column in &new_table.columns {
+            let Some(attribute) = column.attribute else {
+                continue;
+            };
+            let column_name = column.id.name.as_str();
+            let previous = old_column(&renamed_columns, name, column_name);
+            let unchanged = old_table
+                .columns
+                .iter()
+                .any(|c| c.id.name == previous && c.attribute == Some(attribute));
+            if !unchanged {
+                continue;
+            }
+
+            match (dialect, attribute) {
+                (Dialect::Postgres, _) => {
+                    let from = attribute_constraint_name(&old_table.id.name, previous, attribute);
+                    let to = attribute_constraint_name(name, column_name, attribute);
+                    if from != to {
+                        statements.push(format!(
+                            "ALTER TABLE {} RENAME CONSTRAINT {} TO {};",
+                            dialect.quote(name),
+                            dialect.quote(&from),
+                            dialect.quote(&to)
+                        ));
+                    }
+                }
+                (Dialect::Mysql, ColumnAttribute::Unique) if previous != column_name => {
+                    statements.push(format!(
+                        "ALTER TABLE {} RENAME INDEX {} TO {};",
+                        dialect.quote(name),
+                        dialect.quote(previous),
+                        dialect.quote(column_name)
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let created_tables: Vec<&TableDef> = to_tables
+        .iter()
+        .filter(|t| created.contains(&t.id.name.as_str()))
+        .copied()
+        .collect();
+    let created_tables = dependency_order(created_tables, &to_foreign_keys);
+
+    for table in &created_tables {
+        let inlined_foreign_keys = if dialect.inlines_foreign_keys() {
+            to_foreign_keys.get(table.id.name.as_str())
+        } else {
+            None
+        };
+        statements.push(create_table(table, inlined_foreign_keys, dialect));
+
+        if dialect == Dialect::Postgres {
+            if let Some(doc) = &table.doc {
+                statements.push(format!(
+                    "COMMENT ON TABLE {} IS {};",
+                    dialect.quote(&table.id.name),
+                    string_literal(doc, dialect)
+                ));
+            }
+            statements.extend(
+                table
+                    .columns
+                    .iter()
+                    .filter_map(|column| comment_on_column(table, column, dialect)),
+            );
+        }
+    }
+
+    for new_table in &to_tables {
+        let name = new_table.id.name.as_str();
+        let Some(old_table) = from_by_name.get(old_name(&renamed, name)) else {
+            continue;
+        };
+
+        if rebuilt.contains(name) {
+            let foreign_keys = to_foreign_keys.get(name);
+            match rebuild_table(
+                old_table,
+                new_table,
+                &renamed_columns,
+                foreign_keys,
+                dialect,
+            ) {
+                Ok(rebuild) => statements.extend(rebuild),
+                Err(rebuild_errs) => errs.extend(rebuild_errs),
+            }
+            continue;
+        }
+
+        alter_table(new_table, &changes, dialect, &mut statements);
+
+        if dialect == Dialect::Sqlite {
+            continue;
+        }
+        let columns = column_changes(&changes, name);
+        for constraint in check_constraints(new_table) {
+            let added_with_column = constraint
+                .column
+                .is_some_and(|column| columns.added.contains(column));
+            if added_with_column || has_check(old_table, &constraint) {
+                continue;
+            }
+            statements.push(format!(
+                "ALTER TABLE {} ADD {};",
+                dialect.quote(name),
+                check_constraint(&constraint, dialect)
+            ));
+        }
+    }
+
+    for table in &created_tables {
+        for index in table.indexes.iter().flatten() {
+            match create_index(table, index, dialect) {
+                Ok(statement) => statements.push(statement),
+                Err(index_errs) => errs.extend(index_errs),
+            }
+        }
+    }
+
+    for change in &changes {
+        let Change::IndexAdded { table, index, .. } = change else {
+            continue;
+        };
+        if rebuilt.contains(table) {
+            continue;
+        }
+        let table = to_tables
+            .iter()
+            .find(|t| t.id.name == *table)
+            .expect("added indexes belong to a table of the new schema");
+        match create_index(table, index, dialect) {
+            Ok(statement) => statements.push(statement),
+            Err(index_errs) => errs.extend(index_errs),
+        }
+    }
+
+    if !dialect.inlines_foreign_keys() {
+        for fk in &added_foreign_keys {
+            statements.push(format!(
+                "ALTER TABLE {} ADD {};",
+                dialect.quote(fk.table),
+                foreign_key_constraint(fk, dialect)
+            ));
+        }
+    }
+
+    if !errs.is_empty() {
+        return Err(errs);
+    }
+
+    if statements.is_empty() {
+        return Ok(String::new());
+    }
+
+    // sqlite would otherwise enforce foreign keys against the tables being rebuilt
+    if !rebuilt.is_empty() {
+        statements.insert(0, "PRAGMA foreign_keys = OFF;".to_string());
+        statements.push("PRAGMA foreign_keys = ON;".to_string());
+    }
+
+    Ok(statements.join("\n\n") + "\n")
+}
+
+fn old_name<'n>(renamed: &HashMap<&'n str, &'n str>, table: &'n str) -> &'n str {
+    renamed.get(table).copied().unwrap_or(table)
+}
+
+fn old_column<'n>(
+    renamed_columns: &HashMap<(&'n str, &'n str), &'n str>,
+    table: &'n str,
+    column: &'n str,
+) -> &'n str {
+    renamed_columns
+        .get(&(table, column))
+        .copied()
+        .unwrap_or(column)
+}
+
+fn concrete_tables(schema: &Schema) -> Vec<&TableDef> {
+    schema.tables.iter().filter(|t| !t.is_abstract).collect()
+}
+
+/// Foreign keys of `tables` that have no identical counterpart in `others`, in table order
+fn missing_foreign_keys<'m, 's>(
+    tables: &[&TableDef],
+    foreign_keys: &'m HashMap<&str, Vec<ForeignKey<'s>>>,
+    others: &HashMap<&str, Vec<ForeignKey>>,
+) -> Vec<&'m ForeignKey<'s>> {
+    let same = |a: &ForeignKey, b: &ForeignKey| {
+        a.name == b.name
+            && a.table == b.table
+            && a.column == b.column
+            && a.referenced_table == b.referenced_table
+            && a.referenced_column == b.referenced_column
+    };
+
+    tables
+        .iter()
+        .flat_map(|table| {
+            foreign_keys
+                .get(table.id.name.as_str())
+                .into_iter()
+                .flatten()
+        })
+        .filter(|fk| {
+            !others
+                .get(fk.table)
+                .into_iter()
+                .flatten()
+                .any(|other| same(fk, other))
+        })
+        .collect()
+}
+
+/// Orders tables so the ones referenced by a foreign key come before the tables holding it,
+/// tables caught in a reference cycle keep their declaration order
+fn dependency_order<'s>(
+    mut remaining: Vec<&'s TableDef>,
+    foreign_keys: &HashMap<&str, Vec<ForeignKey>>,
+) -> Vec<&'s TableDef> {
+    let mut ordered = Vec::new();
+
+    while !remaining.is_empty() {
+        let ready = remaining.iter().position(|table| {
+            foreign_keys
+                .get(table.id.name.as_str())
+                .into_iter()
+                .flatten()
+                .all(|fk| {
+                    fk.referenced_table == fk.table
+                        || !remaining.iter().any(|t| t.id.name == fk.referenced_table)
+                })
+        });
+        ordered.push(remaining.remove(ready.unwrap_or(0)));
+    }
+
+    ordered
+}
+
+/// Tables sqlite can't migrate with `ALTER TABLE`, named as in the new schema
+fn rebuilt_tables(
+    changes: &[Change],
+    removed_foreign_keys: &[&ForeignKey],
+    added_foreign_keys: &[&ForeignKey],
+    renamed: &HashMap<&str, &str>,
+) -> HashSet<String> {
+    let mut rebuilt = HashSet::new();
+    let mut dropped = HashSet::new();
+    let mut created = HashSet::new();
+
+    for change in changes {
+        match change {
+            Change::TableRemoved { table } => {
+                dropped.insert(table.id.name.as_str());
+            }
+            Change::TableAdded { table } => {
+                created.insert(table.id.name.as_str());
+            }
+            Change::ColumnAdded { table, column } if !adds_in_place(column) => {
+                rebuilt.insert(table.clone());
+            }
+            Change::ColumnRemoved { table, .. }
+            | Change::ColumnTypeChanged { table, .. }
+            | Change::ColumnAttributeChanged { table, .. }
+            | Change::ColumnGeneratedChanged { table, .. }
+            | Change::ColumnCheckChanged { table, .. }
+            | Change::CheckAdded { table, .. }
+            | Change::CheckRemoved { table, .. } => {
+                rebuilt.insert(table.clone());
+            }
+            _ => {}
+        }
+    }
+
+    // foreign keys are part of the table definition in sqlite
+    for fk in removed_foreign_keys {
+        if dropped.contains(fk.table) {
+            continue;
+        }
+        let new_name = renamed
+            .iter()
+            .find(|(_, old)| **old == fk.table)
+            .map_or(fk.table, |(new, _)| *new);
+        rebuilt.insert(new_name.to_string());
+    }
+    for fk in added_foreign_keys {
+        if !created.contains(fk.table) {
+            rebuilt.insert(fk.table.to_string());
+        }
+    }
+
+    rebuilt
+}
+
+/// Whether sqlite can add the column with a plain `ALTER TABLE ... ADD COLUMN`
+fn adds_in_place(column: &ColumnDef) -> bool {
+    column.attribute.is_none() && column.generated.is_none()
+}
+
+/// Columns a table gains or loses, a regenerated column is both dropped and added back
+struct ColumnChanges<'c> {
+    added: HashSet<&'c str>,
+    removed: HashSet<&'c str>,
+}
+
+fn column_changes<'c>(changes: &'c [Change], table: &str) -> ColumnChanges<'c> {
+    let mut columns = ColumnChanges {
+        added: HashSet::new(),
+        removed: HashSet::new(),
+    };
+
+    for change in changes {
+        match change {
+            Change::ColumnAdded { table: t, column } if t == table => {
+                columns.added.insert(column.id.name.as_str());
+            }
+            Change::ColumnRemoved { table: t, column } if t == table => {
+                columns.removed.insert(column.id.name.as_str());
+            }
+            Change::ColumnGeneratedChanged {
+                table: t,
+                column,
+                to: Some(_),
+                ..
+            } if t == table => {
+                columns.added.insert(column.id.name.as_str());
+                columns.removed.insert(column.id.name.as_str());
+            }
+            _ => {}
+        }
+    }
+
+    columns
+}
+
+fn has_check(table: &TableDef, constraint: &CheckConstraint) -> bool {
+    check_constraints(table).iter().any(|other| {
+        other.name == constraint.name && other.check.to_string() == constraint.check.to_string()
+    })
+}
+
+/// The names postgres gives to inline primary key and unique constraints
+fn attribute_constraint_name(table: &str, column: &str, attribute: ColumnAttribute) -> String {
+    match attribute {
+        ColumnAttribute::Primary => format!("{table}_pkey"),
+        ColumnAttribute::Unique => format!("{table}_{column}_key"),
+    }
+}
+
+/// Column statements of a table that already exists under its new name
+fn alter_table(
+    table: &TableDef,
+    changes: &[Change],
+    dialect: Dialect,
+    statements: &mut Vec<String>,
+) {
+    let name = table.id.name.as_str();
+    let quoted = dialect.quote(name);
+    let column_named = |column: &str| {
+        table
+            .columns
+            .iter()
+            .find(|c| c.id.name == column)
+            .expect("changed columns belong to the new table")
+    };
+
+    for change in changes {
+        match change {
+            Change::ColumnRemoved { table: t, column } if t == name => {
+                statements.push(format!(
+                    "ALTER TABLE {quoted} DROP COLUMN {};",
+                    dialect.quote(&column.id.name)
+                ));
+            }
+            Change::ColumnAdded { table: t, column } if t == name => {
+                statements.push(format!(
+                    "ALTER TABLE {quoted} ADD COLUMN {};",
+                    column_definition(table, column, dialect)
+                ));
+                if dialect == Dialect::Postgres {
+                    statements.extend(comment_on_column(table, column, dialect));
+                }
+            }
+            Change::ColumnTypeChanged {
+                table: t, column, ..
+            } if t == name => {
+                let column = column_named(column);
+                statements.push(match dialect {
+                    Dialect::Mysql => format!(
+                        "ALTER TABLE {quoted} MODIFY COLUMN {};",
+                        modified_column(column, dialect)
+                    ),
+                    _ => {
+                        let typ = column_type(column, dialect);
+                        format!(
+                            "ALTER TABLE {quoted} ALTER COLUMN {0} TYPE {typ} USING {0}::{typ};",
+                            dialect.quote(&column.id.name)
+                        )
+                    }
+                });
+            }
+            Change::ColumnAttributeChanged {
+                table: t,
+                column,
+                to: Some(attribute),
+                ..
+            } if t == name => {
+                let add = match attribute {
+                    ColumnAttribute::Primary => "PRIMARY KEY",
+                    ColumnAttribute::Unique => "UNIQUE",
+                };
+                statements.push(format!(
+                    "ALTER TABLE {quoted} ADD {add} ({});",
+                    dialect.quote(column)
+                ));
+            }
+            Change::ColumnGeneratedChanged {
+                table: t,
+                column,
+                to: None,
+                ..
+            } if t == name => {
+                // the computed values are kept as the column becomes a regular one
+                statements.push(match dialect {
+                    Dialect::Mysql => format!(
+                        "ALTER TABLE {quoted} MODIFY COLUMN {};",
+                        modified_column(column, dialect)
+                    ),
+                    _ => format!(
+                        "ALTER TABLE {quoted} ALTER COLUMN {} DROP EXPRESSION;",
+                        dialect.quote(&column.id.name)
+                    ),
+                });
+            }
+            Change::ColumnGeneratedChanged {
+                table: t, column, ..
+            } if t == name => {
+                statements.push(format!(
+                    "ALTER TABLE {quoted} DROP COLUMN {};",
+                    dialect.quote(&column.id.name)
+                ));
+                statements.push(format!(
+                    "ALTER TABLE {quoted} ADD COLUMN {};",
+                    column_definition(table, column, dialect)
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A column definition for mysql's `MODIFY COLUMN`, which leaves keys and checks alone
+fn modified_column(column: &ColumnDef, dialect: Dialect) -> String {
+    let mut definition = format!(
+        "{} {}",
+        dialect.quote(&column.id.name),
+        column_type(column, dialect)
+    );
+    if let Some(generated) = &column.generated {
+        definition.push_str(&generated_column(generated, dialect));
+    }
+    if let Some(doc) = &column.doc {
+        definition.push_str(&format!(" COMMENT {}", string_literal(doc, dialect)));
+    }
+    definition
+}
+
+/// The sqlite way of altering a table: create the new version aside, copy the rows over,
+/// then swap the two tables. Renamed columns already carry their new name at this point.
+fn rebuild_table(
+    old: &TableDef,
+    new: &TableDef,
+    renamed_columns: &HashMap<(&str, &str), &str>,
+    foreign_keys: Option<&Vec<ForeignKey>>,
+    dialect: Dialect,
+) -> GenerateResult<Vec<String>> {
+    let name = dialect.quote(&new.id.name);
+    let temporary = dialect.quote(&format!("_mecha_new_{}", new.id.name));
+
+    let mut statements = vec![create_table(new, foreign_keys, dialect).replacen(
+        &format!("CREATE TABLE {name} ("),
+        &format!("CREATE TABLE {temporary} ("),
+        1,
+    )];
+
+    let copied: Vec<String> = new
+        .columns
+        .iter()
+        .filter(|column| column.generated.is_none())
+        .filter(|column| {
+            let previous = old_column(renamed_columns, &new.id.name, &column.id.name);
+            old.columns
+                .iter()
+                .any(|c| c.id.name == previous && c.generated.is_none())
+        })
+        .map(|column| dialect.quote(&column.id.name))
+        .collect();
+    if !copied.is_empty() {
+        let copied = copied.join(", ");
+        statements.push(format!(
+            "INSERT INTO {temporary} ({copied}) SELECT {copied} FROM {name};"
+        ));
+    }
+
+    statements.push(format!("DROP TABLE {name};"));
+    statements.push(format!("ALTER TABLE {temporary} RENAME TO {name};"));
+
+    let mut errs = Vec::new();
+    for index in new.indexes.iter().flatten() {
+        match create_index(new, index, dialect) {
+            Ok(statement) => statements.push(statement),
+            Err(index_errs) => errs.extend(index_errs),
+        }
+    }
+
+    if !errs.is_empty() {
+        return Err(errs);
+    }
+
+    Ok(statements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate;
+    use crate::codegen;
+    use crate::ddl::Dialect;
+    use crate::parser::parse;
+
+    fn assert_migration(old: &str, new: &str, dialect: Dialect) -> (String, String) {
+        let old_schema = &mut parse("old.mecha", old).unwrap();
+        let new_schema = &mut parse("new.mecha", new).unwrap();
+        assert!(old_schema.check().is_ok());
+        assert!(new_schema.check().is_ok());
+
+        let up = generate(old_schema, new_schema, dialect).unwrap_or_else(|errs| {
+            codegen::report(new, "new.mecha", &errs);
+            panic!("up migration failed unexpectedly");
+        });
+        let down = generate(new_schema, old_schema, dialect).unwrap_or_else(|errs| {
+            codegen::report(old, "old.mecha", &errs);
+            panic!("down migration failed unexpectedly");
+        });
+        (up, down)
+    }
+
+    fn position(migration: &str, statement: &str) -> usize {
+        migration
+            .find(statement)
+            .unwrap_or_else(|| panic!("missing `{statement}` in:\n{migration}"))
+    }
+
+    #[test]
+    fn test_no_changes() {
+        let src = r"
+            table users {
+                id: uuid primary
+            }
+        ";
+        let (up, down) = assert_migration(src, src, Dialect::Postgres);
+        assert!(up.is_empty());
+        assert!(down.is_empty());
+    }
+
+    #[test]
+    fn test_tables_follow_the_reference_graph() {
+        let old = r"
+            table accounts {
+                id: uuid primary
+            }
+        ";
+        let new = r"
+            table accounts {
+                id: uuid primary
+            }
+
+            table posts {
+                id: uuid primary,
+                user_id: uuid
+            }
+
+            table users {
+                id: uuid primary (ref => posts.user_id)
+            }
+        ";
+        let (up, down) = assert_migration(old, new, Dialect::Postgres);
+        let fk = r#"ALTER TABLE "posts" ADD CONSTRAINT "fk_posts_user_id_users" FOREIGN KEY ("user_id") REFERENCES "users" ("id");"#;
+        assert!(
+            position(&up, r#"CREATE TABLE "users""#) < position(&up, r#"CREATE TABLE "posts""#)
+        );
+        assert!(position(&up, r#"CREATE TABLE "posts""#) < position(&up, fk));
+
+        // the table holding the foreign key goes first
+        assert_eq!(down, "DROP TABLE \"posts\";\n\nDROP TABLE \"users\";\n");
+    }
+
+    #[test]
+    fn test_column_changes() {
+        let old = r"
+            table users {
+                id: uuid primary,
+                nickname: text,
+                age: int
+            }
+        ";
+        let new = r"
+            table users {
+                id: uuid primary,
+                age: number check (age >= 0),
+                email: text unique
+            }
+        ";
+        let (up, down) = assert_migration(old, new, Dialect::Postgres);
+        assert_eq!(
+            up,
+            "ALTER TABLE \"users\" DROP COLUMN \"nickname\";\n\n\
+             ALTER TABLE \"users\" ALTER COLUMN \"age\" TYPE numeric USING \"age\"::numeric;\n\n\
+             ALTER TABLE \"users\" ADD COLUMN \"email\" text UNIQUE;\n\n\
+             ALTER TABLE \"users\" ADD CONSTRAINT \"chk_users_age\" CHECK (\"age\" >= 0);\n"
+        );
+        assert_eq!(
+            down,
+            "ALTER TABLE \"users\" DROP CONSTRAINT \"chk_users_age\";\n\n\
+             ALTER TABLE \"users\" DROP COLUMN \"email\";\n\n\
+             ALTER TABLE \"users\" ADD COLUMN \"nickname\" text;\n\n\
+             ALTER TABLE \"users\" ALTER COLUMN \"age\" TYPE int USING \"age\"::int;\n"
+        );
+
+        let (up, _) = assert_migration(old, new, Dialect::Mysql);
+        assert!(up.contains("ALTER TABLE `users` MODIFY COLUMN `age` decimal;"));
+    }
+
+    #[test]
+    fn test_index_and_foreign_key_changes() {
+        let old = r"
+            table users {
+                id: uuid primary (ref => posts.user_id),
+                email: text
+                indexes {
+                    email
+                }
+            }
+
+            table posts {
+                user_id: uuid
+            }
+        ";
+        let new = r"
+            table users {
+                id: uuid primary,
+                email: text
+                indexes {
+                    unique email
+                }
+            }
+
+            table posts {
+                user_id: uuid
+            }
+        ";
+        let (up, down) = assert_migration(old, new, Dialect::Mysql);
+        assert_eq!(
+            up,
+            "ALTER TABLE `posts` DROP FOREIGN KEY `fk_posts_user_id_users`;\n\n\
+             DROP INDEX `idx_users_email` ON `users`;\n\n\
+             CREATE UNIQUE INDEX `idx_users_email` ON `users` (`email`);\n"
+        );
+        assert!(down.ends_with(
+            "ALTER TABLE `posts` ADD CONSTRAINT `fk_posts_user_id_users` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`);\n"
+        ));
+    }
+
+    #[test]
+    fn test_sqlite_rebuilds_tables() {
+        let old = r"
+            table users {
+                id: uuid primary,
+                age: int
+                indexes {
+                    age
+                }
+            }
+        ";
+        let new = r"
+            table users {
+                id: uuid primary,
+                age: int check (age >= 0),
+                nickname: text
+                indexes {
+                    age
+                }
+            }
+        ";
+        let (up, _) = assert_migration(old, new, Dialect::Sqlite);
+        assert_eq!(
+            up,
+            "PRAGMA foreign_keys = OFF;\n\n\
+             CREATE TABLE \"_mecha_new_users\" (\n    \
+                 \"id\" text PRIMARY KEY,\n    \
+                 \"age\" int CONSTRAINT \"chk_users_age\" CHECK (\"age\" >= 0),\n    \
+                 \"nickname\" text\n\
+             );\n\n\
+             INSERT INTO \"_mecha_new_users\" (\"id\", \"age\") SELECT \"id\", \"age\" FROM \"users\";\n\n\
+             DROP TABLE \"users\";\n\n\
+             ALTER TABLE \"_mecha_new_users\" RENAME TO \"users\";\n\n\
+             CREATE INDEX \"idx_users_age\" ON \"users\" (\"age\");\n\n\
+             PRAGMA foreign_keys = ON;\n"
+        );
+
+        // plain columns don't need a rebuild
+        let (up, down) = assert_migration(
+            old,
+            r"
+            table users {
+                id: uuid primary,
+                age: int,
+                nickname: text
+                indexes {
+                    age
+                }
+            }
+            ",
+            Dialect::Sqlite,
+        );
+        assert_eq!(up, "ALTER TABLE \"users\" ADD COLUMN \"nickname\" text;\n");
+        assert!(down.contains("DROP TABLE \"users\";"));
+    }
+
+    #[test]
+    fn test_postgres_table_rename_keeps_constraint_names() {
+        let old = r"
+            table people {
+                id: uuid primary,
+                name: text
+            }
+        ";
+        let new = r#"
+            @renamed_from("people")
+            table users {
+                id: uuid primary,
+                name: text
+            }
+        "#;
+        let (up, _) = assert_migration(old, new, Dialect::Postgres);
+        assert_eq!(
+            up,
+            "ALTER TABLE \"people\" RENAME TO \"users\";\n\n\
+             ALTER TABLE \"users\" RENAME CONSTRAINT \"people_pkey\" TO \"users_pkey\";\n"
+        );
+    }
+
+    #[test]
+    fn test_renamed_from() {
+        let old = r"
+            table people {
+                id: uuid primary,
+                mail: text unique,
+                age: int
+            }
+        ";
+        let new = r#"
+            @renamed_from("people")
+            table users {
+                id: uuid primary,
+                @renamed_from("mail")
+                email: text unique,
+                age: number
+            }
+        "#;
+        let (up, down) = assert_migration(old, new, Dialect::Postgres);
+        assert_eq!(
+            up,
+            "ALTER TABLE \"people\" RENAME TO \"users\";\n\n\
+             ALTER TABLE \"users\" RENAME COLUMN \"mail\" TO \"email\";\n\n\
+             ALTER TABLE \"users\" RENAME CONSTRAINT \"people_pkey\" TO \"users_pkey\";\n\n\
+             ALTER TABLE \"users\" RENAME CONSTRAINT \"people_mail_key\" TO \"users_email_key\";\n\n\
+             ALTER TABLE \"users\" ALTER COLUMN \"age\" TYPE numeric USING \"age\"::numeric;\n"
+        );
+        assert!(down.starts_with(
+            "ALTER TABLE \"users\" RENAME TO \"people\";\n\n\
+             ALTER TABLE \"people\" RENAME COLUMN \"email\" TO \"mail\";\n\n"
+        ));
+
+        // the rows of the renamed column are copied over when sqlite rebuilds the table
+        let (up, _) = assert_migration(old, new, Dialect::Sqlite);
+        assert!(up.contains(
+            "INSERT INTO \"_mecha_new_users\" (\"id\", \"email\", \"age\") SELECT \"id\", \"email\", \"age\" FROM \"users\";"
+        ));
+    }
+}
@@ -0,0 +1,368 @@
+use crate::ast::{ColumnDef, Expr, Ident, Literal, Schema, SemanticErr, TableDef};
+use crate::ddl;
+use crate::diagnostic::Diagnostic;
+use chumsky::span::SimpleSpan;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// How the names of a schema are written in what is generated from it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Convention {
+    /// Names as they are declared
+    #[default]
+    #[serde(rename = "preserve")]
+    Preserve,
+    #[serde(rename = "snake_case")]
+    SnakeCase,
+    #[serde(rename = "camelCase")]
+    CamelCase,
+    #[serde(rename = "PascalCase")]
+    PascalCase,
+}
+
+impl Convention {
+    /// How the convention is written in a project file
+    pub fn name(self) -> &'static str {
+        match self {
+            Convention::Preserve => "preserve",
+            Convention::SnakeCase => "snake_case",
+            Convention::CamelCase => "camelCase",
+            Convention::PascalCase => "PascalCase",
+        }
+    }
+}
+
+/// Writes a name in a convention. Words are split at `_`, `-` and where a lowercase letter
+/// or a digit is followed by an uppercase one, so `userID` is `user_id` but `HTTPCode` is
+/// a single word.
+pub fn convert(name: &str, convention: Convention) -> String {
+    if convention == Convention::Preserve {
+        return name.to_string();
+    }
+
+    let words = words(name);
+    match convention {
+        Convention::Preserve => unreachable!(),
+        Convention::SnakeCase => words.join("_"),
+        Convention::CamelCase => words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                if i == 0 {
+                    word.clone()
+                } else {
+                    capitalize(word)
+                }
+            })
+            .collect(),
+        Convention::PascalCase => words.iter().map(|word| capitalize(word)).collect(),
+    }
+}
+
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous: Option<char> = None;
+
+    for c in name.chars() {
+        let boundary = c == '_'
+            || c == '-'
+            || (c.is_uppercase()
+                && previous.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit()));
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c != '_' && c != '-' {
+            word.extend(c.to_lowercase());
+        }
+        previous = Some(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Renames every table, column and index of a schema, along with the places naming them.
+/// Distinct names can be written the same in a convention, such as `userId` and `user_id`
+/// in snake_case, which is an error rather than one of them silently taking the other's place.
+pub fn apply(schema: &mut Schema, convention: Convention) -> Result<(), Vec<Diagnostic>> {
+    if convention == Convention::Preserve {
+        return Ok(());
+    }
+    let declared = schema.clone();
+    let rename = |id: &mut Ident| id.name = convert(&id.name, convention);
+
+    for table in &mut schema.tables {
+        rename(&mut table.id);
+        if let Some(parent) = &mut table.extended_by {
+            rename(parent);
+        }
+        rename_previous(&mut table.annotations, convention);
+
+        for column in &mut table.columns {
+            rename(&mut column.id);
+            rename_previous(&mut column.annotations, convention);
+            if let Some(reference) = &mut column.reference {
+                rename(&mut reference.table);
+                rename(&mut reference.column);
+            }
+            if let Some(check) = &mut column.check {
+                rename_columns(&mut check.expr, convention);
+            }
+            if let Some(generated) = &mut column.generated {
+                rename_columns(&mut generated.expr, convention);
+            }
+        }
+
+        for check in &mut table.checks {
+            rename_columns(&mut check.expr, convention);
+        }
+
+        for index in table.indexes.iter_mut().flatten() {
+            if let Some(name) = &mut index.name {
+                rename(name);
+            }
+            for column in &mut index.columns {
+                rename(&mut column.id);
+            }
+            for id in index.include.iter_mut().flatten() {
+                rename(id);
+            }
+            if let Some(predicate) = &mut index.predicate {
+                rename_columns(predicate, convention);
+            }
+        }
+    }
+
+    check_names(&declared, schema, convention)
+}
+
+/// Check that the names of a schema are still distinct once written in a convention, the
+/// declared schema is the renamed one before renaming so both have the same shape
+fn check_names(
+    declared: &Schema,
+    renamed: &Schema,
+    convention: Convention,
+) -> Result<(), Vec<Diagnostic>> {
+    let collision = |kind: SemanticErr,
+                     what: &str,
+                     name: &str,
+                     prev: &str,
+                     span: SimpleSpan,
+                     prev_span: SimpleSpan| {
+        vec![
+            kind.error(
+                span,
+                format!(
+                    "{what} {name} is named {} in {}, like {what} {prev}",
+                    convert(name, convention),
+                    convention.name()
+                ),
+            )
+            .with_label(prev_span, format!("{prev} is declared here"))
+            .with_help("rename one of them, so that they stay distinct"),
+        ]
+    };
+
+    let mut tables: HashMap<&str, (&str, SimpleSpan)> = HashMap::new();
+    for (table, declared_table) in renamed.tables.iter().zip(&declared.tables) {
+        let name = declared_table.id.name.as_str();
+        if let Some((prev, prev_span)) = tables.get(table.id.name.as_str()) {
+            return Err(collision(
+                SemanticErr::TableRedeclaration,
+                "table",
+                name,
+                prev,
+                table.id.span,
+                *prev_span,
+            ));
+        }
+        tables.insert(&table.id.name, (name, table.id.span));
+    }
+
+    for (table, declared_table) in renamed.tables.iter().zip(&declared.tables) {
+        let mut columns: HashMap<&str, (&str, SimpleSpan)> = HashMap::new();
+        let all_columns = extended_columns(renamed, table);
+        let declared_columns = extended_columns(declared, declared_table);
+        for (column, declared_column) in all_columns.into_iter().zip(declared_columns) {
+            let name = declared_column.id.name.as_str();
+            if let Some((prev, prev_span)) = columns.get(column.id.name.as_str()) {
+                return Err(collision(
+                    SemanticErr::ColumnRedeclaration,
+                    "column",
+                    name,
+                    prev,
+                    column.id.span,
+                    *prev_span,
+                ));
+            }
+            columns.insert(&column.id.name, (name, column.id.span));
+        }
+    }
+
+    let mut indexes: HashMap<String, (String, SimpleSpan)> = HashMap::new();
+    for (table, declared_table) in renamed.tables.iter().zip(&declared.tables) {
+        let table_indexes = table.indexes.iter().flatten();
+        for (index, declared_index) in table_indexes.zip(declared_table.indexes.iter().flatten()) {
+            let name = ddl::index_name(declared_table, declared_index);
+            let span = index.name.as_ref().map_or(index.span, |name| name.span);
+            let renamed_name = ddl::index_name(table, index);
+            if let Some((prev, prev_span)) = indexes.get(&renamed_name) {
+                return Err(collision(
+                    SemanticErr::IndexRedeclaration,
+                    "index",
+                    &name,
+                    prev,
+                    span,
+                    *prev_span,
+                ));
+            }
+            indexes.insert(renamed_name, (name, span));
+        }
+    }
+
+    Ok(())
+}
+
+/// The columns of a table followed by the ones of the tables it extends
+fn extended_columns<'s>(schema: &'s Schema, table: &'s TableDef) -> Vec<&'s ColumnDef> {
+    let mut columns: Vec<&ColumnDef> = table.columns.iter().collect();
+    let mut visited: HashSet<&str> = HashSet::from([table.id.name.as_str()]);
+    let mut current = table;
+
+    while let Some(parent) = &current.extended_by {
+        let Some(parent_table) = schema.tables.iter().find(|t| t.id.name == parent.name) else {
+            break;
+        };
+        if !visited.insert(&parent_table.id.name) {
+            break;
+        }
+        columns.extend(&parent_table.columns);
+        current = parent_table;
+    }
+    columns
+}
+
+/// The names given with `@renamed_from`, so that migrations still find them
+fn rename_previous(annotations: &mut [crate::ast::Annotation], convention: Convention) {
+    for annotation in annotations {
+        if annotation.name.name != "renamed_from" {
+            continue;
+        }
+        for arg in &mut annotation.args {
+            if let Literal::String(name) = &mut arg.value {
+                *name = convert(name, convention);
+            }
+        }
+    }
+}
+
+fn rename_columns(expr: &mut Expr, convention: Convention) {
+    match expr {
+        Expr::Column(id) => id.name = convert(&id.name, convention),
+        Expr::Literal(..) => {}
+        Expr::Unary { expr, .. } | Expr::IsNull { expr, .. } => rename_columns(expr, convention),
+        Expr::Binary { lhs, rhs, .. } => {
+            rename_columns(lhs, convention);
+            rename_columns(rhs, convention);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Convention, apply, convert};
+    use crate::ddl::{self, Dialect};
+    use crate::parser::parse;
+
+    #[test]
+    fn test_convert() {
+        assert_eq!(convert("userID", Convention::SnakeCase), "user_id");
+        assert_eq!(
+            convert("user-Accounts", Convention::SnakeCase),
+            "user_accounts"
+        );
+        assert_eq!(
+            convert("HTTPRequests", Convention::SnakeCase),
+            "httprequests"
+        );
+        assert_eq!(convert("created_at", Convention::CamelCase), "createdAt");
+        assert_eq!(
+            convert("user_vehicle", Convention::PascalCase),
+            "UserVehicle"
+        );
+        assert_eq!(
+            convert("user_vehicle", Convention::Preserve),
+            "user_vehicle"
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let src = r"
+            table user_accounts {
+                account_id: uuid primary (ref => login_events.account_id),
+                display_name: text check (display_name != '')
+                indexes {
+                    by_name (display_name)
+                }
+            }
+
+            table login_events {
+                account_id: uuid
+            }
+        ";
+        let schema = &mut parse("test.mecha", src).unwrap();
+        assert!(schema.check().is_ok());
+        apply(schema, Convention::CamelCase).unwrap();
+
+        let ddl = ddl::generate(schema, Dialect::Postgres).unwrap();
+        assert!(ddl.contains(r#"CREATE TABLE "userAccounts""#));
+        assert!(ddl.contains(r#"CHECK ("displayName" <> '')"#));
+        assert!(ddl.contains(r#"CREATE INDEX "byName" ON "userAccounts" ("displayName");"#));
+        assert!(ddl.contains(r#"REFERENCES "userAccounts" ("accountId")"#));
+    }
+
+    #[test]
+    fn test_apply_collisions() {
+        let codes = |src: &str| {
+            let schema = &mut parse("test.mecha", src).unwrap();
+            assert!(schema.check().is_ok());
+            apply(schema, Convention::SnakeCase)
+                .err()
+                .map(|errs| errs.iter().map(|err| err.code.unwrap()).collect::<Vec<_>>())
+        };
+
+        assert_eq!(
+            codes("table users { userId: uuid, user_id: uuid }"),
+            Some(vec!["M0006"])
+        );
+        assert_eq!(
+            codes("table userAccounts { id: uuid }\ntable user_accounts { id: uuid }"),
+            Some(vec!["M0002"])
+        );
+        assert_eq!(
+            codes(
+                "abstract table base { createdAt: timestamp }\n\
+                 table users extends base { created_at: timestamp }"
+            ),
+            Some(vec!["M0006"])
+        );
+        assert_eq!(
+            codes(
+                "table users { email: text, name: text indexes { byEmail (email), by_email (name) } }"
+            ),
+            Some(vec!["M0007"])
+        );
+        assert_eq!(codes("table users { userId: uuid, name: text }"), None);
+    }
+}
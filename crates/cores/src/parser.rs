@@ -1,12 +1,16 @@
 use crate::ast::{
-    ColumnAttribute, ColumnDef, Ident, Index, RefOperator, ReferenceDef, Schema, TableDef,
+    Annotation, AnnotationArg, BinaryOp, CheckDef, ColumnAttribute, ColumnDef, Expr, GeneratedDef,
+    GeneratedStorage, Ident, Index, IndexColumn, IndexMethod, IndexUsing, Literal, RefOperator,
+    ReferenceDef, Schema, SortOrder, TableDef, UnaryOp,
 };
-use crate::codegen::diagnose;
+#[cfg(test)]
+use crate::codegen::report;
+use crate::diagnostic::Diagnostic;
 use crate::lexer;
 use crate::lexer::Token;
 use chumsky::error::Rich;
 use chumsky::input::ValueInput;
-use chumsky::prelude::{SimpleSpan, end};
+use chumsky::prelude::{SimpleSpan, end, recursive};
 use chumsky::{IterParser, Parser, extra, select};
 
 fn schema_parser<'tokens, 'src: 'tokens, I>(
@@ -42,7 +46,9 @@ where
         .or_not()
         .labelled("'extends'");
 
-    abstract_flag
+    doc_parser()
+        .then(annotation_list_parser())
+        .then(abstract_flag)
         .then_ignore(select! { Token::Table => () })
         .then(ident_string())
         .then(extends_clause)
@@ -51,34 +57,181 @@ where
         .then(index_section_parser().or_not())
         .then_ignore(select! { Token::RightBrace => () })
         .map_with(
-            |((((is_abstract, ident), extends), columns), indexes), extra| TableDef {
-                id: ident,
-                is_abstract,
-                columns,
-                extended_by: extends,
+            |(
+                (((((doc, annotations), is_abstract), ident), extends), (columns, checks)),
                 indexes,
-                span: extra.span(),
+            ),
+             extra| {
+                TableDef {
+                    id: ident,
+                    doc,
+                    annotations,
+                    is_abstract,
+                    columns,
+                    checks,
+                    extended_by: extends,
+                    indexes,
+                    span: extra.span(),
+                }
             },
         )
 }
 
+/// Joins the `///` lines preceding a table or a column
+fn doc_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, Option<String>, extra::Err<Rich<'tokens, Token<'src>>>>
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    select! { Token::DocComment(line) => line }
+        .repeated()
+        .collect::<Vec<_>>()
+        .map(|lines| {
+            if lines.is_empty() {
+                return None;
+            }
+            let lines: Vec<&str> = lines
+                .iter()
+                .map(|line| line.strip_prefix(' ').unwrap_or(line).trim_end())
+                .collect();
+            Some(lines.join("\n"))
+        })
+}
+
+fn annotation_list_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, Vec<Annotation>, extra::Err<Rich<'tokens, Token<'src>>>>
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    let name = ident_string()
+        .separated_by(select! { Token::Dot => () }.labelled("'.'"))
+        .at_least(1)
+        .collect::<Vec<_>>()
+        .map_with(|parts, extra| Ident {
+            name: parts
+                .iter()
+                .map(|part| part.name.as_str())
+                .collect::<Vec<_>>()
+                .join("."),
+            span: extra.span(),
+        });
+
+    let value = select! {
+        Token::Number(value) => Literal::Number(value.to_string()),
+        Token::Str(value) => Literal::String(unescape(value)),
+        Token::True => Literal::Bool(true),
+        Token::False => Literal::Bool(false),
+    }
+    .labelled("annotation value");
+
+    let arg = ident_string()
+        .then_ignore(select! { Token::Eq => () })
+        .or_not()
+        .then(value)
+        .map_with(|(key, value), extra| AnnotationArg {
+            key,
+            value,
+            span: extra.span(),
+        });
+
+    let args = arg
+        .separated_by(select! { Token::Comma => () }.labelled("','"))
+        .collect::<Vec<_>>()
+        .delimited_by(
+            select! { Token::LeftParen => () }.labelled("'('"),
+            select! { Token::RightParen => () }.labelled("')'"),
+        );
+
+    select! { Token::At => () }
+        .labelled("'@'")
+        .ignore_then(name)
+        .then(args.or_not())
+        .map_with(|(name, args), extra| Annotation {
+            name,
+            args: args.unwrap_or_default(),
+            span: extra.span(),
+        })
+        .labelled("annotation")
+        .repeated()
+        .collect::<Vec<_>>()
+}
+
 fn column_definition_parser<'tokens, 'src: 'tokens, I>()
 -> impl Parser<'tokens, I, ColumnDef, extra::Err<Rich<'tokens, Token<'src>>>>
 where
     I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
 {
-    ident_string()
+    doc_parser()
+        .then(annotation_list_parser())
+        .then(ident_string())
         .then_ignore(select! { Token::Colon => () }.labelled("':'"))
         .then(ident_string())
         .then(column_attribute_parser().or_not())
+        .then(generated_parser().or_not())
+        .then(check_parser().or_not())
         .then(reference_parser().or_not())
-        .map_with(|(((id, typ), attr_opt), ref_opt), extra| ColumnDef {
-            id,
-            typ,
-            attribute: attr_opt,
-            reference: ref_opt,
+        .map_with(
+            |(
+                ((((((doc, annotations), id), typ), attr_opt), generated_opt), check_opt),
+                ref_opt,
+            ),
+             extra| {
+                ColumnDef {
+                    id,
+                    doc,
+                    annotations,
+                    typ,
+                    attribute: attr_opt,
+                    generated: generated_opt,
+                    check: check_opt,
+                    reference: ref_opt,
+                    span: extra.span(),
+                }
+            },
+        )
+}
+
+fn generated_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, GeneratedDef, extra::Err<Rich<'tokens, Token<'src>>>>
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    let storage = keyword("stored")
+        .to(GeneratedStorage::Stored)
+        .or(keyword("virtual").to(GeneratedStorage::Virtual))
+        .labelled("'stored' or 'virtual'");
+
+    keyword("generated")
+        .labelled("'generated'")
+        .ignore_then(expr_parser().delimited_by(
+            select! { Token::LeftParen => () }.labelled("'('"),
+            select! { Token::RightParen => () }.labelled("')'"),
+        ))
+        .then(storage.or_not())
+        .map_with(|(expr, storage), extra| GeneratedDef {
+            expr,
+            storage,
             span: extra.span(),
         })
+        .labelled("generated column")
+}
+
+fn check_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, CheckDef, extra::Err<Rich<'tokens, Token<'src>>>>
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    keyword("check")
+        .labelled("'check'")
+        .ignore_then(expr_parser().delimited_by(
+            select! { Token::LeftParen => () }.labelled("'('"),
+            select! { Token::RightParen => () }.labelled("')'"),
+        ))
+        .map_with(|expr, extra| CheckDef {
+            expr,
+            span: extra.span(),
+        })
+        .labelled("check constraint")
 }
 
 fn column_attribute_parser<'tokens, 'src: 'tokens, I>()
@@ -93,15 +246,30 @@ where
     .labelled("'primary' or 'unique'")
 }
 
+/// Parses the comma separated body of a table, where table-level check constraints
+/// may appear among the columns
 fn column_list_parser<'tokens, 'src: 'tokens, I>()
--> impl Parser<'tokens, I, Vec<ColumnDef>, extra::Err<Rich<'tokens, Token<'src>>>>
+-> impl Parser<'tokens, I, (Vec<ColumnDef>, Vec<CheckDef>), extra::Err<Rich<'tokens, Token<'src>>>>
 where
     I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
 {
     column_definition_parser()
+        .map(Ok)
+        .or(check_parser().map(Err))
         .separated_by(select! { Token::Comma => () })
         .at_least(1)
         .collect::<Vec<_>>()
+        .map(|items| {
+            let mut columns = Vec::new();
+            let mut checks = Vec::new();
+            for item in items {
+                match item {
+                    Ok(column) => columns.push(column),
+                    Err(check) => checks.push(check),
+                }
+            }
+            (columns, checks)
+        })
 }
 
 fn ident_string<'tokens, 'src: 'tokens, I>()
@@ -119,6 +287,16 @@ where
     })
 }
 
+/// A word that is a keyword only where it's expected, see [`Token::Id`]
+fn keyword<'tokens, 'src: 'tokens, I>(
+    word: &'static str,
+) -> impl Parser<'tokens, I, (), extra::Err<Rich<'tokens, Token<'src>>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    select! { Token::Id(name) if name == word => () }
+}
+
 fn ref_operator_parser<'tokens, 'src: 'tokens, I>()
 -> impl Parser<'tokens, I, RefOperator, extra::Err<Rich<'tokens, Token<'src>>>>
 where
@@ -154,32 +332,288 @@ where
         .labelled("reference expression")
 }
 
-fn composite_index_parser<'tokens, 'src: 'tokens, I>()
--> impl Parser<'tokens, I, Index, extra::Err<Rich<'tokens, Token<'src>>>>
+fn sort_order_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, SortOrder, extra::Err<Rich<'tokens, Token<'src>>>>
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    keyword("asc")
+        .to(SortOrder::Asc)
+        .or(keyword("desc").to(SortOrder::Desc))
+        .labelled("'asc' or 'desc'")
+}
+
+fn index_column_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, IndexColumn, extra::Err<Rich<'tokens, Token<'src>>>>
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    ident_string()
+        .then(sort_order_parser().or_not())
+        .map_with(|(id, order), extra| IndexColumn {
+            id,
+            order,
+            span: extra.span(),
+        })
+}
+
+fn index_column_list_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, Vec<IndexColumn>, extra::Err<Rich<'tokens, Token<'src>>>>
 where
     I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
 {
     select! { Token::LeftParen => () }
         .labelled("'('")
+        .ignore_then(
+            index_column_parser()
+                .separated_by(select! { Token::Comma => () }.labelled("','"))
+                .at_least(1)
+                .collect::<Vec<_>>(),
+        )
+        .then_ignore(select! { Token::RightParen => () }.labelled("')'"))
+        .labelled("indexed columns")
+}
+
+fn index_using_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, IndexUsing, extra::Err<Rich<'tokens, Token<'src>>>>
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    // methods stay plain ids rather than keywords, `hash` is a common column name
+    keyword("using")
+        .labelled("'using'")
+        .ignore_then(ident_string().try_map(|id, span| {
+            let method = match id.name.as_str() {
+                "btree" => IndexMethod::Btree,
+                "hash" => IndexMethod::Hash,
+                "gin" => IndexMethod::Gin,
+                "gist" => IndexMethod::Gist,
+                "brin" => IndexMethod::Brin,
+                other => {
+                    return Err(Rich::custom(
+                        span,
+                        format!(
+                            "unknown index method '{other}', expected one of btree, hash, gin, gist, brin"
+                        ),
+                    ));
+                }
+            };
+            Ok(IndexUsing { method, span })
+        }))
+}
+
+fn index_include_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, Vec<Ident>, extra::Err<Rich<'tokens, Token<'src>>>>
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    keyword("include")
+        .labelled("'include'")
+        .ignore_then(select! { Token::LeftParen => () }.labelled("'('"))
         .ignore_then(
             ident_string()
                 .separated_by(select! { Token::Comma => () }.labelled("','"))
-                .at_least(2)
+                .at_least(1)
                 .collect::<Vec<_>>(),
         )
         .then_ignore(select! { Token::RightParen => () }.labelled("')'"))
-        .map_with(|ids, extra| Index::Composite(ids, extra.span()))
-        .labelled("multiple indexes")
 }
 
+/// Parses `[unique] [name] (col [asc|desc], ...) [using <method>] [include (col, ...)]
+/// [where <expr>]`, where a bare `col [asc|desc]` is kept as a shorthand for a single
+/// column index
 fn index_item_parser<'tokens, 'src: 'tokens, I>()
 -> impl Parser<'tokens, I, Index, extra::Err<Rich<'tokens, Token<'src>>>>
 where
     I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
 {
-    composite_index_parser().or(ident_string()
-        .map_with(|id, extra| Index::Single(id, extra.span()))
-        .labelled("single index"))
+    let unique_flag = select! { Token::Unique => () }
+        .or_not()
+        .map(|opt| opt.is_some())
+        .labelled("'unique'");
+
+    let named_or_single = ident_string()
+        .then(
+            index_column_list_parser()
+                .map(Ok)
+                .or(sort_order_parser().or_not().map(Err)),
+        )
+        .map_with(|(id, rest), extra| match rest {
+            Ok(columns) => (Some(id), columns),
+            Err(order) => (
+                None,
+                vec![IndexColumn {
+                    id,
+                    order,
+                    span: extra.span(),
+                }],
+            ),
+        });
+
+    let columns = index_column_list_parser()
+        .map(|columns| (None, columns))
+        .or(named_or_single);
+
+    let predicate = keyword("where")
+        .labelled("'where'")
+        .ignore_then(expr_parser());
+
+    annotation_list_parser()
+        .then(unique_flag)
+        .then(columns)
+        .then(index_using_parser().or_not())
+        .then(index_include_parser().or_not())
+        .then(predicate.or_not())
+        .map_with(
+            |(((((annotations, is_unique), (name, columns)), using), include), predicate),
+             extra| Index {
+                annotations,
+                name,
+                is_unique,
+                columns,
+                using,
+                include,
+                predicate,
+                span: extra.span(),
+            },
+        )
+        .labelled("index")
+}
+
+/// Resolves the backslash escapes of a string literal
+fn unescape(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.extend(chars.next()),
+            c => value.push(c),
+        }
+    }
+    value
+}
+
+/// Left-associative chain of `operand (operator operand)*`
+fn binary_expr_parser<'tokens, 'src: 'tokens, I, P, O>(
+    operand: P,
+    operator: O,
+) -> impl Parser<'tokens, I, Expr, extra::Err<Rich<'tokens, Token<'src>>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+    P: Parser<'tokens, I, Expr, extra::Err<Rich<'tokens, Token<'src>>>> + Clone,
+    O: Parser<'tokens, I, BinaryOp, extra::Err<Rich<'tokens, Token<'src>>>> + Clone,
+{
+    operand.clone().foldl_with(
+        operator.then(operand).repeated(),
+        |lhs, (op, rhs), extra| Expr::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            span: extra.span(),
+        },
+    )
+}
+
+fn expr_parser<'tokens, 'src: 'tokens, I>()
+-> impl Parser<'tokens, I, Expr, extra::Err<Rich<'tokens, Token<'src>>>> + Clone
+where
+    I: ValueInput<'tokens, Token = Token<'src>, Span = SimpleSpan>,
+{
+    recursive(|expr| {
+        let literal = select! {
+            Token::Number(value) => Literal::Number(value.to_string()),
+            Token::Str(value) => Literal::String(unescape(value)),
+            Token::True => Literal::Bool(true),
+            Token::False => Literal::Bool(false),
+        }
+        .or(keyword("null").to(Literal::Null))
+        .map_with(|literal, extra| Expr::Literal(literal, extra.span()))
+        .labelled("literal");
+
+        // `null` is a literal rather than a column in expressions
+        let atom = literal
+            .or(ident_string().map(Expr::Column))
+            .or(expr.clone().delimited_by(
+                select! { Token::LeftParen => () }.labelled("'('"),
+                select! { Token::RightParen => () }.labelled("')'"),
+            ));
+
+        let negation = select! { Token::Minus => UnaryOp::Neg }
+            .repeated()
+            .foldr_with(atom, |op, expr, extra| Expr::Unary {
+                op,
+                expr: Box::new(expr),
+                span: extra.span(),
+            });
+
+        let product = binary_expr_parser(
+            negation.boxed(),
+            select! {
+                Token::Star => BinaryOp::Mul,
+                Token::Slash => BinaryOp::Div,
+            }
+            .labelled("'*' or '/'"),
+        );
+
+        let sum = binary_expr_parser(
+            product.boxed(),
+            select! {
+                Token::Plus => BinaryOp::Add,
+                Token::Minus => BinaryOp::Sub,
+            }
+            .labelled("'+' or '-'"),
+        );
+
+        let concat = binary_expr_parser(
+            sum.boxed(),
+            select! { Token::Concat => BinaryOp::Concat }.labelled("'||'"),
+        );
+
+        // `==` and `<>` lex as reference operators, but read naturally as comparisons here
+        let comparison = binary_expr_parser(
+            concat.boxed(),
+            select! {
+                Token::Eq => BinaryOp::Eq,
+                Token::RefOneToOne => BinaryOp::Eq,
+                Token::NotEq => BinaryOp::NotEq,
+                Token::RefManyToMany => BinaryOp::NotEq,
+                Token::Lt => BinaryOp::Lt,
+                Token::LtEq => BinaryOp::LtEq,
+                Token::Gt => BinaryOp::Gt,
+                Token::GtEq => BinaryOp::GtEq,
+            }
+            .labelled("comparison operator"),
+        );
+
+        let null_test = comparison.foldl_with(
+            keyword("is")
+                .ignore_then(keyword("not").or_not())
+                .then_ignore(keyword("null").labelled("'null'"))
+                .repeated()
+                .at_most(1),
+            |expr, negated, extra| Expr::IsNull {
+                expr: Box::new(expr),
+                negated: negated.is_some(),
+                span: extra.span(),
+            },
+        );
+
+        let not = keyword("not").to(UnaryOp::Not).repeated().foldr_with(
+            null_test.boxed(),
+            |op, expr, extra| Expr::Unary {
+                op,
+                expr: Box::new(expr),
+                span: extra.span(),
+            },
+        );
+
+        let and = binary_expr_parser(
+            not.boxed(),
+            keyword("and").to(BinaryOp::And).labelled("'and'"),
+        );
+        binary_expr_parser(and.boxed(), keyword("or").to(BinaryOp::Or).labelled("'or'"))
+    })
+    .labelled("expression")
 }
 
 fn index_section_parser<'tokens, 'src: 'tokens, I>()
@@ -199,9 +633,12 @@ where
         .then_ignore(select! { Token::RightBrace => () }.labelled("'}'"))
 }
 
-pub fn parse<'a>(source_name: &'a str, src: &'a str) -> Result<Schema, Vec<Rich<'a, Token<'a>>>> {
+pub fn parse(source_name: &str, src: &str) -> Result<Schema, Vec<Diagnostic>> {
     let tokens = lexer::lex(src);
-    schema_parser(source_name).parse(tokens).into_result()
+    schema_parser(source_name)
+        .parse(tokens)
+        .into_result()
+        .map_err(|errs| errs.into_iter().map(Diagnostic::from).collect())
 }
 
 #[test]
@@ -271,7 +708,7 @@ fn test_invalid_table_should_false() {
     ";
     match parse("test.mecha", schema) {
         Ok(schema) => assert!(schema.tables.len() > 0),
-        Err(errs) => diagnose(schema, "test.mecha", errs),
+        Err(errs) => report(schema, "test.mecha", &errs),
     }
 }
 
@@ -287,7 +724,7 @@ fn test_indexed_table_should_ok() {
     ";
     match parse("test.mecha", schema) {
         Ok(schema) => assert!(schema.tables.len() > 0),
-        Err(errs) => diagnose(schema, "test.mecha", errs),
+        Err(errs) => report(schema, "test.mecha", &errs),
     }
 }
 
@@ -304,6 +741,155 @@ fn test_comment_should_ok() {
     ";
     match parse("test.mecha", schema) {
         Ok(schema) => assert!(schema.tables.len() > 0),
-        Err(errs) => diagnose(schema, "test.mecha", errs),
+        Err(errs) => report(schema, "test.mecha", &errs),
+    }
+}
+
+#[test]
+fn test_named_unique_partial_indexes_should_ok() {
+    let schema: &str = r"
+        table users {
+            email: text,
+            created_at: timestamp,
+            deleted_at: timestamp
+            indexes {
+                unique idx_email (email),
+                (created_at desc),
+                idx_live (email, created_at asc) where deleted_at is null and email is not null
+            }
+        }
+    ";
+    match parse("test.mecha", schema) {
+        Ok(schema) => {
+            let indexes = schema.tables[0].indexes.as_ref().unwrap();
+            assert_eq!(indexes.len(), 3);
+            assert!(indexes[0].is_unique);
+            assert_eq!(indexes[0].name.as_ref().unwrap().name, "idx_email");
+            assert!(indexes[1].name.is_none());
+            assert_eq!(indexes[1].columns[0].order, Some(SortOrder::Desc));
+            assert_eq!(
+                indexes[2].predicate.as_ref().unwrap().to_string(),
+                "(deleted_at is null and email is not null)"
+            );
+        }
+        Err(errs) => {
+            report(schema, "test.mecha", &errs);
+            panic!("test failed");
+        }
+    }
+}
+
+#[test]
+fn test_unknown_index_method_should_fail() {
+    let schema: &str = r"
+        table documents {
+            body: jsonb
+            indexes {
+                body using bitmap
+            }
+        }
+    ";
+    assert!(parse("test.mecha", schema).is_err());
+}
+
+#[test]
+fn test_doc_comment_should_ok() {
+    let schema: &str = r"
+        /// registered users
+        /// of the store
+        table users {
+            // not a doc comment
+            id: uuid,
+            /// higher the number, higher owner level
+            ranking: number
+        }
+    ";
+    match parse("test.mecha", schema) {
+        Ok(schema) => {
+            let table = &schema.tables[0];
+            assert_eq!(table.doc.as_deref(), Some("registered users\nof the store"));
+            assert_eq!(table.columns[0].doc, None);
+            assert_eq!(
+                table.columns[1].doc.as_deref(),
+                Some("higher the number, higher owner level")
+            );
+        }
+        Err(errs) => {
+            report(schema, "test.mecha", &errs);
+            panic!("test failed");
+        }
+    }
+}
+
+#[test]
+fn test_keywords_as_column_names_should_ok() {
+    let schema: &str = r"
+        table items {
+            desc: text,
+            check: bool,
+            where: text,
+            null: int generated (check + 1) stored,
+            check (desc is not null or not check)
+            indexes {
+                desc desc,
+                (check, where asc) where desc != ''
+            }
+        }
+    ";
+    match parse("test.mecha", schema) {
+        Ok(schema) => {
+            let table = &schema.tables[0];
+            let names: Vec<&str> = table.columns.iter().map(|c| c.id.name.as_str()).collect();
+            assert_eq!(names, ["desc", "check", "where", "null"]);
+            assert_eq!(table.checks.len(), 1);
+            assert_eq!(
+                table.checks[0].expr.to_string(),
+                "(desc is not null or not check)"
+            );
+
+            let indexes = table.indexes.as_ref().unwrap();
+            assert_eq!(indexes[0].columns[0].id.name, "desc");
+            assert_eq!(indexes[0].columns[0].order, Some(SortOrder::Desc));
+            assert_eq!(indexes[1].columns[1].id.name, "where");
+            assert!(indexes[1].predicate.is_some());
+        }
+        Err(errs) => {
+            report(schema, "test.mecha", &errs);
+            panic!("test failed");
+        }
+    }
+}
+
+#[test]
+fn test_detached_doc_comment_should_ok() {
+    let schema: &str = r"
+        table users {
+            id: uuid
+            /// after the last column
+        }
+
+        table owners {
+            /// the name
+            @pii
+            name: text
+            /// before the indexes
+            indexes {
+                name
+            }
+        }
+        /// at the end
+    ";
+    match parse("test.mecha", schema) {
+        Ok(schema) => {
+            assert_eq!(schema.tables[0].columns.len(), 1);
+            assert_eq!(schema.tables[0].columns[0].doc, None);
+            assert_eq!(schema.tables[1].doc, None);
+            assert_eq!(schema.tables[1].columns[0].doc.as_deref(), Some("the name"));
+            assert_eq!(schema.tables[1].indexes.as_ref().unwrap().len(), 1);
+        }
+        Err(errs) => {
+            report(schema, "test.mecha", &errs);
+            panic!("test failed");
+        }
     }
 }
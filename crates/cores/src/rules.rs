@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Off,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Off => write!(f, "off"),
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Severity::Error),
+            "warning" => Ok(Severity::Warning),
+            "off" => Ok(Severity::Off),
+            _ => Err(format!(
+                "unknown severity '{s}', expected one of error, warning, off"
+            )),
+        }
+    }
+}
+
+/// A lint or compatibility rule, reported with its own severity unless configured otherwise
+#[derive(Debug)]
+pub struct Rule {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub severity: Severity,
+    pub summary: &'static str,
+}
+
+/// Looks a rule up by its code or its name, e.g. `L001` or `missing-primary-key`
+pub fn find(rules: &'static [Rule], code_or_name: &str) -> Option<&'static Rule> {
+    rules
+        .iter()
+        .find(|rule| rule.code == code_or_name || rule.name == code_or_name)
+}
+
+/// Severities of a set of rules, their defaults unless overridden
+#[derive(Debug, Clone)]
+pub struct Config {
+    rules: &'static [Rule],
+    overrides: HashMap<&'static str, Severity>,
+}
+
+impl Config {
+    pub fn new(rules: &'static [Rule]) -> Self {
+        Config {
+            rules,
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Overrides a rule from a `<code|name>=<severity>` setting, e.g. `L001=error`
+    pub fn set(&mut self, setting: &str) -> Result<(), String> {
+        let Some((key, severity)) = setting.split_once('=') else {
+            return Err(format!(
+                "invalid rule setting '{setting}', expected <code|name>=<severity>"
+            ));
+        };
+        self.set_rule(key.trim(), severity.trim().parse()?)
+    }
+
+    /// Overrides a rule given by its code or its name
+    pub fn set_rule(&mut self, code_or_name: &str, severity: Severity) -> Result<(), String> {
+        let Some(rule) = find(self.rules, code_or_name) else {
+            return Err(format!("unknown rule '{code_or_name}'"));
+        };
+        self.overrides.insert(rule.code, severity);
+        Ok(())
+    }
+
+    pub fn severity(&self, rule: &Rule) -> Severity {
+        self.overrides
+            .get(rule.code)
+            .copied()
+            .unwrap_or(rule.severity)
+    }
+}
@@ -0,0 +1,116 @@
+use crate::diagnostic::{LocatedDiagnostic, Location, Severity};
+use serde_json::{Value, json};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Writes diagnostics as a SARIF 2.1.0 log, which code scanning services and editors read
+pub fn log(diagnostics: &[LocatedDiagnostic]) -> Value {
+    let mut rules: Vec<&str> = diagnostics.iter().filter_map(|d| d.code).collect();
+    rules.sort_unstable();
+    rules.dedup();
+
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "mecha",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|code| json!({ "id": code })).collect::<Vec<_>>(),
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": diagnostics.iter().map(result).collect::<Vec<_>>(),
+        }]
+    })
+}
+
+fn result(diagnostic: &LocatedDiagnostic) -> Value {
+    let level = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+
+    // SARIF has nowhere else for them, notes and help go along with the message
+    let mut text = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        text.push_str(&format!("\nnote: {note}"));
+    }
+    if let Some(help) = &diagnostic.help {
+        text.push_str(&format!("\nhelp: {help}"));
+    }
+
+    let mut result = json!({
+        "level": level,
+        "message": { "text": text },
+        "locations": [location(&diagnostic.location, None)],
+        "relatedLocations": diagnostic
+            .related
+            .iter()
+            .enumerate()
+            .map(|(id, related)| location(related, Some(id)))
+            .collect::<Vec<_>>(),
+    });
+    if let Some(code) = diagnostic.code {
+        result["ruleId"] = json!(code);
+    }
+    result
+}
+
+fn location(location: &Location, id: Option<usize>) -> Value {
+    let range = &location.range;
+    let mut value = json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": location.file.replace('\\', "/") },
+            "region": {
+                "startLine": range.start.line,
+                "startColumn": range.start.column,
+                "endLine": range.end.line,
+                "endColumn": range.end.column,
+                "byteOffset": range.start.offset,
+                "byteLength": range.end.offset - range.start.offset,
+            }
+        },
+        "message": { "text": location.message },
+    });
+    if let Some(id) = id {
+        value["id"] = json!(id);
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::log;
+    use crate::codegen::check;
+    use crate::diagnostic::LocatedDiagnostic;
+
+    #[test]
+    fn test_log() {
+        let src = "table users { id: uuid }\ntable users { name: text }";
+        let errs = check(src, "test.mecha").unwrap_err();
+        let diagnostics: Vec<LocatedDiagnostic> = errs
+            .iter()
+            .map(|err| LocatedDiagnostic::new("schemas/test.mecha", src, err))
+            .collect();
+
+        let log = log(&diagnostics);
+        let result = &log["runs"][0]["results"][0];
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(result["level"], "error");
+        assert_eq!(result["message"]["text"], "table users is redeclared");
+
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "schemas/test.mecha");
+        assert_eq!(location["region"]["startLine"], 2);
+        assert_eq!(location["region"]["startColumn"], 7);
+        assert_eq!(location["region"]["byteOffset"], 31);
+        assert_eq!(location["region"]["byteLength"], 5);
+
+        let related = &result["relatedLocations"][0];
+        assert_eq!(related["id"], 0);
+        assert_eq!(related["message"]["text"], "it's first declared here");
+        assert_eq!(related["physicalLocation"]["region"]["startLine"], 1);
+    }
+}
@@ -1,15 +1,34 @@
-use crate::ast::{ColumnDef, Ident, Index, Schema, TableDef};
-use crate::lexer::Token;
-use chumsky::error::Rich;
+use crate::ast::{
+    Annotation, CheckDef, ColumnAttribute, ColumnDef, Ident, Index, IndexMethod, Literal, Schema,
+    SemanticErr, SortOrder, TableDef,
+};
+use crate::ddl;
+use crate::diagnostic::Diagnostic;
+use crate::lint;
 use chumsky::span::SimpleSpan;
 use std::collections::{HashMap, HashSet};
 
-type CheckResult<'a, T> = Result<T, Vec<Rich<'a, Token<'a>, SimpleSpan>>>;
+type CheckResult<T> = Result<T, Vec<Diagnostic>>;
 
 impl Schema {
-    pub fn check<'a>(&mut self) -> CheckResult<'a, ()> {
+    pub fn check(&mut self) -> CheckResult<()> {
         let extension_context = self.build_extension_context()?;
 
+        for (annotation, target) in self.annotations() {
+            check_annotation(annotation, target)?;
+        }
+
+        for table in &self.tables {
+            let table_name = table.id.name.as_str();
+            let all_columns = extension_context.get(table_name).unwrap_or_else(|| {
+                panic!("table '{table_name}' not exist in the inheritance context")
+            });
+
+            check_renames(table, &self.tables, all_columns)?;
+        }
+
+        let mut index_names: HashMap<String, SimpleSpan> = HashMap::new();
+
         for table in &self.tables {
             let Some(indexes) = &table.indexes else {
                 continue;
@@ -30,7 +49,7 @@ impl Schema {
 
             let check_column = |id: &Ident| -> CheckResult<()> {
                 if !valid_column_names.contains(id.name.as_str()) {
-                    let errs = vec![Rich::custom(
+                    let errs = vec![SemanticErr::NonExistentColumn.error(
                         id.span,
                         format!(
                             "indexed column '{}' does not exist in table '{}'",
@@ -43,15 +62,24 @@ impl Schema {
             };
 
             for index in indexes {
-                match index {
-                    Index::Single(id, span) => check_column(id)?,
-                    Index::Composite(ids, span) => {
-                        for id in ids {
-                            check_column(id)?;
-                        }
+                for column in &index.columns {
+                    check_column(&column.id)?;
+                }
+
+                for id in index.include.iter().flatten() {
+                    check_column(id)?;
+                }
+
+                if let Some(predicate) = &index.predicate {
+                    for id in predicate.columns() {
+                        check_column(id)?;
                     }
                 }
             }
+
+            // redundant indexes share a name as well, the redundancy tells more
+            check_redundant_indexes(indexes)?;
+            check_index_names(table, indexes, &mut index_names)?;
         }
 
         for table in &self.tables {
@@ -63,7 +91,7 @@ impl Schema {
                 let Some(referenced_table_column) =
                     extension_context.get(reference.table.name.as_str())
                 else {
-                    let errs = vec![Rich::custom(
+                    let errs = vec![SemanticErr::NonExistentTable.error(
                         reference.span,
                         format!(
                             "table '{}' is not exist in the schema",
@@ -73,13 +101,11 @@ impl Schema {
                     return Err(errs);
                 };
 
-                let valid_referenced_columns: HashSet<&str> = referenced_table_column
+                let Some(referenced_column) = referenced_table_column
                     .iter()
-                    .map(|col| col.id.name.as_str())
-                    .collect();
-
-                if !valid_referenced_columns.contains(reference.column.name.as_str()) {
-                    let errs = vec![Rich::custom(
+                    .find(|col| col.id.name == reference.column.name)
+                else {
+                    let errs = vec![SemanticErr::NonExistentColumn.error(
                         reference.column.span,
                         format!(
                             "column '{}' is not existed in the table '{}'",
@@ -88,10 +114,38 @@ impl Schema {
                         ),
                     )];
                     return Err(errs);
+                };
+
+                // the foreign key constraint of a reference lives on the referenced column
+                if let Some(generated) = &referenced_column.generated {
+                    let errs = vec![
+                        SemanticErr::InvalidGeneratedColumn
+                            .error(
+                                reference.column.span,
+                                format!(
+                                    "column '{}' can't hold a foreign key",
+                                    reference.column.name
+                                ),
+                            )
+                            .with_label(generated.span, "because it's generated here"),
+                    ];
+                    return Err(errs);
                 }
             }
         }
 
+        for table in &self.tables {
+            let table_name = table.id.name.as_str();
+            let all_columns = extension_context.get(table_name).unwrap_or_else(|| {
+                panic!("table '{table_name}' not exist in the inheritance context")
+            });
+
+            check_constraints(table, all_columns)?;
+            check_generated_columns(table, all_columns)?;
+        }
+
+        let inherited_checks = self.collect_inherited_checks();
+
         for table in &mut self.tables {
             if table.is_abstract {
                 continue;
@@ -101,6 +155,10 @@ impl Schema {
                 continue;
             };
 
+            if let Some(parent_checks) = inherited_checks.get(parent_table.name.as_str()) {
+                table.checks.extend(parent_checks.iter().cloned());
+            }
+
             let parent_table_name = parent_table.name.as_str();
             let Some(parent_columns) = extension_context.get(parent_table_name) else {
                 continue;
@@ -114,10 +172,76 @@ impl Schema {
         Ok(())
     }
 
+    /// Warnings that don't prevent the schema from compiling, as of now annotations
+    /// unknown to mecha which are kept as-is for downstream tools
+    pub fn warnings(&self) -> Vec<Diagnostic> {
+        self.annotations()
+            .into_iter()
+            .filter(|(annotation, _)| annotation_spec(&annotation.name.name).is_none())
+            .map(|(annotation, _)| {
+                SemanticErr::UnknownAnnotation.warning(
+                    annotation.name.span,
+                    format!("unknown annotation '@{}'", annotation.name.name),
+                )
+            })
+            .collect()
+    }
+
+    /// Every annotation of the schema, along with what it is attached to
+    fn annotations(&self) -> Vec<(&Annotation, AnnotationTarget)> {
+        let mut annotations = Vec::new();
+
+        for table in &self.tables {
+            for annotation in &table.annotations {
+                annotations.push((annotation, AnnotationTarget::Table));
+            }
+            for column in &table.columns {
+                for annotation in &column.annotations {
+                    annotations.push((annotation, AnnotationTarget::Column));
+                }
+            }
+            for index in table.indexes.iter().flatten() {
+                for annotation in &index.annotations {
+                    annotations.push((annotation, AnnotationTarget::Index));
+                }
+            }
+        }
+
+        annotations
+    }
+
+    /// Collects the table-level checks of every table together with the ones of its
+    /// ancestors, so they can be carried into the concrete tables extending them
+    fn collect_inherited_checks(&self) -> HashMap<String, Vec<CheckDef>> {
+        let table_map: HashMap<&str, &TableDef> = self
+            .tables
+            .iter()
+            .map(|table| (table.id.name.as_str(), table))
+            .collect();
+
+        let mut inherited: HashMap<String, Vec<CheckDef>> = HashMap::new();
+
+        for table in &self.tables {
+            let mut checks = table.checks.clone();
+            let mut current_table = table;
+
+            while let Some(parent_ident) = current_table.extended_by.as_ref() {
+                let Some(parent_table) = table_map.get(parent_ident.name.as_str()) else {
+                    break;
+                };
+                checks.extend(parent_table.checks.iter().cloned());
+                current_table = parent_table;
+            }
+
+            inherited.insert(table.id.name.clone(), checks);
+        }
+
+        inherited
+    }
+
     /// Collects tables and resolves all extended columns into an owned HashMap
     /// Returns `HashMap<String, Vec<ColumnDef>>` instead of references to avoid borrowing conflicts
-    /// Note: 'a is the lifetime of the Error, independent of the &self borrow
-    fn build_extension_context<'a>(&self) -> CheckResult<'a, HashMap<String, Vec<ColumnDef>>> {
+    fn build_extension_context(&self) -> CheckResult<HashMap<String, Vec<ColumnDef>>> {
         let table_map = self.collect_tables()?;
 
         self.check_extension(&table_map)?;
@@ -126,13 +250,15 @@ impl Schema {
         let mut context: HashMap<String, Vec<ColumnDef>> = HashMap::new();
 
         for (name, &table) in &table_map {
-            let mut extension_columns: HashMap<String, ColumnDef> = HashMap::new();
+            // columns are kept in declaration order, own columns first, then ancestors'
+            let mut extension_columns: Vec<ColumnDef> = table.columns.clone();
+            let mut column_names: HashSet<String> = table
+                .columns
+                .iter()
+                .map(|col| col.id.name.clone())
+                .collect();
             let mut current_table = table;
 
-            for column in &table.columns {
-                extension_columns.insert(column.id.name.clone(), column.clone());
-            }
-
             loop {
                 let Some(parent_table_ident) = current_table.extended_by.as_ref() else {
                     break;
@@ -143,15 +269,15 @@ impl Schema {
                 let mut check_column = |parent_columns: &Vec<ColumnDef>| -> CheckResult<()> {
                     for parent_column in parent_columns {
                         let parent_column_name = parent_column.id.name.as_str();
-                        if extension_columns.contains_key(parent_column_name) {
-                            let errs = vec![Rich::custom(
+                        if column_names.contains(parent_column_name) {
+                            let errs = vec![SemanticErr::ColumnRedeclaration.error(
                                 parent_column.span,
                                 format!("column '{}' is redeclared", parent_column_name),
                             )];
                             return Err(errs);
                         }
-                        extension_columns
-                            .insert(parent_column_name.to_string(), parent_column.clone());
+                        column_names.insert(parent_column_name.to_string());
+                        extension_columns.push(parent_column.clone());
                     }
                     Ok(())
                 };
@@ -170,8 +296,7 @@ impl Schema {
                 }
             }
 
-            let extension_columns_vec = extension_columns.values().cloned().collect();
-            context.insert(name.to_string(), extension_columns_vec);
+            context.insert(name.to_string(), extension_columns);
         }
 
         Ok(context)
@@ -179,16 +304,13 @@ impl Schema {
 
     /// Check for [`SemanticErr::NonAbstractParent`], [`SemanticErr::NonExistentParent`]
     /// Uses explicit lifetimes 'a (Error) and 's (Self/Map) to allow decoupling
-    fn check_extension<'a, 's>(
-        &self,
-        table_map: &HashMap<String, &'s TableDef>,
-    ) -> CheckResult<'a, ()> {
+    fn check_extension<'s>(&self, table_map: &HashMap<String, &'s TableDef>) -> CheckResult<()> {
         for (_, table) in table_map {
             if let Some(parent_ident) = table.extended_by.as_ref() {
                 let parent_name = parent_ident.name.as_str();
 
                 let Some(parent_table) = table_map.get(parent_name) else {
-                    let errs = vec![Rich::custom(
+                    let errs = vec![SemanticErr::NonExistentParent.error(
                         parent_ident.span,
                         format!("table {} is not existed", parent_name),
                     )];
@@ -197,11 +319,13 @@ impl Schema {
 
                 if !parent_table.is_abstract {
                     let errs = vec![
-                        Rich::custom(
-                            table.span,
-                            format!("table {} is referenced here", parent_name),
-                        ),
-                        Rich::custom(parent_table.span, "but it's not abstract"),
+                        SemanticErr::NonAbstractParent
+                            .error(
+                                parent_ident.span,
+                                format!("table {} is extended but it's not abstract", parent_name),
+                            )
+                            .with_label(parent_table.id.span, "it's declared here")
+                            .with_help(format!("declare it as `abstract table {parent_name}`")),
                     ];
                     return Err(errs);
                 }
@@ -212,10 +336,10 @@ impl Schema {
     }
 
     /// Check for [`SemanticErr::CyclicRef`]
-    fn check_cyclic_extension<'a, 's>(
+    fn check_cyclic_extension<'s>(
         &self,
         table_map: &HashMap<String, &'s TableDef>,
-    ) -> CheckResult<'a, ()> {
+    ) -> CheckResult<()> {
         let mut checked: HashSet<&str> = HashSet::new();
 
         let mut sorted_tables: Vec<&TableDef> = table_map.values().copied().collect();
@@ -254,7 +378,7 @@ impl Schema {
 
                 if visited.contains(next_table_name) {
                     // oops, this table has been visited
-                    let errs = vec![Rich::custom(
+                    let errs = vec![SemanticErr::CyclicRef.error(
                         next_table.span,
                         format!("cyclic reference happens at {next_table_name}",),
                     )];
@@ -273,7 +397,7 @@ impl Schema {
 
     /// Return a [`HashMap`] and also check for [`SemanticErr::TableRedeclaration`]
     /// Crucial: 's is the lifetime of the borrow of self, 'a is the lifetime of the Error
-    fn collect_tables<'a, 's>(&'s self) -> CheckResult<'a, HashMap<String, &'s TableDef>> {
+    fn collect_tables<'s>(&'s self) -> CheckResult<HashMap<String, &'s TableDef>> {
         let mut map: HashMap<String, &TableDef> = HashMap::new();
 
         for table in &self.tables {
@@ -281,11 +405,12 @@ impl Schema {
 
             if let Some(prev_table) = map.get(table_name) {
                 let errs = vec![
-                    Rich::custom(
-                        prev_table.id.span,
-                        format!("table {} is declared here", prev_table.id.name),
-                    ),
-                    Rich::custom(table.id.span, "but redeclared here"),
+                    SemanticErr::TableRedeclaration
+                        .error(
+                            table.id.span,
+                            format!("table {} is redeclared", table.id.name),
+                        )
+                        .with_label(prev_table.id.span, "it's first declared here"),
                 ];
                 return Err(errs);
             }
@@ -296,6 +421,416 @@ impl Schema {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AnnotationTarget {
+    Table,
+    Column,
+    Index,
+}
+
+impl AnnotationTarget {
+    fn name(&self) -> &'static str {
+        match self {
+            AnnotationTarget::Table => "tables",
+            AnnotationTarget::Column => "columns",
+            AnnotationTarget::Index => "indexes",
+        }
+    }
+}
+
+enum AnnotationArgs {
+    None,
+    /// a single optional positional string, e.g. `@deprecated("use email_v2")`
+    OptionalString,
+    /// a single positional string, e.g. `@renamed_from("people")`
+    String,
+    /// codes or names of lint rules as positional strings, e.g.
+    /// `@allow("L001", "naming-convention")`
+    LintRules,
+    /// named string arguments only, e.g. `@db.postgres(type = "citext")`
+    Named(&'static [&'static str]),
+}
+
+struct AnnotationSpec {
+    targets: &'static [AnnotationTarget],
+    args: AnnotationArgs,
+}
+
+/// Annotations mecha knows about, anything else is reported by [`Schema::warnings`]
+fn annotation_spec(name: &str) -> Option<AnnotationSpec> {
+    use AnnotationTarget::*;
+
+    let spec = match name {
+        "pii" => AnnotationSpec {
+            targets: &[Table, Column],
+            args: AnnotationArgs::None,
+        },
+        "deprecated" => AnnotationSpec {
+            targets: &[Table, Column, Index],
+            args: AnnotationArgs::OptionalString,
+        },
+        "renamed_from" => AnnotationSpec {
+            targets: &[Table, Column],
+            args: AnnotationArgs::String,
+        },
+        "db.postgres" | "db.mysql" | "db.sqlite" => AnnotationSpec {
+            targets: &[Column],
+            args: AnnotationArgs::Named(&["type"]),
+        },
+        "allow" => AnnotationSpec {
+            targets: &[Table, Column, Index],
+            args: AnnotationArgs::LintRules,
+        },
+        _ => return None,
+    };
+    Some(spec)
+}
+
+/// Check for [`SemanticErr::InvalidAnnotation`], unknown annotations are left to [`Schema::warnings`]
+fn check_annotation(annotation: &Annotation, target: AnnotationTarget) -> CheckResult<()> {
+    let name = annotation.name.name.as_str();
+    let Some(spec) = annotation_spec(name) else {
+        return Ok(());
+    };
+
+    if !spec.targets.contains(&target) {
+        let errs = vec![SemanticErr::InvalidAnnotation.error(
+            annotation.span,
+            format!("annotation '@{name}' can't be used on {}", target.name()),
+        )];
+        return Err(errs);
+    }
+
+    match spec.args {
+        AnnotationArgs::None => {
+            if let Some(arg) = annotation.args.first() {
+                let errs = vec![
+                    SemanticErr::InvalidAnnotation
+                        .error(arg.span, format!("annotation '@{name}' takes no arguments")),
+                ];
+                return Err(errs);
+            }
+        }
+        AnnotationArgs::OptionalString => {
+            let valid = match annotation.args.as_slice() {
+                [] => true,
+                [arg] => arg.key.is_none() && matches!(arg.value, Literal::String(_)),
+                _ => false,
+            };
+            if !valid {
+                let errs = vec![SemanticErr::InvalidAnnotation.error(
+                    annotation.span,
+                    format!("annotation '@{name}' takes a single optional string"),
+                )];
+                return Err(errs);
+            }
+        }
+        AnnotationArgs::String => {
+            let valid = matches!(
+                annotation.args.as_slice(),
+                [arg] if arg.key.is_none() && matches!(arg.value, Literal::String(_))
+            );
+            if !valid {
+                let errs = vec![SemanticErr::InvalidAnnotation.error(
+                    annotation.span,
+                    format!("annotation '@{name}' takes a single string"),
+                )];
+                return Err(errs);
+            }
+        }
+        AnnotationArgs::LintRules => {
+            if annotation.args.is_empty() {
+                let errs = vec![SemanticErr::InvalidAnnotation.error(
+                    annotation.span,
+                    format!("annotation '@{name}' takes one or more lint rules"),
+                )];
+                return Err(errs);
+            }
+
+            for arg in &annotation.args {
+                let Literal::String(value) = &arg.value else {
+                    let errs = vec![
+                        SemanticErr::InvalidAnnotation
+                            .error(arg.span, format!("annotation '@{name}' only takes strings")),
+                    ];
+                    return Err(errs);
+                };
+                if arg.key.is_some() {
+                    let errs = vec![SemanticErr::InvalidAnnotation.error(
+                        arg.span,
+                        format!("annotation '@{name}' only takes positional arguments"),
+                    )];
+                    return Err(errs);
+                }
+
+                if lint::rule(value).is_none() {
+                    let errs = vec![
+                        SemanticErr::InvalidAnnotation
+                            .error(arg.span, format!("unknown lint rule '{value}'")),
+                    ];
+                    return Err(errs);
+                }
+            }
+        }
+        AnnotationArgs::Named(keys) => {
+            for arg in &annotation.args {
+                let Some(key) = &arg.key else {
+                    let errs = vec![SemanticErr::InvalidAnnotation.error(
+                        arg.span,
+                        format!("annotation '@{name}' only takes named arguments"),
+                    )];
+                    return Err(errs);
+                };
+
+                if !keys.contains(&key.name.as_str()) {
+                    let errs = vec![SemanticErr::InvalidAnnotation.error(
+                        key.span,
+                        format!(
+                            "unknown argument '{}' for '@{name}', expected one of {}",
+                            key.name,
+                            keys.join(", ")
+                        ),
+                    )];
+                    return Err(errs);
+                }
+
+                if !matches!(arg.value, Literal::String(_)) {
+                    let errs = vec![SemanticErr::InvalidAnnotation.error(
+                        arg.span,
+                        format!("argument '{}' of '@{name}' must be a string", key.name),
+                    )];
+                    return Err(errs);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check for [`SemanticErr::InvalidRename`], a table or column can't be renamed from a
+/// name that is still in use next to it
+fn check_renames(
+    table: &TableDef,
+    tables: &[TableDef],
+    all_columns: &[ColumnDef],
+) -> CheckResult<()> {
+    if let Some((from, annotation)) = table.renamed_from()
+        && tables.iter().any(|t| t.id.name == from)
+    {
+        let errs = vec![SemanticErr::InvalidRename.error(
+            annotation.span,
+            format!(
+                "table '{}' can't be renamed from '{from}', which is still declared",
+                table.id.name
+            ),
+        )];
+        return Err(errs);
+    }
+
+    for column in &table.columns {
+        if let Some((from, annotation)) = column.renamed_from()
+            && all_columns.iter().any(|c| c.id.name == from)
+        {
+            let errs = vec![SemanticErr::InvalidRename.error(
+                annotation.span,
+                format!(
+                    "column '{}' can't be renamed from '{from}', which is still a column of '{}'",
+                    column.id.name, table.id.name
+                ),
+            )];
+            return Err(errs);
+        }
+    }
+
+    Ok(())
+}
+
+/// Check for [`SemanticErr::InvalidCheckColumn`], table-level checks may use any column
+/// of the table, column-level checks only the column they are attached to
+fn check_constraints(table: &TableDef, all_columns: &[ColumnDef]) -> CheckResult<()> {
+    let valid_column_names: HashSet<&str> =
+        all_columns.iter().map(|col| col.id.name.as_str()).collect();
+
+    for check in &table.checks {
+        for id in check.expr.columns() {
+            if !valid_column_names.contains(id.name.as_str()) {
+                let errs = vec![SemanticErr::InvalidCheckColumn.error(
+                    id.span,
+                    format!(
+                        "checked column '{}' does not exist in table '{}'",
+                        id.name, table.id.name
+                    ),
+                )];
+                return Err(errs);
+            }
+        }
+    }
+
+    for column in &table.columns {
+        let Some(check) = &column.check else {
+            continue;
+        };
+
+        for id in check.expr.columns() {
+            if id.name != column.id.name {
+                let errs = vec![
+                    SemanticErr::InvalidCheckColumn
+                        .error(
+                            id.span,
+                            format!(
+                                "check on column '{}' can't use column '{}'",
+                                column.id.name, id.name
+                            ),
+                        )
+                        .with_label(check.span, "the check of the column")
+                        .with_help("move it to a table-level check to compare columns"),
+                ];
+                return Err(errs);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check for [`SemanticErr::InvalidGeneratedColumn`], generated columns are computed
+/// from plain columns of the table and can't be keys or foreign key sources
+fn check_generated_columns(table: &TableDef, all_columns: &[ColumnDef]) -> CheckResult<()> {
+    for column in &table.columns {
+        let Some(generated) = &column.generated else {
+            continue;
+        };
+
+        if let Some(ColumnAttribute::Primary) = column.attribute {
+            let errs = vec![SemanticErr::InvalidGeneratedColumn.error(
+                column.span,
+                format!(
+                    "generated column '{}' can't be a primary key",
+                    column.id.name
+                ),
+            )];
+            return Err(errs);
+        }
+
+        if let Some(reference) = &column.reference {
+            let errs = vec![SemanticErr::InvalidGeneratedColumn.error(
+                reference.span,
+                format!(
+                    "generated column '{}' can't be a foreign key source",
+                    column.id.name
+                ),
+            )];
+            return Err(errs);
+        }
+
+        for id in generated.expr.columns() {
+            let Some(source) = all_columns.iter().find(|col| col.id.name == id.name) else {
+                let errs = vec![SemanticErr::NonExistentColumn.error(
+                    id.span,
+                    format!(
+                        "column '{}' does not exist in table '{}'",
+                        id.name, table.id.name
+                    ),
+                )];
+                return Err(errs);
+            };
+
+            if source.id.name == column.id.name {
+                let errs = vec![SemanticErr::InvalidGeneratedColumn.error(
+                    id.span,
+                    format!("generated column '{}' can't use itself", column.id.name),
+                )];
+                return Err(errs);
+            }
+
+            if source.generated.is_some() {
+                let errs = vec![
+                    SemanticErr::InvalidGeneratedColumn
+                        .error(
+                            id.span,
+                            format!(
+                                "generated column '{}' can't use another generated column",
+                                column.id.name
+                            ),
+                        )
+                        .with_label(source.span, format!("'{}' is generated here", id.name)),
+                ];
+                return Err(errs);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check for [`SemanticErr::IndexRedeclaration`]
+/// Index names share one namespace across the whole schema, as they do in most dialects
+/// Check for [`SemanticErr::IndexRedeclaration`], an index named like a previous one. An
+/// index without a name goes by the one it's generated with, made of its table and columns.
+fn check_index_names(
+    table: &TableDef,
+    indexes: &[Index],
+    index_names: &mut HashMap<String, SimpleSpan>,
+) -> CheckResult<()> {
+    for index in indexes {
+        let name = ddl::index_name(table, index);
+        let span = index.name.as_ref().map_or(index.span, |name| name.span);
+
+        if let Some(prev_span) = index_names.get(&name) {
+            let mut err = SemanticErr::IndexRedeclaration
+                .error(span, format!("index {name} is redeclared"))
+                .with_label(*prev_span, "it's first declared here");
+            if index.name.is_none() {
+                err = err
+                    .with_note("an index without a name is named after its table and columns")
+                    .with_help("give one of the indexes a name");
+            }
+            return Err(vec![err]);
+        }
+
+        index_names.insert(name, span);
+    }
+
+    Ok(())
+}
+
+/// Check for [`SemanticErr::RedundantIndex`], an index that covers the same columns
+/// in the same order, with the same uniqueness, method and predicate as a previous one of
+/// the table. A unique index next to a plain one is a constraint, not a duplicate.
+fn check_redundant_indexes(indexes: &[Index]) -> CheckResult<()> {
+    // (column name, is descending) pairs, uniqueness, the method and the rendered predicate
+    type IndexShape<'s> = (Vec<(&'s str, bool)>, bool, IndexMethod, Option<String>);
+    let mut seen: HashMap<IndexShape, &Index> = HashMap::new();
+
+    for index in indexes {
+        let columns: Vec<_> = index
+            .columns
+            .iter()
+            .map(|col| (col.id.name.as_str(), col.order == Some(SortOrder::Desc)))
+            .collect();
+        let method = index
+            .using
+            .as_ref()
+            .map_or(IndexMethod::Btree, |u| u.method);
+        let predicate = index.predicate.as_ref().map(|expr| expr.to_string());
+        let shape = (columns, index.is_unique, method, predicate);
+
+        if let Some(prev_index) = seen.get(&shape) {
+            let errs = vec![
+                SemanticErr::RedundantIndex
+                    .error(index.span, "this index is redundant")
+                    .with_label(prev_index.span, "an equivalent index is declared here"),
+            ];
+            return Err(errs);
+        }
+
+        seen.insert(shape, index);
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::codegen;
@@ -304,7 +839,7 @@ mod tests {
     fn assert_valid(src: &str) {
         let schema = &mut parse("test.mecha", src).unwrap();
         if let Err(errs) = schema.check() {
-            codegen::diagnose(src, "test.mecha", errs);
+            codegen::report(src, "test.mecha", &errs);
             panic!("schema validation failed unexpectedly");
         }
         println!("{:?}", serde_json::to_string(schema).unwrap().as_str())
@@ -313,7 +848,7 @@ mod tests {
     fn assert_invalid(src: &str) {
         let schema = &mut parse("test.mecha", src).unwrap();
         if let Err(errs) = schema.check() {
-            codegen::diagnose(src, "test.mecha", errs);
+            codegen::report(src, "test.mecha", &errs);
         } else {
             panic!("schema validation succeeded but should have failed");
         }
@@ -544,4 +1079,315 @@ mod tests {
         ";
         assert_valid(src);
     }
+
+    #[test]
+    fn test_indexes_with_predicate() {
+        let src = r"
+            table foo {
+                id: uuid,
+                deleted_at: timestamp
+                indexes {
+                    unique foo_live_id (id) where deleted_at is null
+                }
+            }
+        ";
+        assert_valid(src);
+    }
+
+    #[test]
+    fn test_indexes_predicate_column_not_exist() {
+        let src = r"
+            table foo {
+                id: uuid
+                indexes {
+                    id where deleted_at is null
+                }
+            }
+        ";
+        assert_invalid(src);
+    }
+
+    #[test]
+    fn test_duplicated_default_index_names() {
+        let src = r"
+            table users {
+                email: text,
+                deleted_at: timestamp
+                indexes {
+                    (email),
+                    (email desc)
+                }
+            }
+        ";
+        let schema = &mut parse("test.mecha", src).unwrap();
+        let errs = schema.check().unwrap_err();
+        assert_eq!(errs[0].code, Some("M0007"));
+        assert_eq!(errs[0].message, "index idx_users_email is redeclared");
+
+        let src = r"
+            table users {
+                email: text,
+                deleted_at: timestamp
+                indexes {
+                    (email),
+                    live_email (email) where deleted_at is null
+                }
+            }
+        ";
+        assert_valid(src);
+    }
+
+    #[test]
+    fn test_duplicated_index_names() {
+        let src = r"
+            table foo {
+                id: uuid
+                indexes {
+                    idx_id (id)
+                }
+            }
+
+            table bar {
+                id: uuid
+                indexes {
+                    idx_id (id)
+                }
+            }
+        ";
+        assert_invalid(src);
+    }
+
+    #[test]
+    fn test_redundant_indexes() {
+        let src = r"
+            table foo {
+                id: uuid,
+                name: string
+                indexes {
+                    (id, name),
+                    idx_foo (id, name asc)
+                }
+            }
+        ";
+        assert_invalid(src);
+
+        let src = r"
+            table foo {
+                id: uuid,
+                name: string
+                indexes {
+                    (id, name),
+                    unique idx_foo (id, name asc)
+                }
+            }
+        ";
+        assert_valid(src);
+    }
+
+    #[test]
+    fn test_indexes_include_column_not_exist() {
+        let src = r"
+            table foo {
+                id: uuid
+                indexes {
+                    id using btree include (name)
+                }
+            }
+        ";
+        assert_invalid(src);
+    }
+
+    #[test]
+    fn test_check_constraints() {
+        let src = r"
+            abstract table period {
+                starts_at: timestamp,
+                ends_at: timestamp,
+                check (starts_at < ends_at or ends_at is null)
+            }
+
+            table products extends period {
+                price: number check (price >= 0 and not price = -1),
+                name: text check (name != '')
+            }
+        ";
+        let schema = &mut parse("test.mecha", src).unwrap();
+        assert!(schema.check().is_ok());
+        assert_eq!(schema.tables[1].checks.len(), 1);
+    }
+
+    #[test]
+    fn test_check_column_not_exist() {
+        let src = r"
+            table products {
+                price: number,
+                check (cost >= 0)
+            }
+        ";
+        assert_invalid(src);
+    }
+
+    #[test]
+    fn test_column_check_uses_other_column() {
+        let src = r"
+            table events {
+                starts_at: timestamp,
+                ends_at: timestamp check (starts_at < ends_at)
+            }
+        ";
+        assert_invalid(src);
+    }
+
+    #[test]
+    fn test_generated_columns() {
+        let src = r"
+            table users {
+                first_name: text,
+                last_name: text,
+                full_name: text generated (first_name || ' ' || last_name) stored,
+                price_cents: number,
+                quantity: number,
+                total_cents: number generated (price_cents * quantity - 1)
+            }
+        ";
+        assert_valid(src);
+    }
+
+    #[test]
+    fn test_generated_column_not_exist() {
+        let src = r"
+            table users {
+                full_name: text generated (first_name || last_name) stored
+            }
+        ";
+        assert_invalid(src);
+    }
+
+    #[test]
+    fn test_generated_primary_key() {
+        let src = r"
+            table users {
+                email: text,
+                id: text primary generated (email) stored
+            }
+        ";
+        assert_invalid(src);
+    }
+
+    #[test]
+    fn test_generated_foreign_key() {
+        let src = r"
+            table users {
+                id: uuid primary (ref => posts.owner_id)
+            }
+
+            table posts {
+                user_id: uuid,
+                owner_id: uuid generated (user_id) stored
+            }
+        ";
+        assert_invalid(src);
+    }
+
+    #[test]
+    fn test_annotations() {
+        let src = r#"
+            @pii
+            table users {
+                @deprecated("use email_v2")
+                email: text,
+                @pii @db.postgres(type = "citext")
+                email_v2: text
+                indexes {
+                    @deprecated email
+                }
+            }
+        "#;
+        let schema = &mut parse("test.mecha", src).unwrap();
+        assert!(schema.check().is_ok());
+        assert!(schema.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_renamed_from() {
+        assert_valid(
+            r#"
+            @renamed_from("people")
+            table users {
+                @renamed_from("mail")
+                email: text
+            }
+        "#,
+        );
+        assert_invalid(
+            r#"
+            @renamed_from("people")
+            table users {
+                email: text
+            }
+
+            table people {
+                email: text
+            }
+        "#,
+        );
+        assert_invalid(
+            r#"
+            table users {
+                @renamed_from("mail")
+                email: text,
+                mail: text
+            }
+        "#,
+        );
+        assert_invalid(
+            r"
+            table users {
+                @renamed_from
+                email: text
+            }
+        ",
+        );
+    }
+
+    #[test]
+    fn test_unknown_annotation_warns() {
+        let src = r#"
+            table users {
+                @owner("growth-team")
+                email: text
+            }
+        "#;
+        let schema = &mut parse("test.mecha", src).unwrap();
+        assert!(schema.check().is_ok());
+        assert_eq!(schema.warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_annotations() {
+        assert_invalid(
+            r"
+            table users {
+                @pii(true)
+                email: text
+            }
+        ",
+        );
+        assert_invalid(
+            r#"
+            @db.postgres(type = "citext")
+            table users {
+                email: text
+            }
+        "#,
+        );
+        assert_invalid(
+            r#"
+            table users {
+                @db.mysql(collation = "utf8mb4_bin")
+                email: text
+            }
+        "#,
+        );
+    }
 }
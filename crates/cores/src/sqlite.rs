@@ -0,0 +1,306 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+/// A table as SQLite reports it through its pragmas
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableInfo {
+    pub name: String,
+    pub columns: Vec<ColumnInfo>,
+    pub indexes: Vec<IndexInfo>,
+    pub foreign_keys: Vec<ForeignKeyInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub typ: String,
+    pub primary_key: bool,
+    pub generated: bool,
+}
+
+/// Indexes SQLite creates for `UNIQUE` and `PRIMARY KEY` constraints have no name of ours
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexInfo {
+    pub name: Option<String>,
+    pub unique: bool,
+    pub partial: bool,
+    /// Whether some of the keys are expressions, they're left out of `columns`
+    pub expression: bool,
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKeyInfo {
+    /// Position in a composite key, a key starts over at 0
+    pub seq: i64,
+    pub column: String,
+    pub referenced_table: String,
+    /// `None` when the referenced table's primary key is implied
+    pub referenced_column: Option<String>,
+}
+
+/// Reads the tables of a database in creation order, SQLite's own tables left out
+pub fn tables(conn: &Connection) -> Result<Vec<TableInfo>> {
+    let mut statement = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+    )?;
+    let names = statement
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+
+    names.into_iter().map(|name| table(conn, name)).collect()
+}
+
+fn table(conn: &Connection, name: String) -> Result<TableInfo> {
+    let context = || format!("unable to read table '{name}'");
+
+    // hidden is 2 for virtual and 3 for stored generated columns
+    let columns = conn
+        .prepare("SELECT name, type, pk, hidden FROM pragma_table_xinfo(?1)")?
+        .query_map([&name], |row| {
+            Ok(ColumnInfo {
+                name: row.get(0)?,
+                // sqlite keeps declared types as written, up to their case
+                typ: row.get::<_, String>(1)?.to_lowercase(),
+                primary_key: row.get::<_, i64>(2)? > 0,
+                generated: row.get::<_, i64>(3)? >= 2,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .with_context(context)?;
+
+    let listed = conn
+        .prepare("SELECT name, \"unique\", origin, partial FROM pragma_index_list(?1)")?
+        .query_map([&name], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, bool>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, bool>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .with_context(context)?;
+
+    let mut indexes = Vec::new();
+    for (index_name, unique, origin, partial) in listed {
+        // an expression key has no name
+        let keys = conn
+            .prepare("SELECT name FROM pragma_index_info(?1) ORDER BY seqno")?
+            .query_map([&index_name], |row| row.get::<_, Option<String>>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .with_context(context)?;
+        indexes.push(IndexInfo {
+            name: (origin == "c").then_some(index_name),
+            unique,
+            partial,
+            expression: keys.iter().any(Option::is_none),
+            columns: keys.into_iter().flatten().collect(),
+        });
+    }
+    // pragma_index_list lists the most recent index first
+    indexes.reverse();
+
+    let foreign_keys = conn
+        .prepare(
+            "SELECT seq, \"from\", \"table\", \"to\" FROM pragma_foreign_key_list(?1) ORDER BY id, seq",
+        )?
+        .query_map([&name], |row| {
+            Ok(ForeignKeyInfo {
+                seq: row.get(0)?,
+                column: row.get(1)?,
+                referenced_table: row.get(2)?,
+                referenced_column: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .with_context(context)?;
+
+    Ok(TableInfo {
+        name,
+        columns,
+        indexes,
+        foreign_keys,
+    })
+}
+
+/// Describes how `actual` differs from `expected`. Column and constraint order is ignored,
+/// `ALTER TABLE ... ADD COLUMN` can only append while a fresh table follows the schema.
+pub fn differences(expected: &[TableInfo], actual: &[TableInfo]) -> Vec<String> {
+    let actual_tables: HashMap<&str, &TableInfo> =
+        actual.iter().map(|t| (t.name.as_str(), t)).collect();
+    let mut differences = Vec::new();
+
+    for table in expected {
+        match actual_tables.get(table.name.as_str()) {
+            Some(actual) => table_differences(table, actual, &mut differences),
+            None => differences.push(format!("table '{}' is missing", table.name)),
+        }
+    }
+
+    for table in actual {
+        if !expected.iter().any(|t| t.name == table.name) {
+            differences.push(format!("table '{}' is not in the schema", table.name));
+        }
+    }
+
+    differences
+}
+
+fn table_differences(expected: &TableInfo, actual: &TableInfo, differences: &mut Vec<String>) {
+    let table = &expected.name;
+
+    for column in &expected.columns {
+        match actual.columns.iter().find(|c| c.name == column.name) {
+            Some(found) if found == column => {}
+            Some(found) => differences.push(format!(
+                "column '{table}.{}' is {}, expected {}",
+                column.name,
+                describe_column(found),
+                describe_column(column)
+            )),
+            None => differences.push(format!("column '{table}.{}' is missing", column.name)),
+        }
+    }
+    for column in &actual.columns {
+        if !expected.columns.iter().any(|c| c.name == column.name) {
+            differences.push(format!(
+                "column '{table}.{}' is not in the schema",
+                column.name
+            ));
+        }
+    }
+
+    for index in &expected.indexes {
+        if !actual.indexes.contains(index) {
+            differences.push(format!("{} is missing", describe_index(table, index)));
+        }
+    }
+    for index in &actual.indexes {
+        if !expected.indexes.contains(index) {
+            differences.push(format!(
+                "{} is not in the schema",
+                describe_index(table, index)
+            ));
+        }
+    }
+
+    for fk in &expected.foreign_keys {
+        if !actual.foreign_keys.contains(fk) {
+            differences.push(format!("{} is missing", describe_foreign_key(table, fk)));
+        }
+    }
+    for fk in &actual.foreign_keys {
+        if !expected.foreign_keys.contains(fk) {
+            differences.push(format!(
+                "{} is not in the schema",
+                describe_foreign_key(table, fk)
+            ));
+        }
+    }
+}
+
+fn describe_column(column: &ColumnInfo) -> String {
+    let mut description = format!("'{}'", column.typ);
+    if column.primary_key {
+        description.push_str(" primary");
+    }
+    if column.generated {
+        description.push_str(" generated");
+    }
+    description
+}
+
+fn describe_index(table: &str, index: &IndexInfo) -> String {
+    let kind = if index.unique {
+        "unique index"
+    } else {
+        "index"
+    };
+    match &index.name {
+        Some(name) => format!("{kind} '{name}' on '{table}'"),
+        None => format!("{kind} on '{table}' ({})", index.columns.join(", ")),
+    }
+}
+
+fn describe_foreign_key(table: &str, fk: &ForeignKeyInfo) -> String {
+    match &fk.referenced_column {
+        Some(column) => format!(
+            "foreign key '{table}.{}' to '{}.{column}'",
+            fk.column, fk.referenced_table
+        ),
+        None => format!(
+            "foreign key '{table}.{}' to '{}'",
+            fk.column, fk.referenced_table
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{differences, tables};
+    use rusqlite::Connection;
+
+    fn database(sql: &str) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(sql).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_tables() {
+        let conn = database(
+            r#"
+            CREATE TABLE "users" ("id" text PRIMARY KEY, "email" text UNIQUE);
+            CREATE TABLE "posts" (
+                "user_id" text,
+                "slug" text GENERATED ALWAYS AS ("user_id" || 'x') STORED,
+                CONSTRAINT "fk_posts_user_id_users" FOREIGN KEY ("user_id") REFERENCES "users" ("id")
+            );
+            CREATE INDEX "idx_posts_user_id" ON "posts" ("user_id") WHERE "user_id" IS NOT NULL;
+            "#,
+        );
+        let tables = tables(&conn).unwrap();
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].name, "users");
+        assert!(tables[0].columns[0].primary_key);
+        assert!(
+            tables[0]
+                .indexes
+                .iter()
+                .any(|i| i.unique && i.name.is_none())
+        );
+
+        let posts = &tables[1];
+        assert!(posts.columns[1].generated);
+        assert_eq!(posts.indexes[0].name.as_deref(), Some("idx_posts_user_id"));
+        assert!(posts.indexes[0].partial);
+        assert_eq!(posts.foreign_keys[0].referenced_table, "users");
+    }
+
+    #[test]
+    fn test_differences() {
+        let expected = database(
+            r#"
+            CREATE TABLE "users" ("id" text PRIMARY KEY, "age" int, "email" text);
+            CREATE INDEX "idx_users_email" ON "users" ("email");
+            "#,
+        );
+        let actual = database(
+            r#"
+            CREATE TABLE "users" ("id" text PRIMARY KEY, "email" text, "age" numeric);
+            CREATE TABLE "legacy" ("id" text);
+            "#,
+        );
+        assert!(differences(&tables(&expected).unwrap(), &tables(&expected).unwrap()).is_empty());
+        assert_eq!(
+            differences(&tables(&expected).unwrap(), &tables(&actual).unwrap()),
+            [
+                "column 'users.age' is 'numeric', expected 'int'",
+                "index 'idx_users_email' on 'users' is missing",
+                "table 'legacy' is not in the schema",
+            ]
+        );
+    }
+}
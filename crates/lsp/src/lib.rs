@@ -1,6 +1,7 @@
-use chumsky::error::Rich;
+use chumsky::span::SimpleSpan;
+use cores::cst::{self, NodeKind, SyntaxNode};
+use cores::diagnostic::Severity;
 use cores::lexer::Token;
-use cores::parser::parse;
 use dashmap::DashMap;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
@@ -22,44 +23,18 @@ impl Backend {
     async fn on_change(&self, uri: Url, text: String) {
         self.document_map.insert(uri.to_string(), text.clone());
 
-        let mut diagnostics = Vec::new();
-        let schema_result = parse(uri.path(), &text);
-
-        let mut handle_errs = |errs: Vec<Rich<Token>>| {
-            for err in errs {
-                let span = err.span();
-                let (start_line, start_col) = byte_index_to_line_col(&text, span.start);
-                let (end_line, end_col) = byte_index_to_line_col(&text, span.end);
-
-                diagnostics.push(Diagnostic {
-                    range: Range {
-                        start: Position {
-                            line: start_line,
-                            character: start_col,
-                        },
-                        end: Position {
-                            line: end_line,
-                            character: end_col,
-                        },
-                    },
-                    severity: Some(DiagnosticSeverity::ERROR),
-                    message: err.to_string(),
-                    source: Some("mecha-lsp".to_string()),
-                    ..Default::default()
-                });
+        let found = match cores::codegen::check(&text, uri.path()) {
+            Ok((schema, mut warnings)) => {
+                let config = cores::rules::Config::new(cores::lint::RULES);
+                warnings.extend(cores::lint::lint(&schema, &config));
+                warnings
             }
+            Err(errs) => errs,
         };
-
-        match schema_result {
-            Ok(mut schema) => {
-                if let Err(errs) = schema.check() {
-                    handle_errs(errs);
-                }
-            }
-            Err(errs) => {
-                handle_errs(errs);
-            }
-        }
+        let diagnostics = found
+            .iter()
+            .map(|diagnostic| to_lsp_diagnostic(&uri, &text, diagnostic))
+            .collect();
 
         self.client
             .publish_diagnostics(uri, diagnostics, None)
@@ -67,6 +42,50 @@ impl Backend {
     }
 }
 
+fn to_lsp_diagnostic(
+    uri: &Url,
+    text: &str,
+    diagnostic: &cores::diagnostic::Diagnostic,
+) -> Diagnostic {
+    let severity = match diagnostic.severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+    };
+
+    // editors show the message alone, notes and help are appended to it
+    let mut message = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        message.push_str(&format!("\nnote: {note}"));
+    }
+    if let Some(help) = &diagnostic.help {
+        message.push_str(&format!("\nhelp: {help}"));
+    }
+
+    let related: Vec<DiagnosticRelatedInformation> = diagnostic
+        .secondary
+        .iter()
+        .map(|label| DiagnosticRelatedInformation {
+            location: Location {
+                uri: uri.clone(),
+                range: span_to_range(text, label.span),
+            },
+            message: label.message.clone(),
+        })
+        .collect();
+
+    Diagnostic {
+        range: span_to_range(text, diagnostic.primary.span),
+        severity: Some(severity),
+        code: diagnostic
+            .code
+            .map(|code| NumberOrString::String(code.to_string())),
+        message,
+        related_information: (!related.is_empty()).then_some(related),
+        source: Some("mecha-lsp".to_string()),
+        ..Default::default()
+    }
+}
+
 fn byte_index_to_line_col(text: &str, index: usize) -> (u32, u32) {
     let mut line = 0;
     let mut col = 0;
@@ -84,6 +103,75 @@ fn byte_index_to_line_col(text: &str, index: usize) -> (u32, u32) {
     (line, col)
 }
 
+fn line_col_to_byte_index(text: &str, position: Position) -> usize {
+    let mut line = 0;
+    let mut col = 0;
+    for (i, c) in text.char_indices() {
+        if line == position.line && col == position.character {
+            return i;
+        }
+        if c == '\n' {
+            if line == position.line {
+                return i;
+            }
+            line += 1;
+            col = 0;
+        } else {
+            col += 1;
+        }
+    }
+    text.len()
+}
+
+fn span_to_range(text: &str, span: SimpleSpan) -> Range {
+    let (start_line, start_col) = byte_index_to_line_col(text, span.start);
+    let (end_line, end_col) = byte_index_to_line_col(text, span.end);
+    Range {
+        start: Position {
+            line: start_line,
+            character: start_col,
+        },
+        end: Position {
+            line: end_line,
+            character: end_col,
+        },
+    }
+}
+
+/// The `///` lines of a table or a column as one text, as the parser reads them
+fn doc(node: &SyntaxNode) -> Option<String> {
+    let lines: Vec<&str> = node
+        .docs()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line).trim_end())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// The tables of a document and their columns, read from its concrete syntax tree so that
+/// they're found while the document doesn't parse
+fn tables<'n, 'a>(root: &'n SyntaxNode<'a>) -> impl Iterator<Item = &'n SyntaxNode<'a>> {
+    root.child_nodes()
+        .filter(|node| node.kind() == NodeKind::Table)
+}
+
+fn columns<'n, 'a>(table: &'n SyntaxNode<'a>) -> impl Iterator<Item = &'n SyntaxNode<'a>> {
+    table
+        .child_nodes()
+        .filter(|node| node.kind() == NodeKind::Column)
+}
+
+fn hover_contents(signature: String, doc: &Option<String>) -> HoverContents {
+    let mut value = format!("```mecha\n{signature}\n```");
+    if let Some(doc) = doc {
+        value.push_str("\n\n");
+        value.push_str(doc);
+    }
+    HoverContents::Markup(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value,
+    })
+}
+
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
     async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
@@ -92,6 +180,7 @@ impl LanguageServer for Backend {
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
                     TextDocumentSyncKind::FULL,
                 )),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
                 semantic_tokens_provider: Some(
                     SemanticTokensServerCapabilities::SemanticTokensOptions(
                         SemanticTokensOptions {
@@ -129,6 +218,54 @@ impl LanguageServer for Backend {
         }
     }
 
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let Some(text) = self.document_map.get(&uri.to_string()) else {
+            return Ok(None);
+        };
+
+        let root = cst::parse(&text);
+        let offset = line_col_to_byte_index(&text, params.text_document_position_params.position);
+        let contains = |span: SimpleSpan| span.start <= offset && offset < span.end;
+
+        for table in tables(&root) {
+            let Some(table_name) = table.name() else {
+                continue;
+            };
+            if contains(table_name.span) {
+                let is_abstract = table.tokens().iter().any(|t| t.token == Token::Abstract);
+                let keyword = if is_abstract {
+                    "abstract table"
+                } else {
+                    "table"
+                };
+                return Ok(Some(Hover {
+                    contents: hover_contents(format!("{keyword} {}", table_name.text), &doc(table)),
+                    range: Some(span_to_range(&text, table_name.span)),
+                }));
+            }
+
+            for column in columns(table) {
+                let mut ids = column.ids();
+                let Some(column_name) = ids.next() else {
+                    continue;
+                };
+                if contains(column_name.span) {
+                    let typ = ids.next().map_or("", |typ| typ.text);
+                    return Ok(Some(Hover {
+                        contents: hover_contents(
+                            format!("{}.{}: {typ}", table_name.text, column_name.text),
+                            &doc(column),
+                        ),
+                        range: Some(span_to_range(&text, column_name.span)),
+                    }));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
     async fn semantic_tokens_full(
         &self,
         params: SemanticTokensParams,
@@ -138,30 +275,31 @@ impl LanguageServer for Backend {
             return Ok(None);
         };
 
-        let Ok(schema) = parse(params.text_document.uri.path(), &text) else {
-            return Ok(None);
-        };
+        let root = cst::parse(&text);
 
         let mut semantic_tokens = Vec::new();
 
         let mut raw_tokens: Vec<(u32, u32, u32, u32)> = Vec::new();
 
-        for table in schema.tables {
-            let span = table.id.span;
+        let mut push = |span: SimpleSpan, token_type: u32| {
             let (line, col) = byte_index_to_line_col(&text, span.start);
             let len = (span.end - span.start) as u32;
-            raw_tokens.push((line, col, len, 0)); // 0 = CLASS
-
-            for column in table.columns {
-                let span = column.id.span;
-                let (line, col) = byte_index_to_line_col(&text, span.start);
-                let len = (span.end - span.start) as u32;
-                raw_tokens.push((line, col, len, 1)); // 1 = PROPERTY
-
-                let span = column.typ.span;
-                let (line, col) = byte_index_to_line_col(&text, span.start);
-                let len = (span.end - span.start) as u32;
-                raw_tokens.push((line, col, len, 2)); // 2 = TYPE
+            raw_tokens.push((line, col, len, token_type));
+        };
+
+        for table in tables(&root) {
+            if let Some(name) = table.name() {
+                push(name.span, 0); // 0 = CLASS
+            }
+
+            for column in columns(table) {
+                let mut ids = column.ids();
+                if let Some(name) = ids.next() {
+                    push(name.span, 1); // 1 = PROPERTY
+                }
+                if let Some(typ) = ids.next() {
+                    push(typ.span, 2); // 2 = TYPE
+                }
             }
         }
 